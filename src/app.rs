@@ -1,67 +1,405 @@
 use std::sync::{Arc, Mutex};
-use std::time::SystemTime;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use std::path::PathBuf;
 use chrono::{Local, Timelike, TimeZone, Utc};
 use crossterm::event::{KeyCode, KeyModifiers};
 use ratatui::Frame;
-use ratatui::layout::{Constraint, Direction, Layout, Rect};
+use ratatui::layout::{Constraint, Direction, Layout, Margin, Rect};
 use ratatui::prelude::{Color, Style};
 use ratatui::widgets::{ListItem, ListState};
 use ratatui::widgets::canvas::Rectangle;
 use textwrap::wrap;
-use crate::log_data::LogData;
-use crate::tui_manager::{TuiManager, TEXT_FG_COLOR};
+use crate::errors;
+use crate::metrics;
+use crate::helpers::{format_count, parse_time_window, percent_decode, preview_captures, safe_ratio, validate_regex, CaptureFields};
+use crate::log_data::{GroupBy, LogData, LogEntry, RecentRequest, TopLimits, LARGE_REQUEST_SIZE, SIZE_BUCKETS};
+use crate::security::{self, SecurityConfig, ThreatFilter, ThreatLevel};
+use crate::tui_manager::{status_color, to_ascii, TuiManager, CHANGED_STYLE, TEXT_FG_COLOR};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FeedColumn {
+    Time,
+    Ip,
+    Method,
+    Status,
+    Size,
+    Url,
+    UserAgent,
+}
+
+impl FeedColumn {
+    /// In display order; F1..F7 toggle them in the Requests tab.
+    pub const ALL: [FeedColumn; 7] = [FeedColumn::Time, FeedColumn::Ip, FeedColumn::Method, FeedColumn::Status, FeedColumn::Size, FeedColumn::Url, FeedColumn::UserAgent];
+    pub const DEFAULT: [FeedColumn; 5] = [FeedColumn::Time, FeedColumn::Ip, FeedColumn::Method, FeedColumn::Status, FeedColumn::Url];
+
+    fn title(&self) -> &'static str {
+        match self {
+            FeedColumn::Time => "Time",
+            FeedColumn::Ip => "IP",
+            FeedColumn::Method => "Method",
+            FeedColumn::Status => "Status",
+            FeedColumn::Size => "Size",
+            FeedColumn::Url => "URL",
+            FeedColumn::UserAgent => "User Agent",
+        }
+    }
+
+    /// Fixed width, or `None` for columns sharing the remaining space.
+    fn width(&self) -> Option<usize> {
+        match self {
+            FeedColumn::Time => Some(19),
+            FeedColumn::Ip => Some(15),
+            FeedColumn::Method => Some(7),
+            FeedColumn::Status => Some(6),
+            FeedColumn::Size => Some(10),
+            FeedColumn::Url | FeedColumn::UserAgent => None,
+        }
+    }
+
+    fn value(&self, request: &RecentRequest) -> String {
+        let optional = |value: Option<String>| value.unwrap_or_else(|| "-".to_string());
+        match self {
+            FeedColumn::Time => Local.timestamp_opt(request.timestamp, 0).single()
+                .map_or_else(|| request.timestamp.to_string(), |dt| dt.format("%Y-%m-%d %H:%M:%S").to_string()),
+            FeedColumn::Ip => request.ip.clone(),
+            FeedColumn::Method => request.request_type.clone(),
+            FeedColumn::Status => optional(request.status.map(|s| s.to_string())),
+            FeedColumn::Size => optional(request.response_size.map(|s| s.to_string())),
+            FeedColumn::Url => request.url.clone(),
+            FeedColumn::UserAgent => optional(request.user_agent.clone()),
+        }
+    }
+}
+
+impl std::str::FromStr for FeedColumn {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "time" => Ok(FeedColumn::Time),
+            "ip" => Ok(FeedColumn::Ip),
+            "method" => Ok(FeedColumn::Method),
+            "status" => Ok(FeedColumn::Status),
+            "size" => Ok(FeedColumn::Size),
+            "url" => Ok(FeedColumn::Url),
+            "ua" => Ok(FeedColumn::UserAgent),
+            _ => Err(format!("unknown column '{}', expected time, ip, method, status, size, url or ua", s)),
+        }
+    }
+}
+
+/// Glyphs and thresholds used to draw intensity bars: the glyph is picked by how many thresholds
+/// the intensity reaches, so `glyphs` always holds one more entry than `thresholds`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IntensityBar {
+    thresholds: Vec<f64>,
+    glyphs: Vec<char>,
+}
+
+impl IntensityBar {
+    pub const DEFAULT_GLYPHS: &'static str = "░▒▓█";
+    pub const ASCII_GLYPHS: &'static str = ".:+#";
+
+    /// `glyphs` may be `ascii`; without explicit thresholds they are spread evenly over 0..1.
+    pub fn parse(glyphs: Option<&str>, thresholds: Option<&str>) -> Result<Self, String> {
+        let glyphs: Vec<char> = match glyphs.map(str::trim) {
+            None => Self::DEFAULT_GLYPHS.chars().collect(),
+            Some("ascii") => Self::ASCII_GLYPHS.chars().collect(),
+            Some(glyphs) => glyphs.chars().collect(),
+        };
+        if glyphs.is_empty() {
+            return Err("at least one bar character is required".to_string());
+        }
+
+        let thresholds: Vec<f64> = match thresholds {
+            Some(spec) => spec.split(',').map(|t| t.trim().parse::<f64>().map_err(|_| format!("invalid threshold '{}'", t.trim()))).collect::<Result<_, _>>()?,
+            None => (1..glyphs.len()).map(|i| i as f64 / glyphs.len() as f64).collect(),
+        };
+        if thresholds.len() + 1 != glyphs.len() {
+            return Err(format!("{} bar characters need {} thresholds, got {}", glyphs.len(), glyphs.len() - 1, thresholds.len()));
+        }
+        if thresholds.iter().any(|t| !(0.0..=1.0).contains(t)) || thresholds.windows(2).any(|w| w[0] >= w[1]) {
+            return Err("thresholds must be increasing values between 0 and 1".to_string());
+        }
+
+        Ok(Self { thresholds, glyphs })
+    }
+
+    /// A bar of `round(intensity * width)` glyphs; `intensity` is clamped to 0..1.
+    pub fn render(&self, intensity: f64, width: usize) -> String {
+        let intensity = if intensity.is_nan() { 0.0 } else { intensity.clamp(0.0, 1.0) };
+        let glyph = self.glyphs[self.thresholds.iter().filter(|&&t| intensity >= t).count()];
+        glyph.to_string().repeat((intensity * width as f64).round() as usize)
+    }
+}
+
+impl Default for IntensityBar {
+    fn default() -> Self {
+        Self::parse(None, None).unwrap()
+    }
+}
 
+const TABS: [&str; 9] = ["Overview", "Requests", "Detailed", "Sparkline", "Heatmap", "Performance", "Security", "Errors", "Subnets"];
 
 pub struct App {
     log_data: Arc<Mutex<LogData>>,
     pub(crate) should_quit: bool,
-    top_n: usize,
+    /// A quit key was pressed and waits for confirmation.
+    quit_requested: bool,
+    /// Ask before quitting; off with `--no-confirm-quit`.
+    confirm_quit: bool,
+    top: TopLimits,
     current_tab: usize,
     last_requests_state: ListState,
     ip_list_state: ListState,
     request_list_state: ListState,
     top_ip_list_state: ListState,
     top_url_list_state: ListState,
+    security_list_state: ListState,
+    suspicious_ip_state: ListState,
+    errors_list_state: ListState,
+    subnet_list_state: ListState,
+    /// Prefix length IPv4 clients are grouped by in the Subnets tab (`--subnet-prefix`).
+    subnet_prefix: u8,
+    /// Seconds in which an IP's first request lists it under New IPs (`--new-ip-window`).
+    new_ip_window: i64,
+    /// Fixed width of the Overview URL column (`--url-width`); fits the terminal when unset.
+    url_width: Option<usize>,
+    /// Log file that does not exist yet and is polled for (`--wait-for-file`).
+    waiting_for: Option<PathBuf>,
+    /// Normal requests per second marked in the Sparkline tab (`--baseline-rps`); the median when unset.
+    baseline_rps: Option<f64>,
+    /// Show URLs percent-decoded in the Overview, Requests and Security tabs (`--decode-urls`).
+    decode_urls: bool,
+    security_config: SecurityConfig,
+    threat_filter: ThreatFilter,
+    /// Status class (5 for 5xx) plotted per minute in the Errors tab (`s`).
+    status_class: u16,
     input: String,
+    feed_columns: Vec<FeedColumn>,
+    intensity_bar: IntensityBar,
+    large_response_threshold: Option<u64>,
+    feed_top_seq: Option<u64>,
+    time_window: Option<i64>,
+    selected_domain: Option<String>,
+    window_input: Option<String>,
+    /// URL the Overview query parameters panel stays on while the selection moves (`f`).
+    pinned_url: Option<String>,
+    regex_input: Option<String>,
+    regex_preview: Option<RegexPreview>,
+    /// First line of the first log, tried by the live regex tester.
+    sample_line: Option<String>,
     current_page: usize,
     total_pages: usize,
     progress: f64,
+    notice: Option<String>,
+    show_parse_errors: bool,
+    /// Alerts overlay (`A`): the `--alert-pattern` counts and the latest matching lines.
+    show_alerts: bool,
+    /// Matching lines already announced, `None` until the initial read is done since its matches
+    /// are not news, and whether the notice is an alert to flash in red.
+    alerts_seen: Option<usize>,
+    alert_notice: bool,
+    /// Suspicious IP whose detection is explained over the Security tab (`e`).
+    explained_ip: Option<String>,
+    dashboard: bool,
+    /// Show "First Seen" and "Last Seen" as "12s ago" instead of a date (`a`).
+    relative_time: bool,
+    /// Merge consecutive identical lines of the Detailed tab into one with a count (`m`).
+    collapse_repeats: bool,
+    /// Show when each IP and URL was first seen in the Overview (`F`).
+    show_first_seen: bool,
+    ip_changes: ChangeTracker,
+    url_changes: ChangeTracker,
+    /// Draw only ASCII characters, for terminals without Unicode glyphs (`--ascii`).
+    ascii: bool,
+    /// Group the digits of counts (`1,234,567`); off with `--no-thousands`.
+    thousands: bool,
     tui_manager: TuiManager,
 }
 
 impl App {
-    pub(crate) fn new(log_data: Arc<Mutex<LogData>>, top_n: usize) -> Self {
+    pub(crate) fn new(log_data: Arc<Mutex<LogData>>, top: TopLimits, security_config: SecurityConfig, feed_columns: Vec<FeedColumn>, intensity_bar: IntensityBar, large_response_threshold: Option<u64>) -> Self {
         Self {
             log_data,
             should_quit: false,
-            top_n,
+            quit_requested: false,
+            confirm_quit: true,
+            top,
             current_tab: 0,
             last_requests_state: ListState::default(),
             ip_list_state: ListState::default(),
             request_list_state: ListState::default(),
             top_ip_list_state: ListState::default(),
             top_url_list_state: ListState::default(),
+            security_list_state: ListState::default(),
+            suspicious_ip_state: ListState::default(),
+            errors_list_state: ListState::default(),
+            subnet_list_state: ListState::default(),
+            subnet_prefix: 24,
+            new_ip_window: 300,
+            url_width: None,
+            waiting_for: None,
+            security_config,
+            threat_filter: ThreatFilter::default(),
+            status_class: 5,
             input: String::new(),
+            feed_columns,
+            intensity_bar,
+            large_response_threshold,
+            feed_top_seq: None,
+            time_window: None,
+            selected_domain: None,
+            window_input: None,
+            pinned_url: None,
+            regex_input: None,
+            regex_preview: None,
+            sample_line: None,
             current_page: 0,
             total_pages: 0,
             progress: 0.0,
+            notice: None,
+            show_parse_errors: false,
+            show_alerts: false,
+            alerts_seen: None,
+            alert_notice: false,
+            explained_ip: None,
+            dashboard: false,
+            relative_time: false,
+            collapse_repeats: false,
+            show_first_seen: false,
+            ip_changes: ChangeTracker::default(),
+            url_changes: ChangeTracker::default(),
+            ascii: false,
+            baseline_rps: None,
+            decode_urls: false,
+            thousands: true,
             tui_manager: TuiManager::new(),
         }
     }
 
+    pub(crate) fn set_sample_line(&mut self, line: Option<String>) {
+        self.sample_line = line;
+    }
+
+    pub(crate) fn set_subnet_prefix(&mut self, prefix: u8) {
+        self.subnet_prefix = prefix;
+    }
+
+    pub(crate) fn set_confirm_quit(&mut self, confirm: bool) {
+        self.confirm_quit = confirm;
+    }
+
+    pub(crate) fn set_thousands(&mut self, thousands: bool) {
+        self.thousands = thousands;
+    }
+
+    pub(crate) fn set_ascii(&mut self, ascii: bool) {
+        self.ascii = ascii;
+    }
+
+    pub(crate) fn set_new_ip_window(&mut self, seconds: i64) {
+        self.new_ip_window = seconds;
+    }
+
+    pub(crate) fn set_url_width(&mut self, width: Option<usize>) {
+        self.url_width = width;
+    }
+
+    pub(crate) fn set_baseline_rps(&mut self, baseline_rps: Option<f64>) {
+        self.baseline_rps = baseline_rps;
+    }
+
+    pub(crate) fn set_decode_urls(&mut self, decode_urls: bool) {
+        self.decode_urls = decode_urls;
+    }
+
+    pub(crate) fn set_waiting_for(&mut self, file_path: Option<PathBuf>) {
+        self.waiting_for = file_path;
+    }
+
     pub(crate) fn set_progress(&mut self, progress: f64) {
         self.progress = progress.clamp(0.0, 100.0);
     }
 
     pub(crate) fn handle_input(&mut self, key: KeyCode, modifiers: KeyModifiers) {
+        if self.quit_requested {
+            self.quit_requested = false;
+            let is_quit_key = matches!(key, KeyCode::Char('c' | 'q')) && modifiers.contains(KeyModifiers::CONTROL);
+            self.should_quit = is_quit_key || matches!(key, KeyCode::Char('y' | 'Y') | KeyCode::Enter);
+            return;
+        }
+        if let Some(input) = self.window_input.as_mut() {
+            match key {
+                KeyCode::Enter => self.apply_window_input(),
+                KeyCode::Esc => self.window_input = None,
+                KeyCode::Backspace => {
+                    input.pop();
+                }
+                KeyCode::Char(c) if is_window_char(c) => input.push(c),
+                _ => {}
+            }
+            return;
+        }
+        if let Some(input) = self.regex_input.as_mut() {
+            match key {
+                KeyCode::Enter => self.apply_regex_input(),
+                KeyCode::Esc => self.regex_input = None,
+                KeyCode::Backspace => {
+                    input.pop();
+                }
+                KeyCode::Char(c) => input.push(c),
+                _ => {}
+            }
+            return;
+        }
+        if self.regex_preview.is_some() && matches!(key, KeyCode::Esc | KeyCode::Char('r')) {
+            self.regex_preview = None;
+            return;
+        }
+        self.notice = None;
+        self.alert_notice = false;
+
         match key {
             KeyCode::Tab | KeyCode::Char('t') => self.toggle_tab(),
+            KeyCode::Char('y') if modifiers.contains(KeyModifiers::CONTROL) => self.copy_visible_table(),
+            KeyCode::Delete => self.remove_selected_ip(),
+            KeyCode::Char('u') if modifiers.contains(KeyModifiers::CONTROL) => self.copy_selected_as_curl(),
             KeyCode::Char('c') if modifiers.contains(KeyModifiers::CONTROL) => self.quit(),
             KeyCode::Up => self.on_up(),
             KeyCode::Down => self.on_down(),
+            KeyCode::Home => self.on_home(),
+            KeyCode::End => self.on_end(),
+            KeyCode::Char('g') if self.current_tab != 1 => self.on_home(),
+            KeyCode::Char('G') if self.current_tab != 1 => self.on_end(),
             KeyCode::Left => self.on_left(),
             KeyCode::Right => self.on_right(),
             KeyCode::Char('q') if modifiers.contains(KeyModifiers::CONTROL) => self.quit(),
+            KeyCode::Char('w') if self.current_tab != 1 => self.window_input = Some(String::new()),
+            KeyCode::Char('d') if self.current_tab != 1 => self.cycle_domain(),
+            KeyCode::Char('D') if self.current_tab != 1 => self.dashboard = !self.dashboard,
+            KeyCode::Char('F') if self.current_tab != 1 => self.show_first_seen = !self.show_first_seen,
+            KeyCode::Char('a') if self.current_tab != 1 => self.relative_time = !self.relative_time,
+            KeyCode::Char('p') if self.current_tab != 1 => self.show_parse_errors = !self.show_parse_errors,
+            KeyCode::Char('A') if self.current_tab != 1 => self.show_alerts = !self.show_alerts,
+            KeyCode::Esc => {
+                self.show_parse_errors = false;
+                self.show_alerts = false;
+                self.explained_ip = None;
+            }
+            KeyCode::Char('r') if self.current_tab != 1 => self.regex_input = Some(String::new()),
+            KeyCode::Char('b') if self.current_tab != 1 => {
+                self.log_data.lock().unwrap().toggle_exclude_bots();
+            }
+            KeyCode::Char('f') if self.current_tab == 0 => self.toggle_pinned_url(),
+            KeyCode::Char('m') if self.current_tab == 2 => self.collapse_repeats = !self.collapse_repeats,
+            KeyCode::Char('f') if self.current_tab == 6 => self.threat_filter = self.threat_filter.next(),
+            KeyCode::Char('c') if self.current_tab == 6 => self.copy_ip_report(),
+            KeyCode::Char('e') if self.current_tab == 6 => self.toggle_explanation(),
+            KeyCode::Char('S') if self.current_tab != 1 => self.copy_session_summary(),
+            KeyCode::Char('s') if self.current_tab == 7 => self.status_class = self.status_class % 5 + 1,
+            KeyCode::F(n) if self.current_tab == 1 => self.toggle_feed_column(n),
             KeyCode::Backspace => {
                 self.last_requests_state.select(None);
                 self.input.pop();
@@ -75,6 +413,14 @@ impl App {
     }
 
     pub(crate) fn draw(&mut self, frame: &mut Frame) {
+        self.draw_screen(frame);
+        if self.ascii {
+            to_ascii(frame.buffer_mut());
+        }
+    }
+
+    fn draw_screen(&mut self, frame: &mut Frame) {
+        self.check_alerts();
         let size = frame.size();
         let chunks = Layout::default()
             .direction(Direction::Vertical)
@@ -91,158 +437,784 @@ impl App {
             .split(chunks[0]);
 
         frame.render_widget(self.tui_manager.draw_tabs(
-            vec!["Overview".into(), "Requests".into(), "Detailed".into(), "Sparkline".into(), "Heatmap".into()],
+            TABS.iter().map(|&tab| tab.into()).collect(),
             self.current_tab,
             "Tabs"
         ), header_chunks[0]);
 
-        frame.render_widget(self.tui_manager.draw_summary(
-            &self.get_summary_text()
-        ), header_chunks[1]);
+        if self.quit_requested {
+            frame.render_widget(self.tui_manager.draw_prompt("Quit? y/Enter to quit, any other key to stay", "", false), header_chunks[1]);
+        } else if let Some(input) = &self.window_input {
+            frame.render_widget(self.tui_manager.draw_prompt("Time window (15m, 1h, 2d, empty for all) — Enter to apply, Esc to cancel", input, is_valid_window(input)), header_chunks[1]);
+        } else if let Some(input) = &self.regex_input {
+            frame.render_widget(self.tui_manager.draw_prompt("Regex to try on the selected IP's lines — Enter to preview, Esc to cancel", input, regex::Regex::new(input).is_ok()), header_chunks[1]);
+        } else if let Some(notice) = &self.notice {
+            let style = if self.alert_notice { Style::default().fg(Color::Red) } else { Style::default() };
+            frame.render_widget(self.tui_manager.draw_summary(notice, self.get_summary_title()).style(style), header_chunks[1]);
+        } else {
+            frame.render_widget(self.tui_manager.draw_summary(
+                &self.get_summary_text(),
+                self.get_summary_title()
+            ), header_chunks[1]);
+        }
 
         frame.render_widget(self.tui_manager.draw_progress_bar(self.progress), header_chunks[2]);
 
+        if self.show_parse_errors {
+            self.draw_parse_errors(frame, chunks[1]);
+            return;
+        }
+
+        if self.show_alerts {
+            self.draw_alerts(frame, chunks[1]);
+            return;
+        }
+
+        if self.explained_ip.is_some() && self.current_tab == 6 {
+            self.draw_explanation(frame, chunks[1]);
+            return;
+        }
+
+        if self.regex_preview.is_some() {
+            self.draw_regex_preview(frame, chunks[1]);
+            return;
+        }
+
+        if self.regex_input.is_some() {
+            self.draw_regex_tester(frame, chunks[1]);
+            return;
+        }
+
+        if let Some(file_path) = &self.waiting_for {
+            self.tui_manager.draw_message(&format!("Waiting for {} to appear...", file_path.display()), frame, chunks[1]);
+            return;
+        }
+
+        if let Some(message) = self.get_empty_state_message() {
+            self.tui_manager.draw_message(message, frame, chunks[1]);
+            return;
+        }
+
+        if self.dashboard {
+            self.draw_dashboard(frame, chunks[1]);
+            return;
+        }
+
         match self.current_tab {
             0 => self.draw_overview(frame, chunks[1]),
             1 => self.draw_last_requests(frame, chunks[1]),
             2 => self.draw_detailed_requests(frame, chunks[1]),
             3 => self.draw_requests_sparkline(frame, chunks[1]),
             4 => self.draw_heatmap(frame, chunks[1]),
+            5 => self.draw_performance(frame, chunks[1]),
+            6 => self.draw_security(frame, chunks[1]),
+            7 => self.draw_errors(frame, chunks[1]),
+            8 => self.draw_subnets(frame, chunks[1]),
             _ => {}
         }
     }
 
-    fn get_summary_text(&self) -> String {
-        let log_data = self.log_data.lock().unwrap();
-        let (unique_ips, unique_urls) = log_data.get_unique_counts();
-        let now = Local::now();
-        format!(
-            "Requests: {} | Unique IPs: {} | Unique URLs: {} | Update: {}",
-            log_data.total_requests, unique_ips, unique_urls, now.format("%Y-%m-%d %H:%M:%S")
-        )
+    /// Steps the Overview through the domains of a multi-vhost log, then back to all domains.
+    fn cycle_domain(&mut self) {
+        let domains = self.log_data.lock().unwrap().get_domains();
+        if domains.len() < 2 {
+            self.selected_domain = None;
+            return;
+        }
+        let next = match &self.selected_domain {
+            Some(current) => domains.iter().position(|(domain, _)| domain == current).map_or(0, |index| index + 1),
+            None => 0,
+        };
+        self.selected_domain = domains.get(next).map(|(domain, _)| domain.clone());
+        self.top_ip_list_state.select(None);
+        self.top_url_list_state.select(None);
+        self.current_tab = 0;
     }
 
-    fn draw_overview(&mut self, frame: &mut Frame, area: Rect) {
-        let log_data = self.log_data.lock().unwrap();
-        let (top_ips, top_urls) = log_data.get_top_n(self.top_n);
-
-        let chunks = Layout::default()
-            .direction(Direction::Vertical)
-            .constraints([Constraint::Length(0), Constraint::Min(0)].as_ref())
-            .split(area);
+    /// IP selected in the Overview or Detailed tab.
+    fn selected_ip(&self, log_data: &LogData) -> Option<String> {
+        match self.current_tab {
+            0 if self.selected_domain.is_some() => {
+                let domain = self.selected_domain.as_deref().unwrap_or_default();
+                self.top_ip_list_state.selected().and_then(|i| log_data.get_top_n_for_domain(domain, self.top).0.get(i).map(|(ip, _)| ip.clone()))
+            }
+            0 => {
+                let range = self.get_time_range(log_data);
+                self.top_ip_list_state.selected().and_then(|i| log_data.get_top_n_in_range(self.top, range).0.get(i).map(|(ip, _, _)| ip.clone()))
+            }
+            2 => self.ip_list_state.selected().and_then(|i| log_data.get_top_n(self.top).0.get(i).map(|(ip, _)| ip.clone())),
+            _ => None,
+        }
+    }
 
-        let chunks = Layout::default()
-            .direction(Direction::Horizontal)
-            .constraints([Constraint::Percentage(30), Constraint::Percentage(70)].as_ref())
-            .split(chunks[1]);
+    fn remove_selected_ip(&mut self) {
+        let mut log_data = self.log_data.lock().unwrap();
+        if let Some(ip) = self.selected_ip(&log_data) {
+            if log_data.remove_ip(&ip) {
+                self.request_list_state.select(None);
+                self.notice = Some(format!("Removed {} from the analysis", ip));
+            }
+        }
+    }
 
+    fn copy_selected_as_curl(&mut self) {
+        let command = {
+            let log_data = self.log_data.lock().unwrap();
+            match (self.current_tab, self.last_requests_state.selected()) {
+                (1, Some(selected)) => self.get_search_results(&log_data).get(self.current_page * 100 + selected).map(|request| curl_command(request)),
+                _ => None,
+            }
+        };
+        self.notice = Some(match command.map(|command| self.tui_manager.copy_to_clipboard(&command)) {
+            Some(Ok(())) => "Copied the selected request as a curl command".to_string(),
+            Some(Err(e)) => format!("Copy failed: {}", e),
+            None => "Select a request in the Requests tab to copy it as curl".to_string(),
+        });
+    }
 
-        // Top IPs
-        let ip_items: Vec<ListItem> = top_ips.iter().map(|(ip, entry)| {
-            let last_update = entry.last_update.duration_since(SystemTime::UNIX_EPOCH).unwrap().as_secs();
-            let last_update_str = format!("{}", Local.timestamp_opt(last_update as i64, 0).unwrap().format("%Y-%m-%d %H:%M:%S"));
-            ListItem::new(format!("{:<15} | {:<8} | {}", ip, entry.count, last_update_str))
-        }).collect();
+    /// Locks the Overview query parameters panel to the selected URL, or releases it.
+    fn toggle_pinned_url(&mut self) {
+        if self.pinned_url.take().is_some() {
+            return;
+        }
+        let log_data = self.log_data.lock().unwrap();
+        let range = self.get_time_range(&log_data);
+        self.pinned_url = self.top_url_list_state.selected()
+            .and_then(|i| log_data.get_top_n_in_range(self.top, range).1.get(i).map(|(url, _, _)| url.clone()));
+        if self.pinned_url.is_none() {
+            self.notice = Some("Select a URL in the Overview to pin it".to_string());
+        }
+    }
 
+    /// Copies the selected suspicious IP with its threat level and the patterns it matched.
+    fn copy_ip_report(&mut self) {
+        let report = {
+            let log_data = self.log_data.lock().unwrap();
+            self.suspicious_ip_state.selected()
+                .and_then(|selected| self.visible_suspicious_ips(&log_data).into_iter().nth(selected))
+                .map(|(ip, count, level)| security::format_ip_report(&ip, count, level, &security::ip_patterns(&log_data, &self.security_config, &ip)))
+        };
+        self.notice = Some(match report.map(|report| self.tui_manager.copy_to_clipboard(&report)) {
+            Some(Ok(())) => "Copied the selected IP and its patterns".to_string(),
+            Some(Err(e)) => format!("Copy failed: {}", e),
+            None => "Select a suspicious IP in the Security tab to copy its report".to_string(),
+        });
+    }
 
-        frame.render_stateful_widget(self.tui_manager.draw_list(ip_items.clone(), format!("{:<15} | {:<8} | {}", "Top IPs", "Requests", "Last Update").to_string()), chunks[0], &mut self.top_ip_list_state);
+    /// Opens the explanation of the selected suspicious IP, or closes the open one.
+    fn toggle_explanation(&mut self) {
+        if self.explained_ip.take().is_some() {
+            return;
+        }
+        let log_data = self.log_data.lock().unwrap();
+        self.explained_ip = self.suspicious_ip_state.selected()
+            .and_then(|selected| self.visible_suspicious_ips(&log_data).into_iter().nth(selected))
+            .map(|(ip, _, _)| ip);
+        if self.explained_ip.is_none() {
+            self.notice = Some("Select a suspicious IP in the Security tab to explain it".to_string());
+        }
+    }
 
-        self.tui_manager.draw_scrollbar(ip_items.len(), self.top_ip_list_state.selected().unwrap_or(0), frame, chunks[0]);
+    /// A URL as shown in the lists: percent-decoded with `--decode-urls`, as logged otherwise.
+    fn display_url(&self, url: &str) -> String {
+        if self.decode_urls { percent_decode(url) } else { url.to_string() }
+    }
 
-        // Top URLs
-        let url_items: Vec<ListItem> = top_urls.iter().map(|(url, entry)| {
-            let last_update = entry.last_update.duration_since(SystemTime::UNIX_EPOCH).unwrap().as_secs();
-            let last_update_str = format!("{}", Local.timestamp_opt(last_update as i64, 0).unwrap().format("%Y-%m-%d %H:%M:%S"));
-            ListItem::new(format!("{:<50} | {:<20} | {:<6} | {:<8} | {}", url, entry.request_type, entry.request_domain, entry.count, last_update_str))
-        }).collect();
+    /// Suspicious IPs shown in the Security tab under the current threat filter.
+    fn visible_suspicious_ips(&self, log_data: &LogData) -> Vec<(String, usize, ThreatLevel)> {
+        security::find_suspicious_ips(log_data, &self.security_config)
+            .into_iter()
+            .map(|(ip, count)| (ip, count, security::get_threat_level(count)))
+            .filter(|(_, _, level)| self.threat_filter.allows(*level))
+            .take(self.top.ips)
+            .collect()
+    }
 
-        frame.render_stateful_widget(self.tui_manager.draw_list(url_items.clone(), format!("{:<50} | {:<20} | {:<6} | {:<8} | {}",  "Top URLs", "Type", "Domain", "Requests", "Last Update").to_string()), chunks[1], &mut self.top_url_list_state);
+    /// Copies the plain-text summary of the whole session, for pasting into an incident ticket.
+    fn copy_session_summary(&mut self) {
+        let summary = {
+            let log_data = self.log_data.lock().unwrap();
+            let suspicious = security::find_suspicious_ips(&log_data, &self.security_config);
+            metrics::format_session_summary(&log_data, &suspicious, self.thousands)
+        };
+        self.notice = Some(match self.tui_manager.copy_to_clipboard(&summary) {
+            Ok(()) => "Copied the session summary".to_string(),
+            Err(e) => format!("Copy failed: {}", e),
+        });
+    }
 
-        self.tui_manager.draw_scrollbar(url_items.len(), self.top_url_list_state.selected().unwrap_or(0), frame, chunks[1]);
+    fn copy_visible_table(&mut self) {
+        let rows = self.visible_table();
+        self.notice = Some(match self.tui_manager.copy_to_clipboard(&to_tsv(&rows)) {
+            Ok(()) => format!("Copied {} rows as TSV", rows.len().saturating_sub(1)),
+            Err(e) => format!("Copy failed: {}", e),
+        });
     }
 
+    /// The current tab's table as a header row followed by its data rows, without truncation.
+    fn visible_table(&self) -> Vec<Vec<String>> {
+        let log_data = self.log_data.lock().unwrap();
+        let row = |cells: &[&dyn ToString]| cells.iter().map(|cell| cell.to_string()).collect::<Vec<_>>();
+        let mut rows = Vec::new();
+        match self.current_tab {
+            0 => {
+                let (top_ips, top_urls) = log_data.get_top_n_in_range(self.top, self.get_time_range(&log_data));
+                rows.push(row(&[&"Kind", &"Value", &"Requests"]));
+                rows.extend(top_ips.iter().map(|(ip, count, _)| row(&[&"ip", ip, count])));
+                rows.extend(top_urls.iter().map(|(url, count, _)| row(&[&"url", url, count])));
+            }
+            1 => {
+                let start = self.current_page * 100;
+                let page = self.get_search_results(&log_data).into_iter().skip(start).take(100);
+                if self.feed_columns.is_empty() {
+                    rows.push(vec!["Log line".to_string()]);
+                    rows.extend(page.map(|request| vec![request.log_line.trim_end().to_string()]));
+                } else {
+                    rows.push(self.feed_columns.iter().map(|column| column.title().to_string()).collect());
+                    rows.extend(page.map(|request| self.feed_columns.iter().map(|column| column.value(request)).collect()));
+                }
+            }
+            2 => {
+                rows.push(row(&[&"IP", &"Requests"]));
+                rows.extend(log_data.get_top_n(self.top).0.iter().map(|(ip, entry)| row(&[ip, &entry.count])));
+            }
+            3 | 4 => {
+                let mut intervals: Vec<_> = log_data.requests_per_interval.iter().collect();
+                intervals.sort();
+                rows.push(row(&[&"Timestamp", &"Requests"]));
+                rows.extend(intervals.iter().map(|(timestamp, count)| row(&[timestamp, count])));
+            }
+            5 => {
+                rows.push(row(&[&"Size", &"Responses"]));
+                rows.extend(SIZE_BUCKETS.iter().zip(log_data.get_response_size_histogram()).map(|(&(label, _), count)| row(&[&label, &count])));
+            }
+            6 => {
+                rows.push(row(&[&"URL", &"Requests", &"Top IPs"]));
+                rows.extend(security::find_sensitive_file_probes(&log_data, &self.security_config, self.top.other).iter().map(|finding| {
+                    let sources = finding.sources.iter().map(|(ip, count)| format!("{} ({})", ip, count)).collect::<Vec<_>>().join(", ");
+                    row(&[&finding.target, &finding.count, &sources])
+                }));
+            }
+            7 => {
+                rows.push(row(&[&"URL", &"Baseline 5xx", &"Recent 5xx"]));
+                rows.extend(errors::find_failing_urls(&log_data, errors::RECENT_WINDOW, errors::FAILURE_JUMP).iter().take(self.top.errors)
+                    .map(|url| row(&[&url.url, &url.baseline_ratio, &url.recent_ratio])));
+            }
+            8 => {
+                rows.push(row(&[&"Subnet", &"Requests", &"IPs"]));
+                rows.extend(log_data.get_top_subnets(self.subnet_prefix, self.top.other).iter().map(|(subnet, requests, ips)| row(&[subnet, requests, ips])));
+            }
+            _ => {}
+        }
+        rows
+    }
 
+    fn toggle_feed_column(&mut self, key: u8) {
+        let column = match FeedColumn::ALL.get((key as usize).wrapping_sub(1)) {
+            Some(&column) => column,
+            None => return,
+        };
+        if let Some(index) = self.feed_columns.iter().position(|&c| c == column) {
+            self.feed_columns.remove(index);
+        } else {
+            self.feed_columns.push(column);
+            self.feed_columns.sort_by_key(|c| FeedColumn::ALL.iter().position(|a| a == c));
+        }
+    }
 
-    fn draw_last_requests(&mut self, frame: &mut Frame, area: Rect) {
-        let items: Vec<ListItem>;
-        let total_pages: usize;
+    /// Applies the entered window; an invalid one keeps the prompt open (shown in red) instead of being ignored.
+    fn apply_window_input(&mut self) {
+        let Some(input) = self.window_input.as_deref().map(str::trim) else {
+            return;
+        };
+        if !is_valid_window(input) {
+            return;
+        }
+        self.time_window = parse_time_window(input);
+        self.window_input = None;
+    }
 
-        {
-            let log_data = self.log_data.lock().unwrap();
-            let search_results = self.get_search_results(&log_data);
+    /// Previews the entered regex on the stored lines of the selected IP; the analysis is left as it is.
+    fn apply_regex_input(&mut self) {
+        let Some(pattern) = self.regex_input.take() else {
+            return;
+        };
+        let log_data = self.log_data.lock().unwrap();
+        let Some(ip) = self.selected_ip(&log_data) else {
+            self.notice = Some("Select an IP in the Overview or Detailed tab to try a regex on its lines".to_string());
+            return;
+        };
+        let lines = log_data.get_last_requests(&ip);
+        drop(log_data);
+        if lines.iter().all(|line| line.is_empty()) {
+            self.notice = Some(format!("No raw lines are stored for {} (--no-raw-lines)", ip));
+            return;
+        }
+        let result = preview_captures(&pattern, &lines).map(|fields| lines.into_iter().zip(fields).collect());
+        self.regex_preview = Some(RegexPreview { ip, pattern, result });
+    }
 
-            total_pages = (search_results.len() + 99) / 100;
-            let start = self.current_page * 100;
-            let end = (start + 100).min(search_results.len());
+    fn get_time_range(&self, log_data: &LogData) -> Option<(i64, i64)> {
+        let window = self.time_window?;
+        let latest = log_data.get_latest_timestamp()?;
+        Some((latest - window, latest))
+    }
 
-            items = search_results[start..end]
-                .iter()
-                .map(|request| {
-                    let wrapped_text = wrap(request, (area.width as f64 * 0.7) as usize - 5);
-                    ListItem::new(wrapped_text.join("\n")).style(Style::default().fg(TEXT_FG_COLOR))
-                })
-                .collect();
+    fn get_window_label(&self) -> String {
+        match self.time_window {
+            Some(window) if window % 86400 == 0 => format!(" (last {}d)", window / 86400),
+            Some(window) if window % 3600 == 0 => format!(" (last {}h)", window / 3600),
+            Some(window) if window % 60 == 0 => format!(" (last {}m)", window / 60),
+            Some(window) => format!(" (last {}s)", window),
+            None => String::new(),
         }
+    }
 
-        self.total_pages = total_pages;
-        let pages: Vec<String> = (1..=self.total_pages).map(|i| format!("{}", i)).collect();
+    fn get_empty_state_message(&self) -> Option<&'static str> {
+        if self.log_data.lock().unwrap().total_requests > 0 {
+            return None;
+        }
+        if self.progress >= 1.0 {
+            Some("No matching log entries — check your --regex/--date-format (p: parse errors)")
+        } else {
+            Some("Waiting for log entries...")
+        }
+    }
 
-        let chunks = Layout::default()
+    /// Small summaries of the other tabs on one screen, most important first; tiles that do not fit are dropped.
+    fn draw_dashboard(&mut self, frame: &mut Frame, area: Rect) {
+        let (columns, tiles) = dashboard_grid(area.width, area.height);
+        if tiles == 0 {
+            self.tui_manager.draw_message("Terminal too small for the dashboard (D to leave)", frame, area);
+            return;
+        }
+        let rows = Layout::default()
             .direction(Direction::Vertical)
-            .constraints([Constraint::Length(3), Constraint::Min(0)].as_ref())
+            .constraints(vec![Constraint::Ratio(1, tiles.div_ceil(columns) as u32); tiles.div_ceil(columns)])
             .split(area);
+        let cells: Vec<Rect> = rows.iter()
+            .flat_map(|row| Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints(vec![Constraint::Ratio(1, columns as u32); columns])
+                .split(*row)
+                .to_vec())
+            .take(tiles)
+            .collect();
 
-        let header_chunks = Layout::default()
-            .direction(Direction::Horizontal)
-            .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
-            .split(chunks[0]);
+        let log_data = self.log_data.lock().unwrap();
+        let (top_ips, top_urls) = log_data.get_top_n_in_range(TopLimits::uniform(DASHBOARD_TOP), self.get_time_range(&log_data));
+        let ranked_items = |entries: &[(String, usize, &LogEntry)]| -> Vec<ListItem> {
+            entries.iter().map(|(key, count, _)| ListItem::new(format!("{:<8} {}", self.count(*count), key))).collect()
+        };
+        let mut widgets = vec![
+            self.tui_manager.draw_list(ranked_items(&top_ips), format!("Top {} IPs{} (D: leave dashboard)", DASHBOARD_TOP, self.get_window_label())),
+            self.tui_manager.draw_list(ranked_items(&top_urls), format!("Top {} URLs", DASHBOARD_TOP)),
+        ];
+
+        let classes = log_data.get_status_classes();
+        let status_items: Vec<ListItem> = classes.iter().enumerate().skip(1)
+            .map(|(index, &count)| {
+                let class = (index as u16 + 1) * 100;
+                ListItem::new(format!("{}xx {:>10}", index + 1, self.count(count))).style(Style::default().fg(status_color(Some(class))))
+            })
+            .collect();
+        widgets.push(self.tui_manager.draw_list(status_items, "Status classes".to_string()));
+
+        let suspicious = security::find_suspicious_ips(&log_data, &self.security_config);
+        let high = suspicious.iter().filter(|(_, count)| security::get_threat_level(*count) == ThreatLevel::High).count();
+        let suspicious_items = vec![
+            ListItem::new(format!("Suspicious IPs {:>8}", self.count(suspicious.len()))).style(Style::default().fg(Color::Yellow)),
+            ListItem::new(format!("High threat    {:>8}", self.count(high))).style(Style::default().fg(Color::Red)),
+        ];
+        let rps = log_data.get_requests_per_second(60);
+        let mut per_second: Vec<(i64, u64)> = log_data.requests_per_interval.iter().map(|(&timestamp, &count)| (timestamp, count as u64)).collect();
+        drop(log_data);
+        per_second.sort_by_key(|&(timestamp, _)| std::cmp::Reverse(timestamp));
+        let sparkline_data: Vec<u64> = per_second.iter().take(cells.get(3).map_or(0, |cell| cell.width as usize)).map(|&(_, count)| count).collect();
+        let sparkline_title = format!("Requests/s (last minute avg {:.1})", rps);
+
+        for (index, (widget, cell)) in widgets.into_iter().zip(&cells).enumerate() {
+            match index {
+                0 => frame.render_stateful_widget(widget, *cell, &mut self.top_ip_list_state),
+                1 => frame.render_stateful_widget(widget, *cell, &mut self.top_url_list_state),
+                _ => frame.render_widget(widget, *cell),
+            }
+        }
+        if let Some(cell) = cells.get(3) {
+            frame.render_widget(self.tui_manager.draw_sparkline(&sparkline_data, &sparkline_title), *cell);
+        }
+        if let Some(cell) = cells.get(4) {
+            frame.render_widget(self.tui_manager.draw_list(suspicious_items, "Security".to_string()), *cell);
+        }
+    }
 
+    fn draw_regex_preview(&mut self, frame: &mut Frame, area: Rect) {
+        let Some(preview) = &self.regex_preview else {
+            return;
+        };
+        let items = preview_items(&preview.result);
+        let title = format!("Regex preview for {} (read-only, r/Esc to close): {}", preview.ip, preview.pattern);
+        frame.render_widget(self.tui_manager.draw_list(items, title), area);
+    }
 
-        frame.render_widget(self.tui_manager.draw_input(&self.input), header_chunks[0]);
-        frame.render_widget(self.tui_manager.draw_pagination(pages.clone(), self.current_page), header_chunks[1]);
+    /// Live result of the regex being typed: whether it is usable for parsing, and what it captures from
+    /// the first line of the log and the selected IP's lines.
+    fn draw_regex_tester(&mut self, frame: &mut Frame, area: Rect) {
+        let pattern = self.regex_input.clone().unwrap_or_default();
+        let mut lines: Vec<String> = self.sample_line.iter().cloned().collect();
+        {
+            let log_data = self.log_data.lock().unwrap();
+            if let Some(ip) = self.selected_ip(&log_data) {
+                lines.extend(log_data.get_last_requests(&ip).into_iter().filter(|line| !line.is_empty()));
+            }
+        }
 
-        frame.render_stateful_widget(self.tui_manager.draw_list(items.clone(), "".to_string()), chunks[1], &mut self.last_requests_state);
-        self.tui_manager.draw_scrollbar(items.len(), self.last_requests_state.selected().unwrap_or(0), frame, chunks[1]);
+        let mut items = vec![match validate_regex(&pattern) {
+            Ok(()) => ListItem::new("Usable as --regex").style(Style::default().fg(Color::Green)),
+            Err(error) => ListItem::new(format!("Not usable as --regex: {}", error)).style(Style::default().fg(Color::Yellow)),
+        }];
+        let result = preview_captures(&pattern, &lines).map(|fields| lines.into_iter().zip(fields).collect());
+        items.extend(preview_items(&result));
+        frame.render_widget(self.tui_manager.draw_list(items, "Regex tester (first log line and the selected IP's lines)".to_string()), area);
     }
 
-    fn get_search_results<'a>(&self, log_data: &'a LogData) -> Vec<&'a String> {
-        if !self.input.is_empty() {
-            log_data
-                .by_ip
-                .iter()
-                .flat_map(|(_, entry)| &entry.last_requests)
-                .filter(|request| request.contains(&self.input))
-                .collect()
+    fn draw_parse_errors(&mut self, frame: &mut Frame, area: Rect) {
+        let log_data = self.log_data.lock().unwrap();
+        let (failures, total) = log_data.get_parse_failures();
+        let items: Vec<ListItem> = if failures.is_empty() {
+            vec![ListItem::new("Every line parsed")]
         } else {
-            log_data
-                .by_ip
-                .values()
-                .flat_map(|entry| &entry.last_requests)
+            failures.iter()
+                .map(|failure| ListItem::new(format!("{}\n  {}", failure.reason, failure.line)).style(Style::default().fg(Color::Red)))
                 .collect()
+        };
+        let title = format!("Parse Errors (first {} of {} failed lines, p/Esc to close)", failures.len(), self.count(total));
+        frame.render_widget(self.tui_manager.draw_list(items, title), area);
+    }
+
+    /// Takes the lines matched while reading the existing log as seen, so only lines arriving
+    /// while tailing are announced.
+    pub(crate) fn finish_initial_read(&mut self) {
+        self.alerts_seen = Some(self.log_data.lock().unwrap().get_alert_count());
+    }
+
+    /// Announces lines that matched an `--alert-pattern` since the last check.
+    fn check_alerts(&mut self) {
+        let Some(seen) = self.alerts_seen else { return };
+        let count = self.log_data.lock().unwrap().get_alert_count();
+        if count > seen {
+            self.notice = Some(format!("Alert: {} new line(s) matched --alert-pattern (A: show alerts)", self.count(count - seen)));
+            self.alert_notice = true;
+            self.alerts_seen = Some(count);
         }
     }
 
-    fn draw_detailed_requests(&mut self, frame: &mut Frame, area: Rect) {
+    fn draw_alerts(&mut self, frame: &mut Frame, area: Rect) {
         let log_data = self.log_data.lock().unwrap();
-        let mut top_ips = log_data.get_top_n(self.top_n).0;
+        let (patterns, matches) = log_data.get_alerts();
+        let mut items: Vec<ListItem> = patterns.iter()
+            .map(|(pattern, count)| ListItem::new(format!("{:<40} | {}", pattern, self.count(*count))).style(Style::default().fg(if *count > 0 { Color::Red } else { TEXT_FG_COLOR })))
+            .collect();
+        if patterns.is_empty() {
+            items.push(ListItem::new("No patterns to watch — pass --alert-pattern"));
+        }
+        for alert in matches.iter().rev() {
+            let time = Local.timestamp_opt(alert.timestamp, 0).single().map_or_else(|| alert.timestamp.to_string(), |dt| dt.format("%Y-%m-%d %H:%M:%S").to_string());
+            items.push(ListItem::new(format!("{} {} [{}]\n  {}", time, alert.ip, alert.pattern, alert.line)).style(Style::default().fg(Color::Yellow)));
+        }
+        let title = format!("Alerts ({} matching lines, latest first, A/Esc to close)", self.count(log_data.get_alert_count()));
+        frame.render_widget(self.tui_manager.draw_list(items, title), area);
+    }
 
-        top_ips.sort_by(|a, b| b.1.count.cmp(&a.1.count));
+    fn draw_explanation(&mut self, frame: &mut Frame, area: Rect) {
+        let Some(ip) = self.explained_ip.clone() else { return };
+        let explanation = security::explain_ip(&self.log_data.lock().unwrap(), &self.security_config, &ip);
+        let (items, color) = match &explanation {
+            Some(explanation) => (security::format_explanation(explanation), threat_color(explanation.level)),
+            None => (vec!["No detection flags this IP anymore".to_string()], TEXT_FG_COLOR),
+        };
+        let items: Vec<ListItem> = items.into_iter().map(|line| ListItem::new(line).style(Style::default().fg(color))).collect();
+        let title = format!("Why {} was flagged (e/Esc to close)", ip);
+        frame.render_widget(self.tui_manager.draw_list(items, title), area);
+    }
+
+    fn count(&self, count: usize) -> String {
+        format_count(count, self.thousands)
+    }
+
+    /// "First Seen" and "Last Seen" cells: a log timestamp in local time, or its age when relative times are on.
+    fn format_log_time(&self, timestamp: i64) -> String {
+        if self.relative_time {
+            return format_relative(Local::now().timestamp() - timestamp);
+        }
+        Local.timestamp_opt(timestamp, 0).single().map_or_else(|| timestamp.to_string(), |dt| dt.format("%Y-%m-%d %H:%M:%S").to_string())
+    }
+
+    /// Optional "First Seen" column placed before "Last Seen".
+    fn format_first_seen(&self, entry: &LogEntry) -> String {
+        match self.show_first_seen {
+            true => format!("{:<19} | ", self.format_log_time(entry.first_seen)),
+            false => String::new(),
+        }
+    }
+
+    fn first_seen_header(&self) -> String {
+        match self.show_first_seen {
+            true => format!("{:<19} | ", "First Seen"),
+            false => String::new(),
+        }
+    }
+
+    fn get_summary_title(&self) -> String {
+        let span = self.log_data.lock().unwrap().get_time_span();
+        let format = |timestamp: i64| Local.timestamp_opt(timestamp, 0).unwrap().format("%Y-%m-%d %H:%M:%S").to_string();
+        match span {
+            Some((first, last)) => format!("Summary | Log spans: {} → {} ({})", format(first), format(last), format_duration(last - first)),
+            None => "Summary".to_string(),
+        }
+    }
+
+    fn get_summary_text(&self) -> String {
+        let log_data = self.log_data.lock().unwrap();
+        let (unique_ips, unique_urls) = log_data.get_unique_counts();
+        let (per_ip, per_url) = log_data.get_request_ratios();
+        let now = Local::now();
+        // Distinct counts do not scale with the sampling rate, so only the request total is
+        // extrapolated and the figures read from the sample alone say so.
+        let (requests, in_sample) = match log_data.is_sampled() {
+            true => (format!("~{} (estimated from sample)", self.count(log_data.estimate(log_data.total_requests))), " (in sample)"),
+            false => (self.count(log_data.total_requests), ""),
+        };
+        let (unique_ips, unique_urls) = match log_data.get_unique_estimates() {
+            Some((ips, urls)) => (format!("~{}{}", self.count(ips), in_sample), format!("~{}{}", self.count(urls), in_sample)),
+            None => (format!("{}{}", self.count(unique_ips), in_sample), format!("{}{}", self.count(unique_urls), in_sample)),
+        };
+        let bots = if log_data.excludes_bots() { " | Bots hidden" } else { "" };
+        let alerts = match log_data.has_alert_patterns() {
+            true => format!(" | Alerts: {}", self.count(log_data.get_alert_count())),
+            false => String::new(),
+        };
+        format!(
+            "Requests: {} | Unique IPs: {} | Unique URLs: {} | Req/IP: {:.1}{} | Req/URL: {:.1}{} | Update: {}{}{}",
+            requests, unique_ips, unique_urls, per_ip, in_sample, per_url, in_sample, now.format("%Y-%m-%d %H:%M:%S"), bots, alerts
+        )
+    }
+
+    fn draw_domain_overview(&mut self, domain: &str, frame: &mut Frame, area: Rect) {
+        let (top_ips, top_urls, domain_count) = {
+            let log_data = self.log_data.lock().unwrap();
+            let (top_ips, top_urls) = log_data.get_top_n_for_domain(domain, self.top);
+            (top_ips, top_urls, log_data.get_domains().len())
+        };
+
+        let chunks = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(30), Constraint::Percentage(70)].as_ref())
+            .split(area);
+
+        let ip_items: Vec<ListItem> = top_ips.iter().map(|(ip, count)| ListItem::new(format!("{:<15} | {}", ip, self.count(*count)))).collect();
+        let title = format!("{:<15} | {} ({}, d: next of {} domains)", "Top IPs", "Requests", domain, domain_count);
+        frame.render_stateful_widget(self.tui_manager.draw_list(ip_items.clone(), title), chunks[0], &mut self.top_ip_list_state);
+        self.tui_manager.draw_scrollbar(ip_items.len(), self.top_ip_list_state.selected().unwrap_or(0), frame, chunks[0]);
+
+        let url_items: Vec<ListItem> = top_urls.iter().map(|(url, count)| ListItem::new(format!("{:<50} | {}", url, self.count(*count)))).collect();
+        let title = format!("{:<50} | {} ({})", "Top URLs", "Requests", domain);
+        frame.render_stateful_widget(self.tui_manager.draw_list(url_items.clone(), title), chunks[1], &mut self.top_url_list_state);
+        self.tui_manager.draw_scrollbar(url_items.len(), self.top_url_list_state.selected().unwrap_or(0), frame, chunks[1]);
+    }
+
+    fn draw_overview(&mut self, frame: &mut Frame, area: Rect) {
+        if let Some(domain) = self.selected_domain.clone() {
+            self.draw_domain_overview(&domain, frame, area);
+            return;
+        }
+        let log_data = self.log_data.lock().unwrap();
+        let range = self.get_time_range(&log_data);
+        let (top_ips, top_urls) = log_data.get_top_n_in_range(self.top, range);
+        let window_label = self.get_window_label();
+
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(if log_data.has_url_categories() { 3 } else { 0 }), Constraint::Min(0)].as_ref())
+            .split(area);
+
+        // Requests per --url-categories label, over the whole log
+        if log_data.has_url_categories() {
+            let categories = log_data.get_top_n_by(GroupBy::Category, usize::MAX);
+            let total: usize = categories.iter().map(|(_, count)| count).sum();
+            let summary = categories.iter()
+                .map(|(category, count)| format!("{} {} ({:.0}%)", category, self.count(*count), safe_ratio(*count as f64, total as f64) * 100.0))
+                .collect::<Vec<_>>()
+                .join("  ");
+            frame.render_widget(self.tui_manager.draw_summary(&summary, "URL categories".to_string()), chunks[0]);
+        }
+
+        let chunks = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(30), Constraint::Percentage(70)].as_ref())
+            .split(chunks[1]);
+
+
+        let now = Instant::now();
+        self.ip_changes.update(top_ips.iter().map(|(ip, count, _)| (ip.as_str(), *count)), now);
+        self.url_changes.update(top_urls.iter().map(|(url, count, _)| (url.as_str(), *count)), now);
+
+        // Top IPs
+        let ip_items: Vec<ListItem> = top_ips.iter().map(|(ip, count, entry)| {
+            let last_seen = self.format_log_time(entry.last_seen);
+            let item = ListItem::new(format!("{:<15} | {:<8} | {}{}", ip, self.count(*count), self.format_first_seen(entry), last_seen));
+            if self.ip_changes.is_highlighted(ip, now) { item.style(CHANGED_STYLE) } else { item }
+        }).collect();
+
+
+        frame.render_stateful_widget(self.tui_manager.draw_list(ip_items.clone(), format!("{:<15} | {:<8} | {}{}{}", "Top IPs", "Requests", self.first_seen_header(), "Last Seen", window_label).to_string()), chunks[0], &mut self.top_ip_list_state);
+
+        self.tui_manager.draw_scrollbar(ip_items.len(), self.top_ip_list_state.selected().unwrap_or(0), frame, chunks[0]);
+
+        // Top URLs
+        let url_width = self.url_width.unwrap_or_else(|| url_column_width(chunks[1].width, URL_ROW_COLUMNS + self.first_seen_header().chars().count()));
+        let url_items: Vec<ListItem> = top_urls.iter().map(|(url, count, entry)| {
+            let last_seen = self.format_log_time(entry.last_seen);
+            let item = ListItem::new(format!("{} | {:<20} | {:<6} | {:<8} | {}{}", fit(&self.display_url(url), url_width), entry.request_type, entry.request_domain, self.count(*count), self.format_first_seen(entry), last_seen));
+            if self.url_changes.is_highlighted(url, now) { item.style(CHANGED_STYLE) } else { item }
+        }).collect();
+
+        let query_params = match &self.pinned_url {
+            Some(url) => Some((url.clone(), log_data.get_query_params(url, 10))),
+            None => self.top_url_list_state.selected()
+                .and_then(|i| top_urls.get(i))
+                .map(|(url, _, _)| (url.clone(), log_data.get_query_params(url, 10)))
+                .filter(|(_, params)| !params.is_empty()),
+        };
+
+        let url_chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints(if query_params.is_some() {
+                [Constraint::Percentage(70), Constraint::Percentage(30)]
+            } else {
+                [Constraint::Percentage(100), Constraint::Percentage(0)]
+            })
+            .split(chunks[1]);
+
+        frame.render_stateful_widget(self.tui_manager.draw_list(url_items.clone(), format!("{} | {:<20} | {:<6} | {:<8} | {}{}{}", fit("Top URLs", url_width), "Type", "Domain", "Requests", self.first_seen_header(), "Last Seen", window_label).to_string()), url_chunks[0], &mut self.top_url_list_state);
+
+        self.tui_manager.draw_scrollbar(url_items.len(), self.top_url_list_state.selected().unwrap_or(0), frame, url_chunks[0]);
+
+        // Query parameters of the selected or pinned URL
+        if let Some((url, params)) = query_params {
+            let param_items: Vec<ListItem> = params.iter().map(|(key, count, values)| {
+                let values = values.iter().map(|(value, count)| format!("{} ({})", value, count)).collect::<Vec<_>>().join(", ");
+                ListItem::new(format!("{:<20} | {:<8} | {}", key, self.count(*count), values)).style(Style::default().fg(TEXT_FG_COLOR))
+            }).collect();
+            let param_items = or_empty_message(param_items, "No query parameters");
+            let title = match self.pinned_url.is_some() {
+                true => format!("[pinned] Query parameters for {} (f: unpin)", url),
+                false => format!("Query parameters for {} (f: pin)", url),
+            };
+            frame.render_widget(self.tui_manager.draw_list(param_items, title), url_chunks[1]);
+        }
+    }
+
+
+
+    fn draw_last_requests(&mut self, frame: &mut Frame, area: Rect) {
+        let items: Vec<ListItem>;
+        let total_pages: usize;
+
+        {
+            let log_data = self.log_data.lock().unwrap();
+            let search_results = self.get_search_results(&log_data);
+
+            // Keep the selected row in place when newer requests are prepended, unless following the top.
+            if let (Some(selected), Some(top_seq)) = (self.last_requests_state.selected(), self.feed_top_seq) {
+                if selected > 0 || self.current_page > 0 {
+                    let inserted = search_results.iter().take_while(|request| request.seq > top_seq).count();
+                    self.last_requests_state.select(Some(selected.saturating_add(inserted)));
+                }
+            }
+            self.feed_top_seq = search_results.first().map(|request| request.seq);
+
+            total_pages = search_results.len().div_ceil(100);
+            let start = self.current_page * 100;
+            let end = (start + 100).min(search_results.len());
+
+            let widths = feed_column_widths(&self.feed_columns, area.width.saturating_sub(4) as usize);
+            items = search_results[start..end]
+                .iter()
+                .map(|request| {
+                    let text = if self.feed_columns.is_empty() && !request.log_line.is_empty() {
+                        wrap(&request.log_line, (area.width as f64 * 0.7) as usize - 5).join("\n")
+                    } else if self.feed_columns.is_empty() {
+                        // Stored without its raw line (--no-raw-lines).
+                        format_feed_row(request, &FeedColumn::DEFAULT, &feed_column_widths(&FeedColumn::DEFAULT, area.width.saturating_sub(4) as usize), self.decode_urls)
+                    } else {
+                        format_feed_row(request, &self.feed_columns, &widths, self.decode_urls)
+                    };
+                    ListItem::new(text).style(Style::default().fg(status_color(request.status)))
+                })
+                .collect();
+        }
+
+        self.total_pages = total_pages;
+        let pages: Vec<String> = (1..=self.total_pages).map(|i| format!("{}", i)).collect();
+
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(3), Constraint::Min(0)].as_ref())
+            .split(area);
+
+        let header_chunks = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+            .split(chunks[0]);
+
+
+        frame.render_widget(self.tui_manager.draw_input(&self.input), header_chunks[0]);
+        frame.render_widget(self.tui_manager.draw_pagination(pages.clone(), self.current_page), header_chunks[1]);
+
+        let widths = feed_column_widths(&self.feed_columns, area.width.saturating_sub(4) as usize);
+        let header = self.feed_columns.iter().zip(&widths).map(|(column, &width)| fit(column.title(), width)).collect::<Vec<_>>().join(" | ");
+        frame.render_stateful_widget(self.tui_manager.draw_list(items.clone(), header), chunks[1], &mut self.last_requests_state);
+        self.tui_manager.draw_scrollbar(items.len(), self.last_requests_state.selected().unwrap_or(0), frame, chunks[1]);
+    }
+
+    fn get_search_results<'a>(&self, log_data: &'a LogData) -> Vec<&'a RecentRequest> {
+        let requests = log_data.get_recent_requests();
+        if !self.input.is_empty() {
+            requests.into_iter().filter(|request| request_matches(request, &self.input)).collect()
+        } else {
+            requests
+        }
+    }
+
+    fn draw_detailed_requests(&mut self, frame: &mut Frame, area: Rect) {
+        let log_data = self.log_data.lock().unwrap();
+        let mut top_ips = log_data.get_top_n(self.top).0;
+
+        top_ips.sort_by_key(|b| std::cmp::Reverse(b.1.count));
 
         let ip_items: Vec<ListItem> = top_ips
             .iter()
             .map(|(ip, entry)| {
-                ListItem::new(format!("{:<15} ({})", ip, entry.count)).style(Style::default().fg(Color::Yellow))
+                ListItem::new(format!("{:<15} ({})", ip, self.count(entry.count))).style(Style::default().fg(Color::Yellow))
             })
             .collect();
 
         let selected_ip = self.ip_list_state.selected().and_then(|i| top_ips.get(i).map(|(ip, _)| ip.clone()));
 
+        let method_profile = selected_ip.as_deref().map_or(Vec::new(), |ip| log_data.get_method_profile(ip));
         let mut request_items: Vec<ListItem> = vec![];
         if let Some(ip) = selected_ip.clone() {
-            let last_requests = log_data.get_last_requests(&ip);
-            for request in last_requests {
+            let requests: Vec<(String, Option<u16>)> = log_data.get_last_requests(&ip).into_iter().zip(log_data.get_last_statuses(&ip)).collect();
+            let requests = match self.collapse_repeats {
+                true => collapse_consecutive(requests),
+                false => requests.into_iter().map(|request| (request, 1)).collect(),
+            };
+            for ((request, status), repeats) in requests {
+                let request = if request.is_empty() { "(raw line not kept, --no-raw-lines)".to_string() } else { request };
+                let request = if repeats > 1 { format!("{} (x{})", request, repeats) } else { request };
                 let wrapped_text = wrap(&request, (area.width as f64 * 0.7) as usize - 5);
-                let list_item = ListItem::new(wrapped_text.join("\n")).style(Style::default().fg(TEXT_FG_COLOR));
+                let list_item = ListItem::new(wrapped_text.join("\n")).style(Style::default().fg(status_color(status)));
                 request_items.push(list_item);
             }
         }
@@ -258,13 +1230,25 @@ impl App {
         self.tui_manager.draw_scrollbar(ip_items.len(), self.ip_list_state.selected().unwrap_or(0), frame, chunks[0]);
 
         let request_list_title = if let Some(ip) = selected_ip.clone() {
-            format!("Requests for IP: {}", ip)
+            format!("Requests for IP: {} (m: {} repeats)", ip, if self.collapse_repeats { "expand" } else { "collapse" })
         } else {
             "Requests".to_string()
         };
 
-        frame.render_stateful_widget(self.tui_manager.draw_list(request_items.clone(), request_list_title), chunks[1], &mut self.request_list_state);
-        self.tui_manager.draw_scrollbar(request_items.len(), self.request_list_state.selected().unwrap_or(0), frame, chunks[1]);
+        let right = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(3), Constraint::Min(0)].as_ref())
+            .split(chunks[1]);
+        let profile_text = method_profile.iter().map(|(method, count, share)| format!("{} {:.0}% ({})", method, share * 100.0, self.count(*count))).collect::<Vec<_>>().join("  ");
+        let (profile_title, profile_color) = if security::is_automated_method_profile(&method_profile) {
+            ("Methods — only POST/HEAD, likely automated".to_string(), Color::Red)
+        } else {
+            ("Methods".to_string(), TEXT_FG_COLOR)
+        };
+        frame.render_widget(self.tui_manager.draw_summary(&profile_text, profile_title).style(Style::default().fg(profile_color)), right[0]);
+
+        frame.render_stateful_widget(self.tui_manager.draw_list(request_items.clone(), request_list_title), right[1], &mut self.request_list_state);
+        self.tui_manager.draw_scrollbar(request_items.len(), self.request_list_state.selected().unwrap_or(0), frame, right[1]);
 
         if self.ip_list_state.selected().is_none() {
             self.ip_list_state.select(Some(0));
@@ -287,16 +1271,35 @@ impl App {
         }
 
         let (min_value, max_value, start_time, end_time) = self.get_sparkline_bounds(&data, &sorted_data);
+        let baseline = sparkline_baseline(&data, self.baseline_rps);
 
         let sparkline_title = format!(
-            "Requests over last 20 minutes (Min: {}, Max: {}, Start: {}, End: {})",
+            "Requests over last 20 minutes (Min: {}, Max: {}, Baseline: {:.1} {}, Start: {}, End: {})",
             min_value,
             max_value,
+            baseline,
+            if self.baseline_rps.is_some() { "--baseline-rps" } else { "median" },
             start_time,
             end_time
         );
 
-        frame.render_widget(self.tui_manager.draw_sparkline(&data, &sparkline_title), area);
+        // Scaled to include the baseline so that a quiet period still shows where normal is.
+        let max = max_value.max(baseline.ceil() as u64);
+        frame.render_widget(self.tui_manager.draw_sparkline(&data, &sparkline_title).max(max), area);
+
+        let inner = area.inner(Margin { horizontal: 1, vertical: 1 });
+        if inner.height == 0 {
+            return;
+        }
+        let y = inner.bottom() - 1 - baseline_row(baseline, max, inner.height);
+        let buffer = frame.buffer_mut();
+        for x in inner.left()..inner.right() {
+            let cell = buffer.get_mut(x, y);
+            // Bars crossing the baseline stay drawn, which is what makes a spike stand out.
+            if cell.symbol() == " " {
+                cell.set_symbol("─").set_fg(Color::Yellow);
+            }
+        }
     }
 
     fn get_sparkline_bounds(&self, data: &[u64], sorted_data: &[(i64, u64)]) -> (u64, u64, i64, i64) {
@@ -341,7 +1344,7 @@ impl App {
         let mut cells = Vec::new();
 
         for &(timestamp, value) in sorted_data.iter() {
-            let intensity = (value as f64 - min_value as f64) / (max_value as f64 - min_value as f64);
+            let intensity = safe_ratio(value as f64 - min_value as f64, max_value as f64 - min_value as f64);
             let color = Color::Rgb(
                 (intensity * 255.0) as u8,
                 0,
@@ -364,56 +1367,323 @@ impl App {
         cells
     }
 
-    fn on_up(&mut self) {
-        match self.current_tab {
-            0 => {
-                self.top_ip_list_state.select_previous();
-                self.top_url_list_state.select_previous();
-            }
-            1 => {
-                self.last_requests_state.select_previous()
-            },
-            2 => {
-                if self.request_list_state.selected().is_some() {
-                    self.request_list_state.select_previous();
-                } else {
-                    self.ip_list_state.select_previous();
-                }
-            }
-            _ => {}
+    fn draw_performance(&mut self, frame: &mut Frame, area: Rect) {
+        let (histogram, response_times, largest, talkers, large_responses) = {
+            let log_data = self.log_data.lock().unwrap();
+            (
+                log_data.get_response_size_histogram(),
+                log_data.get_response_time_stats(),
+                log_data.get_largest_responses(self.top.urls),
+                log_data.get_top_talkers(self.top.ips),
+                self.large_response_threshold.map(|threshold| (threshold, log_data.count_large_responses(threshold))),
+            )
+        };
+
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(3), Constraint::Length(SIZE_BUCKETS.len() as u16 + 2), Constraint::Min(0)].as_ref())
+            .split(area);
+
+        let mut response_time_text = match response_times {
+            Some((average, max)) => format!("Response time: avg {:.3}s | max {:.3}s", average, max),
+            None => "Response time: N/A — add a (?P<rt>[\\d.]+) group to --regex".to_string(),
+        };
+        if let Some((threshold, flagged)) = large_responses {
+            response_time_text.push_str(&format!(" | URLs with responses >= {} bytes: {}", threshold, flagged));
+        }
+        frame.render_widget(self.tui_manager.draw_summary(&response_time_text, "Summary".to_string()), chunks[0]);
+
+        let total: usize = histogram.iter().sum();
+        if total == 0 {
+            self.tui_manager.draw_message("No response size data — add a (?P<size>\\d+|-) group to --regex", frame, chunks[1]);
+            return;
         }
+
+        let max_value = *histogram.iter().max().unwrap_or(&1);
+        let bar_width = (chunks[1].width as usize).saturating_sub(40);
+        let items: Vec<ListItem> = SIZE_BUCKETS.iter().zip(histogram.iter()).map(|(&(label, _), &count)| {
+            let intensity = safe_ratio(count as f64, max_value as f64);
+            let color = Color::Rgb((intensity * 255.0) as u8, 0, (255.0 - intensity * 255.0) as u8);
+            let bar = self.intensity_bar.render(intensity, bar_width);
+            ListItem::new(format!("{:<10} | {:<8} | {}", label, self.count(count), bar)).style(Style::default().fg(color))
+        }).collect();
+
+        let title = format!("Response sizes ({} sampled responses)", self.count(total));
+        frame.render_widget(self.tui_manager.draw_list(items, title), chunks[1]);
+
+        let items: Vec<ListItem> = largest.iter().map(|(url, size)| {
+            let color = match self.large_response_threshold {
+                Some(threshold) if *size >= threshold => Color::Red,
+                _ => TEXT_FG_COLOR,
+            };
+            ListItem::new(format!("{:<50} | {}", url, size)).style(Style::default().fg(color))
+        }).collect();
+        let bottom = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(55), Constraint::Percentage(45)].as_ref())
+            .split(chunks[2]);
+        let title = format!("{:<50} | {}", "Largest responses", "Bytes");
+        frame.render_widget(self.tui_manager.draw_list(items, title), bottom[0]);
+
+        let items: Vec<ListItem> = talkers.iter().map(|(ip, count, bytes)| {
+            ListItem::new(format!("{:<15} | {:<10} | {:<8} | {}", ip, format_size(*bytes), self.count(*count), format_size(bytes.checked_div(*count as u64).unwrap_or(0))))
+        }).collect();
+        let title = format!("{:<15} | {:<10} | {:<8} | {}", "Top talkers", "Bytes", "Requests", "Per request");
+        frame.render_widget(self.tui_manager.draw_list(items, title), bottom[1]);
     }
 
-    fn on_down(&mut self) {
+    fn draw_security(&mut self, frame: &mut Frame, area: Rect) {
+        let (any_suspicious, suspicious_ips, slow_clients, new_ips, has_response_times, findings, cms_probers, methods, body_mismatches, cache_busting, referer_spam, credential_stuffing, repeaters, has_referers) = {
+            let log_data = self.log_data.lock().unwrap();
+            let repeaters = self.security_config.detect_repeats.then(|| security::find_repeating_ips(&log_data, security::REPEAT_THRESHOLD));
+            (
+                !security::find_suspicious_ips(&log_data, &self.security_config).is_empty(),
+                self.visible_suspicious_ips(&log_data),
+                security::find_slow_clients(&log_data, &self.security_config, security::SLOW_REQUESTS_THRESHOLD),
+                log_data.get_new_ips(self.new_ip_window, self.top.ips),
+                log_data.get_response_time_stats().is_some(),
+                security::find_sensitive_file_probes(&log_data, &self.security_config, self.top.other),
+                security::find_cms_probers(&log_data, &self.security_config, self.top.ips),
+                security::find_unusual_methods(&log_data, &self.security_config, self.top.other),
+                log_data.has_request_sizes().then(|| security::find_body_mismatches(&log_data, self.top.other)),
+                security::find_cache_busting(&log_data, &self.security_config, self.top.other),
+                security::find_referer_spam(&log_data, &self.security_config, self.top.other),
+                security::find_credential_stuffing(&log_data, &self.security_config, self.top.other),
+                repeaters,
+                !log_data.by_referer.is_empty() || !log_data.empty_referers.is_empty(),
+            )
+        };
+
+        let columns = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(30), Constraint::Percentage(70)].as_ref())
+            .split(area);
+
+        let ip_items: Vec<ListItem> = suspicious_ips.iter()
+            .map(|(ip, count, level)| {
+                ListItem::new(format!("{:<15} | {:<8} | {}", ip, self.count(*count), level.label())).style(Style::default().fg(threat_color(*level)))
+            })
+            .collect();
+        let ip_items = or_empty_message(ip_items, match !any_suspicious {
+            true => "No IP probed sensitive files or used unusual methods",
+            false => "No suspicious IP at this threat level (f: filter)",
+        });
+        let title = format!("{:<15} | {:<8} | Threat [{}] (f: filter, c: copy, e: explain)", "Suspicious IPs", "Requests", self.threat_filter.label());
+        let left = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Percentage(50), Constraint::Percentage(25), Constraint::Percentage(25)].as_ref())
+            .split(columns[0]);
+        frame.render_stateful_widget(self.tui_manager.draw_list(ip_items, title), left[0], &mut self.suspicious_ip_state);
+
+        let items: Vec<ListItem> = slow_clients.iter().take(self.top.ips).map(|(ip, slow)| {
+            ListItem::new(format!("{:<15} | {}", ip, slow)).style(Style::default().fg(Color::Yellow))
+        }).collect();
+        let message = match has_response_times {
+            true => format!("No IP had {} requests over {}s (--slow-threshold)", security::SLOW_REQUESTS_THRESHOLD, self.security_config.slow_threshold),
+            false => "No response times in the log — add a (?P<rt>[\\d.]+) group to --regex".to_string(),
+        };
+        let items = or_empty_message(items, &message);
+        let title = format!("{:<15} | {}", "Slow clients", "Slow requests");
+        frame.render_widget(self.tui_manager.draw_list(items, title), left[1]);
+
+        let items: Vec<ListItem> = new_ips.iter().map(|(ip, count, rate)| {
+            ListItem::new(format!("{:<15} | {:<8} | {:.1}/min", ip, self.count(*count), rate)).style(Style::default().fg(Color::Cyan))
+        }).collect();
+        let message = format!("No IP appeared in the last {}s (--new-ip-window)", self.new_ip_window);
+        let items = or_empty_message(items, &message);
+        let title = format!("{:<15} | {:<8} | {}", "New IPs", "Requests", "Rate");
+        frame.render_widget(self.tui_manager.draw_list(items, title), left[2]);
+        let area = columns[1];
+
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([3, 2, 2, 2, 2, 2].into_iter()
+                .chain(body_mismatches.as_ref().map(|_| 2))
+                .chain(repeaters.as_ref().map(|_| 2))
+                .map(Constraint::Fill)
+                .collect::<Vec<_>>())
+            .split(area);
+        let mut optional_chunks = chunks[6..].iter();
+
+        let thousands = self.thousands;
+        let decode_urls = self.decode_urls;
+        let finding_items = |findings: &[security::Finding], color: Color| -> Vec<ListItem> {
+            findings.iter().map(|finding| {
+                let sources = finding.sources.iter().map(|(ip, count)| format!("{} ({})", ip, count)).collect::<Vec<_>>().join(", ");
+                let target = if decode_urls { percent_decode(&finding.target) } else { finding.target.clone() };
+                ListItem::new(format!("{:<50} | {:<8} | {}", target, format_count(finding.count, thousands), sources)).style(Style::default().fg(color))
+            }).collect()
+        };
+
+        let items = or_empty_message(finding_items(&findings, Color::Red), "No requests for sensitive files (--sensitive-paths)");
+        let title = format!("{:<50} | {:<8} | {}", "Sensitive file probes", "Requests", "Top IPs");
+        frame.render_stateful_widget(self.tui_manager.draw_list(items, title), chunks[0], &mut self.security_list_state);
+        self.tui_manager.draw_scrollbar(findings.len(), self.security_list_state.selected().unwrap_or(0), frame, chunks[0]);
+
+        let items: Vec<ListItem> = cms_probers.iter().map(|prober| {
+            ListItem::new(format!("{:<50} | {:<8} | {}", prober.ip, self.count(prober.paths), prober.cms.join(", "))).style(Style::default().fg(Color::Red))
+        }).collect();
+        let items = or_empty_message(items, "No requests for CMS admin panels (--cms-probes)");
+        let title = format!("{:<50} | {:<8} | {}", "CMS probes", "Paths", "CMSs");
+        frame.render_widget(self.tui_manager.draw_list(items, title), chunks[1]);
+
+        let items = or_empty_message(finding_items(&methods, Color::Magenta), "Only allowed methods were used (--allowed-methods)");
+        let title = format!("{:<50} | {:<8} | {}", "Unusual methods", "Requests", "Top IPs");
+        frame.render_widget(self.tui_manager.draw_list(items, title), chunks[2]);
+
+        let message = format!("No path got {} or more distinct query strings from a few IPs", self.security_config.cache_busting_queries);
+        let items = or_empty_message(finding_items(&cache_busting, Color::Yellow), &message);
+        let title = format!("{:<50} | {:<8} | {}", "Cache busting (distinct queries)", "Queries", "IPs");
+        frame.render_widget(self.tui_manager.draw_list(items, title), chunks[3]);
+
+        let items = or_empty_message(finding_items(&referer_spam, Color::Magenta), match has_referers {
+            true => "No spam (--spam-referers) or look-alike referer, no page asset without referer",
+            false => "No referers in the log — needs the combined format or a (?P<referer>[^\"]*) group",
+        });
+        let title = format!("{:<50} | {:<8} | {}", "Referer spam", "Requests", "Top IPs");
+        frame.render_widget(self.tui_manager.draw_list(items, title), chunks[4]);
+
+        let message = format!(
+            "No burst of 401/403 on a login endpoint within {}s (--auth-failure-threshold, --auth-endpoint-threshold)",
+            self.security_config.auth_window
+        );
+        let items = or_empty_message(finding_items(&credential_stuffing, Color::Red), &message);
+        let title = format!("{:<50} | {:<8} | {}", "Credential stuffing (401/403 bursts)", "Peak", "Top IPs");
+        frame.render_widget(self.tui_manager.draw_list(items, title), chunks[5]);
+
+        if let (Some(body_mismatches), Some(&chunk)) = (body_mismatches, optional_chunks.next()) {
+            let message = format!("No request of {}KB or more on a method without a body", LARGE_REQUEST_SIZE / 1024);
+            let items = or_empty_message(finding_items(&body_mismatches, Color::Red), &message);
+            let title = format!("{:<50} | {:<8} | {}", "Large bodies on GET/HEAD/DELETE/OPTIONS/TRACE", "Requests", "Top IPs");
+            frame.render_widget(self.tui_manager.draw_list(items, title), chunk);
+        }
+
+        if let (Some(repeaters), Some(&chunk)) = (repeaters, optional_chunks.next()) {
+            let items: Vec<ListItem> = repeaters.iter().take(self.top.ips).map(|(ip, repeats)| {
+                ListItem::new(format!("{:<50} | {}", ip, repeats)).style(Style::default().fg(Color::Yellow))
+            }).collect();
+            let message = format!("No IP sent the same request {} times", security::REPEAT_THRESHOLD);
+            let items = or_empty_message(items, &message);
+            let title = format!("{:<50} | {}", "Repeated identical requests", "Max repeats");
+            frame.render_widget(self.tui_manager.draw_list(items, title), chunk);
+        }
+    }
+
+    fn draw_subnets(&mut self, frame: &mut Frame, area: Rect) {
+        let subnets = self.log_data.lock().unwrap().get_top_subnets(self.subnet_prefix, self.top.other);
+        let items: Vec<ListItem> = subnets.iter().map(|(subnet, requests, ips)| {
+            ListItem::new(format!("{:<43} | {:<8} | {}", subnet, self.count(*requests), self.count(*ips)))
+        }).collect();
+        let items = or_empty_message(items, "No client addresses are IPs, so they cannot be grouped into networks");
+        let title = format!("{:<43} | {:<8} | {}", format!("Subnets (IPv4 /{}, IPv6 /64)", self.subnet_prefix), "Requests", "IPs");
+        frame.render_stateful_widget(self.tui_manager.draw_list(items, title), area, &mut self.subnet_list_state);
+        self.tui_manager.draw_scrollbar(subnets.len(), self.subnet_list_state.selected().unwrap_or(0), frame, area);
+    }
+
+    fn draw_errors(&mut self, frame: &mut Frame, area: Rect) {
+        let sparkline_width = area.width.saturating_sub(2) as usize;
+        let (failing, has_statuses, shrinking, has_sizes, class_counts) = {
+            let log_data = self.log_data.lock().unwrap();
+            let mut failing = errors::find_failing_urls(&log_data, errors::RECENT_WINDOW, errors::FAILURE_JUMP);
+            failing.truncate(self.top.errors);
+            let mut shrinking = errors::find_shrinking_urls(&log_data, errors::SIZE_RECENT_WINDOW, errors::SIZE_DROP_RATIO);
+            shrinking.truncate(self.top.errors);
+            (
+                failing,
+                log_data.get_status_classes().iter().sum::<usize>() > 0,
+                shrinking,
+                log_data.get_response_size_histogram().iter().sum::<usize>() > 0,
+                log_data.get_status_class_per_minute(self.status_class, sparkline_width),
+            )
+        };
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Percentage(65), Constraint::Percentage(35)].as_ref())
+            .split(area);
+
+        let items: Vec<ListItem> = shrinking.iter().map(|url| {
+            ListItem::new(format!("{:<50} | {:>10} | {:>10}", url.url, format_size(url.baseline_size as u64), format_size(url.recent_size as u64)))
+                .style(Style::default().fg(Color::Yellow))
+        }).collect();
+        let message = match has_sizes {
+            true => format!("No URL's last {} responses shrank below {}% of its usual size", errors::SIZE_RECENT_WINDOW, errors::SIZE_DROP_RATIO * 100.0),
+            false => "No response sizes in the log — add a (?P<size>\\d+|-) group to --regex".to_string(),
+        };
+        let items = or_empty_message(items, &message);
+        let title = format!("{:<50} | {:>10} | {:>10}", "Abnormally small responses (average size)", "Before", "Now");
+        frame.render_widget(self.tui_manager.draw_list(items, title), chunks[1]);
+
+        let area = chunks[0];
+        if !has_statuses {
+            self.tui_manager.draw_message("No status codes in the log — add a (?P<status>\\d{3}) group to --regex", frame, area);
+            return;
+        }
+
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(6), Constraint::Min(0)].as_ref())
+            .split(area);
+        let newest_first: Vec<u64> = class_counts.iter().rev().copied().collect();
+        let title = format!(
+            "{}xx per minute (now {}, max {}) (s: class)",
+            self.status_class,
+            self.count(newest_first.first().copied().unwrap_or(0) as usize),
+            self.count(newest_first.iter().copied().max().unwrap_or(0) as usize),
+        );
+        frame.render_widget(self.tui_manager.draw_sparkline(&newest_first, &title).style(Style::default().fg(status_color(Some(self.status_class * 100)))), rows[0]);
+        let area = rows[1];
+
+        let items: Vec<ListItem> = failing.iter().map(|url| {
+            ListItem::new(format!("{:<50} | {:>7.1}% | {:>7.1}%", url.url, url.baseline_ratio * 100.0, url.recent_ratio * 100.0))
+                .style(Style::default().fg(status_color(Some(500))))
+        }).collect();
+        let message = format!("No errors detected: no URL's 5xx share rose over its last {} responses", errors::RECENT_WINDOW);
+        let items = or_empty_message(items, &message);
+
+        let title = format!("{:<50} | {:>8} | {:>8}", "Newly failing URLs (5xx share)", "Before", "Now");
+        frame.render_stateful_widget(self.tui_manager.draw_list(items, title), area, &mut self.errors_list_state);
+        self.tui_manager.draw_scrollbar(failing.len(), self.errors_list_state.selected().unwrap_or(0), frame, area);
+    }
+
+    /// The lists that navigation keys move on the current tab.
+    fn focused_lists(&mut self) -> Vec<&mut ListState> {
         match self.current_tab {
-            0 => {
-                self.top_ip_list_state.select_next();
-                self.top_url_list_state.select_next();
-            }
-            1 => self.last_requests_state.select_next(),
-            2 => {
-                if self.request_list_state.selected().is_some() {
-                    self.request_list_state.select_next();
-                } else {
-                    self.ip_list_state.select_next();
-                }
-            }
-            _ => {}
+            0 => vec![&mut self.top_ip_list_state, &mut self.top_url_list_state],
+            1 => vec![&mut self.last_requests_state],
+            2 if self.request_list_state.selected().is_some() => vec![&mut self.request_list_state],
+            2 => vec![&mut self.ip_list_state],
+            6 => vec![&mut self.suspicious_ip_state, &mut self.security_list_state],
+            7 => vec![&mut self.errors_list_state],
+            8 => vec![&mut self.subnet_list_state],
+            _ => vec![],
         }
     }
 
+    fn on_up(&mut self) {
+        self.focused_lists().into_iter().for_each(ListState::select_previous);
+    }
+
+    fn on_down(&mut self) {
+        self.focused_lists().into_iter().for_each(ListState::select_next);
+    }
+
+    fn on_home(&mut self) {
+        self.focused_lists().into_iter().for_each(ListState::select_first);
+    }
+
+    /// Selects past the end; the list clamps the selection to its last row when rendered.
+    fn on_end(&mut self) {
+        self.focused_lists().into_iter().for_each(ListState::select_last);
+    }
+
     fn on_left(&mut self) {
         match self.current_tab {
-            1 => {
-                if self.current_page > 0 {
-                    self.current_page -= 1;
-                    self.last_requests_state.select_first()
-                }
+            1 if self.current_page > 0 => {
+                self.current_page -= 1;
+                self.last_requests_state.select_first()
             }
-            2 => {
-                if self.request_list_state.selected().is_some() {
-                    self.request_list_state.select(None);
-                }
+            2 if self.request_list_state.selected().is_some() => {
+                self.request_list_state.select(None);
             }
             _ => {}
         }
@@ -421,26 +1691,686 @@ impl App {
 
     fn on_right(&mut self) {
         match self.current_tab {
-            1 => {
-                if self.current_page < self.total_pages - 1 {
-                    self.current_page += 1;
-                    self.last_requests_state.select_first()
-                }
+            1 if self.current_page < self.total_pages - 1 => {
+                self.current_page += 1;
+                self.last_requests_state.select_first()
             }
-            2 => {
-                if self.ip_list_state.selected().is_some() {
-                    self.request_list_state.select(Some(0));
-                }
+            2 if self.ip_list_state.selected().is_some() => {
+                self.request_list_state.select(Some(0));
             }
             _ => {}
         }
     }
 
     fn toggle_tab(&mut self) {
-        self.current_tab = (self.current_tab + 1) % 5;
+        self.current_tab = (self.current_tab + 1) % TABS.len();
     }
 
     fn quit(&mut self) {
-        self.should_quit = true;
+        match self.confirm_quit {
+            true => self.quit_requested = true,
+            false => self.should_quit = true,
+        }
+    }
+}
+
+/// Parses a comma-separated column list; `raw` or an empty list shows raw log lines.
+pub fn parse_feed_columns(spec: &str) -> Result<Vec<FeedColumn>, String> {
+    let spec = spec.trim();
+    if spec.is_empty() || spec == "raw" {
+        return Ok(Vec::new());
+    }
+    spec.split(',').map(|column| column.trim().parse()).collect()
+}
+
+/// Splits `total_width` between the columns; flexible columns share what the fixed ones leave.
+fn feed_column_widths(columns: &[FeedColumn], total_width: usize) -> Vec<usize> {
+    let separators = columns.len().saturating_sub(1) * 3;
+    let fixed: usize = columns.iter().filter_map(|c| c.width()).sum();
+    let flexible = columns.iter().filter(|c| c.width().is_none()).count();
+    let remaining = total_width.saturating_sub(fixed + separators);
+    columns.iter().map(|c| c.width().unwrap_or_else(|| (remaining / flexible.max(1)).max(10))).collect()
+}
+
+fn format_feed_row(request: &RecentRequest, columns: &[FeedColumn], widths: &[usize], decode_urls: bool) -> String {
+    columns.iter().zip(widths)
+        .map(|(column, &width)| match column {
+            FeedColumn::Url if decode_urls => fit(&percent_decode(&request.url), width),
+            column => fit(&column.value(request), width),
+        })
+        .collect::<Vec<_>>()
+        .join(" | ")
+}
+
+/// Keys the time window prompt accepts: digits, unit letters and the words `last` and `all`.
+fn is_window_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == ' '
+}
+
+/// Empty input and `all` clear the window; anything else has to parse as one.
+fn is_valid_window(input: &str) -> bool {
+    let input = input.trim();
+    input.is_empty() || input == "all" || parse_time_window(input).is_some()
+}
+
+/// Each line with the fields the regex captured (green) or `no match` (red); a compile error replaces the list.
+fn preview_items(result: &Result<Vec<(String, Option<CaptureFields>)>, String>) -> Vec<ListItem<'static>> {
+    match result {
+        Err(error) => vec![ListItem::new(error.clone()).style(Style::default().fg(Color::Red))],
+        Ok(lines) => lines.iter().map(|(line, fields)| match fields {
+            Some(fields) => {
+                let fields = fields.iter().map(|(label, value)| format!("{}={}", label, value)).collect::<Vec<_>>().join(" | ");
+                ListItem::new(format!("{}\n  {}", line.trim_end(), fields)).style(Style::default().fg(Color::Green))
+            }
+            None => ListItem::new(format!("{}\n  no match", line.trim_end())).style(Style::default().fg(Color::Red)),
+        }).collect(),
+    }
+}
+
+/// Requests per second considered normal: `--baseline-rps`, or the median of the plotted seconds.
+fn sparkline_baseline(data: &[u64], baseline_rps: Option<f64>) -> f64 {
+    if let Some(baseline_rps) = baseline_rps {
+        return baseline_rps;
+    }
+    let mut sorted = data.to_vec();
+    sorted.sort_unstable();
+    match sorted.len() {
+        0 => 0.0,
+        len if len % 2 == 0 => (sorted[len / 2 - 1] + sorted[len / 2]) as f64 / 2.0,
+        len => sorted[len / 2] as f64,
+    }
+}
+
+/// Row of a sparkline `height` rows high, counted up from the bottom, that `baseline` reaches when
+/// `max` fills the height.
+fn baseline_row(baseline: f64, max: u64, height: u16) -> u16 {
+    let row = safe_ratio(baseline, max as f64) * height as f64;
+    (row as u16).min(height.saturating_sub(1))
+}
+
+fn threat_color(level: ThreatLevel) -> Color {
+    match level {
+        ThreatLevel::High => Color::Red,
+        ThreatLevel::Medium => Color::Yellow,
+        ThreatLevel::Low => TEXT_FG_COLOR,
+    }
+}
+
+/// A single dimmed line explaining why a list is empty, so an empty panel is not mistaken for missing data.
+fn or_empty_message<'a>(items: Vec<ListItem<'a>>, message: &str) -> Vec<ListItem<'a>> {
+    if items.is_empty() {
+        vec![ListItem::new(message.to_string()).style(Style::default().fg(TEXT_FG_COLOR))]
+    } else {
+        items
+    }
+}
+
+/// How long a row of the Overview stays highlighted after its count went up.
+const CHANGE_HIGHLIGHT: Duration = Duration::from_secs(2);
+
+/// Counts of the rows drawn in the previous frame, to highlight the rows that grew since.
+#[derive(Default)]
+struct ChangeTracker {
+    counts: HashMap<String, usize>,
+    highlighted_until: HashMap<String, Instant>,
+}
+
+impl ChangeTracker {
+    /// Records the counts of this frame and highlights keys whose count increased, or that are
+    /// new, since the previous frame. Nothing is highlighted on the first frame.
+    fn update<'a>(&mut self, rows: impl IntoIterator<Item = (&'a str, usize)>, now: Instant) {
+        let first_frame = self.counts.is_empty();
+        let mut counts = HashMap::new();
+        for (key, count) in rows {
+            if !first_frame && self.counts.get(key).is_none_or(|&previous| count > previous) {
+                self.highlighted_until.insert(key.to_string(), now + CHANGE_HIGHLIGHT);
+            }
+            counts.insert(key.to_string(), count);
+        }
+        self.counts = counts;
+        self.highlighted_until.retain(|_, until| *until > now);
+    }
+
+    fn is_highlighted(&self, key: &str, now: Instant) -> bool {
+        self.highlighted_until.get(key).is_some_and(|until| *until > now)
+    }
+}
+
+/// Result of trying a regex on the stored lines of one IP: each line with its fields, or the regex error.
+struct RegexPreview {
+    ip: String,
+    pattern: String,
+    result: Result<Vec<(String, Option<CaptureFields>)>, String>,
+}
+
+/// Rows listed per dashboard tile, and the smallest tile that still shows them.
+const DASHBOARD_TOP: usize = 5;
+const DASHBOARD_TILE_HEIGHT: u16 = DASHBOARD_TOP as u16 + 2;
+const DASHBOARD_TILE_WIDTH: u16 = 40;
+const DASHBOARD_TILES: usize = 5;
+
+/// Columns and number of dashboard tiles that fit into `width` x `height`.
+fn dashboard_grid(width: u16, height: u16) -> (usize, usize) {
+    let columns = (width / DASHBOARD_TILE_WIDTH).clamp(1, 2) as usize;
+    let rows = (height / DASHBOARD_TILE_HEIGHT) as usize;
+    let tiles = if width < DASHBOARD_TILE_WIDTH { 0 } else { (columns * rows).min(DASHBOARD_TILES) };
+    (columns, tiles)
+}
+
+/// Runs of equal consecutive items merged into one item with the length of the run.
+fn collapse_consecutive<T: PartialEq>(items: Vec<T>) -> Vec<(T, usize)> {
+    let mut collapsed: Vec<(T, usize)> = Vec::new();
+    for item in items {
+        match collapsed.last_mut() {
+            Some((last, repeats)) if *last == item => *repeats += 1,
+            _ => collapsed.push((item, 1)),
+        }
+    }
+    collapsed
+}
+
+/// Byte count in the largest fitting binary unit, such as `512 B`, `1.5 KB` or `3.2 GB`.
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    match unit {
+        0 => format!("{} B", bytes),
+        _ => format!("{:.1} {}", size, UNITS[unit]),
+    }
+}
+
+/// Age such as `12s ago`, `3m ago`, `5h ago` or `2d ago`, in the largest whole unit.
+fn format_relative(seconds: i64) -> String {
+    let seconds = seconds.max(0);
+    match seconds {
+        0..=59 => format!("{}s ago", seconds),
+        60..=3599 => format!("{}m ago", seconds / 60),
+        3600..=86399 => format!("{}h ago", seconds / 3600),
+        _ => format!("{}d ago", seconds / 86400),
+    }
+}
+
+/// Compact duration such as `2d 3h`, `5h 12m`, `4m 10s` or `12s`, showing the two largest units.
+fn format_duration(seconds: i64) -> String {
+    let (days, hours, minutes, seconds) = (seconds / 86400, seconds % 86400 / 3600, seconds % 3600 / 60, seconds % 60);
+    match (days, hours, minutes) {
+        (0, 0, 0) => format!("{}s", seconds),
+        (0, 0, _) => format!("{}m {}s", minutes, seconds),
+        (0, _, _) => format!("{}h {}m", hours, minutes),
+        _ => format!("{}d {}h", days, hours),
+    }
+}
+
+/// Search matches the raw line, or the parsed fields when raw lines are not kept.
+fn request_matches(request: &RecentRequest, input: &str) -> bool {
+    if request.log_line.is_empty() {
+        [&request.ip, &request.request_type, &request.url, &request.request_domain].iter().any(|field| field.contains(input))
+            || request.user_agent.as_ref().is_some_and(|user_agent| user_agent.contains(input))
+    } else {
+        request.log_line.contains(input)
+    }
+}
+
+/// Rebuilds a request as a `curl` command. The log does not record the scheme, so https is assumed
+/// unless the URL is already absolute.
+fn curl_command(request: &RecentRequest) -> String {
+    let url = if request.url.starts_with("http://") || request.url.starts_with("https://") {
+        request.url.clone()
+    } else if request.request_domain.is_empty() || request.request_domain == "-" {
+        format!("https://localhost{}", request.url)
+    } else {
+        format!("https://{}{}", request.request_domain, request.url)
+    };
+
+    let mut command = String::from("curl");
+    match request.request_type.as_str() {
+        "GET" => {}
+        "HEAD" => command.push_str(" -I"),
+        method => command.push_str(&format!(" -X {}", shell_quote(method))),
+    }
+    if let Some(user_agent) = &request.user_agent {
+        command.push_str(&format!(" -A {}", shell_quote(user_agent)));
+    }
+    command.push(' ');
+    command.push_str(&shell_quote(&url));
+    command
+}
+
+/// Single-quotes a value for POSIX shells; embedded quotes become `'\''`.
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+/// Joins rows with tabs and newlines; tabs and line breaks inside cells become spaces.
+fn to_tsv(rows: &[Vec<String>]) -> String {
+    rows.iter()
+        .map(|row| row.iter().map(|cell| cell.replace(['\t', '\r', '\n'], " ")).collect::<Vec<_>>().join("\t") + "\n")
+        .collect()
+}
+
+/// Characters of an Overview URL row besides the URL: type, domain, requests and last update.
+const URL_ROW_COLUMNS: usize = " | ".len() * 4 + 20 + 6 + 8 + 19;
+/// The URL column never gets narrower than this, however small the terminal.
+const MIN_URL_WIDTH: usize = 20;
+
+/// Width left for the URL column of a list `list_width` cells wide, after its borders and the other columns.
+fn url_column_width(list_width: u16, other_columns: usize) -> usize {
+    (list_width as usize).saturating_sub(2 + other_columns).max(MIN_URL_WIDTH)
+}
+
+/// Pads or truncates `value` to exactly `width` characters.
+fn fit(value: &str, width: usize) -> String {
+    if value.chars().count() > width {
+        let truncated: String = value.chars().take(width.saturating_sub(1)).collect();
+        format!("{}…", truncated)
+    } else {
+        format!("{:<width$}", value, width = width)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::log_data::RequestDetails;
+    use ratatui::backend::TestBackend;
+    use ratatui::Terminal;
+
+    fn request() -> RecentRequest {
+        RecentRequest {
+            seq: 0,
+            timestamp: 0,
+            ip: "10.0.0.1".to_string(),
+            request_type: "GET".to_string(),
+            url: "/a/very/long/path/to/a/resource".to_string(),
+            request_domain: "example.com".to_string(),
+            status: Some(404),
+            response_size: None,
+            user_agent: Some("curl/8.0".to_string()),
+            log_line: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_feed_column_widths() {
+        let columns = [FeedColumn::Ip, FeedColumn::Status, FeedColumn::Url, FeedColumn::UserAgent];
+
+        assert_eq!(feed_column_widths(&columns, 80), vec![15, 6, 25, 25]);
+        assert_eq!(feed_column_widths(&[FeedColumn::Ip, FeedColumn::Method], 80), vec![15, 7]);
+    }
+
+    #[test]
+    fn test_url_column_width() {
+        assert_eq!(URL_ROW_COLUMNS, 65);
+        assert_eq!(url_column_width(200, URL_ROW_COLUMNS), 133);
+        assert_eq!(url_column_width(117, URL_ROW_COLUMNS), 50);
+        assert_eq!(url_column_width(60, URL_ROW_COLUMNS), MIN_URL_WIDTH);
+        let url = "/catalog/electronics/laptops/ultrabooks?page=2";
+        assert_eq!(fit(url, url_column_width(80, URL_ROW_COLUMNS)), "/catalog/electronic…");
+        assert_eq!(fit(url, url_column_width(160, URL_ROW_COLUMNS)), format!("{:<93}", url));
+    }
+
+    #[test]
+    fn test_format_feed_row() {
+        let columns = [FeedColumn::Ip, FeedColumn::Method, FeedColumn::Status, FeedColumn::Size, FeedColumn::Url, FeedColumn::UserAgent];
+        let row = format_feed_row(&request(), &columns, &[15, 7, 6, 10, 12, 8], false);
+
+        assert_eq!(row, "10.0.0.1        | GET     | 404    | -          | /a/very/lon… | curl/8.0");
+
+        let mut encoded = request();
+        encoded.url = "/files/..%2f..%2fetc%2Fpasswd%0a".to_string();
+        assert_eq!(format_feed_row(&encoded, &[FeedColumn::Url], &[40], true), format!("{:<40}", "/files/../../etc/passwd%0a"));
+        assert_eq!(format_feed_row(&encoded, &[FeedColumn::Url], &[40], false), format!("{:<40}", encoded.url));
+    }
+
+    fn app() -> App {
+        App::new(Arc::new(Mutex::new(LogData::new())), TopLimits::uniform(10), SecurityConfig::default(), FeedColumn::DEFAULT.to_vec(), IntensityBar::default(), None)
+    }
+
+    #[test]
+    fn test_sampled_summary_labels_sample_figures() {
+        let app = app();
+        {
+            let mut log_data = app.log_data.lock().unwrap();
+            log_data.set_sample_percent(Some(10));
+            for ip in ["10.0.0.1", "10.0.0.1", "10.0.0.2"] {
+                log_data.add_entry(ip.to_string(), "/".to_string(), String::new(), 0, "GET".to_string(), "example.com".to_string(), RequestDetails::default(), false);
+            }
+        }
+
+        let summary = app.get_summary_text();
+        assert!(summary.starts_with("Requests: ~30 (estimated from sample) | Unique IPs: 2 (in sample) | Unique URLs: 1 (in sample) | Req/IP: 1.5 (in sample) | Req/URL: 3.0 (in sample)"), "{}", summary);
+    }
+
+    #[test]
+    fn test_ascii_mode_draws_only_ascii() {
+        let log_data = Arc::new(Mutex::new(LogData::new()));
+        for (ip, url, timestamp) in [("10.0.0.1", "/a", 1_700_000_000), ("10.0.0.2", "/a/very/long/path/that/does/not/fit/in/the/url/column", 1_700_003_600), ("10.0.0.1", "/caf\u{e9}", 1_700_090_000)] {
+            let details = RequestDetails { status: Some(200), response_size: Some(512), ..RequestDetails::default() };
+            log_data.lock().unwrap().add_entry(ip.to_string(), url.to_string(), String::new(), timestamp, "GET".to_string(), "example.com".to_string(), details, false);
+        }
+        let mut app = App::new(log_data, TopLimits::uniform(10), SecurityConfig::default(), FeedColumn::DEFAULT.to_vec(), IntensityBar::default(), None);
+        let mut terminal = Terminal::new(TestBackend::new(160, 50)).unwrap();
+        let symbols = |terminal: &Terminal<TestBackend>| terminal.backend().buffer().content.iter().map(|cell| cell.symbol().to_string()).collect::<String>();
+
+        terminal.draw(|frame| app.draw(frame)).unwrap();
+        assert!(!symbols(&terminal).is_ascii());
+
+        app.set_ascii(true);
+        for (tab, name) in TABS.iter().enumerate() {
+            app.current_tab = tab;
+            terminal.draw(|frame| app.draw(frame)).unwrap();
+            assert!(symbols(&terminal).is_ascii(), "the {} tab drew non-ASCII characters", name);
+        }
+    }
+
+    #[test]
+    fn test_heatmap_with_equal_values() {
+        let timestamp = 1_700_000_000;
+        let date = Utc.timestamp_opt(timestamp, 0).unwrap().date_naive();
+        let cells = app().generate_heatmap_cells(&[(timestamp, 4), (timestamp + 3600, 4)], 4, 4, &[date]);
+        assert!(cells.iter().all(|cell| cell.color == Color::Rgb(0, 0, 255)));
+    }
+
+    #[test]
+    fn test_sparkline_baseline() {
+        assert_eq!(sparkline_baseline(&[5, 1, 40, 3, 4], None), 4.0);
+        assert_eq!(sparkline_baseline(&[2, 8, 3, 5], None), 4.0);
+        assert_eq!(sparkline_baseline(&[], None), 0.0);
+        assert_eq!(sparkline_baseline(&[5, 1, 40], Some(12.5)), 12.5);
+
+        assert_eq!(baseline_row(4.0, 40, 10), 1);
+        assert_eq!(baseline_row(40.0, 40, 10), 9);
+        assert_eq!(baseline_row(0.0, 0, 10), 0);
+    }
+
+    #[test]
+    fn test_alert_notice() {
+        let mut app = app();
+        app.log_data.lock().unwrap().set_alert_patterns(&["/.git/".to_string()]);
+        app.log_data.lock().unwrap().add_entry("203.0.113.5".to_string(), "/.git/HEAD".to_string(), String::new(), 0, "GET".to_string(), "example.com".to_string(), RequestDetails::default(), false);
+        app.check_alerts();
+        assert_eq!(app.notice, None);
+        app.finish_initial_read();
+        app.check_alerts();
+        assert_eq!(app.notice, None);
+
+        for _ in 0..2 {
+            app.log_data.lock().unwrap().add_entry("203.0.113.5".to_string(), "/.git/config".to_string(), String::new(), 0, "GET".to_string(), "example.com".to_string(), RequestDetails::default(), false);
+        }
+        app.check_alerts();
+        assert_eq!(app.notice.as_deref(), Some("Alert: 2 new line(s) matched --alert-pattern (A: show alerts)"));
+        assert!(app.alert_notice);
+
+        app.handle_input(KeyCode::Char('A'), KeyModifiers::SHIFT);
+        assert!(app.show_alerts);
+        assert!(!app.alert_notice);
+        app.check_alerts();
+        assert_eq!(app.notice, None);
+    }
+
+    #[test]
+    fn test_confirm_quit() {
+        let mut twice = app();
+        let mut unconfirmed = app();
+        let mut app = app();
+        app.handle_input(KeyCode::Char('c'), KeyModifiers::CONTROL);
+        assert!(app.quit_requested);
+        assert!(!app.should_quit);
+        app.handle_input(KeyCode::Esc, KeyModifiers::NONE);
+        assert!(!app.quit_requested);
+        assert!(!app.should_quit);
+
+        app.handle_input(KeyCode::Char('q'), KeyModifiers::CONTROL);
+        app.handle_input(KeyCode::Char('t'), KeyModifiers::NONE);
+        assert!(!app.should_quit);
+        assert_eq!(app.current_tab, 0, "the key cancelling the prompt is not handled further");
+
+        app.handle_input(KeyCode::Char('q'), KeyModifiers::CONTROL);
+        app.handle_input(KeyCode::Char('y'), KeyModifiers::NONE);
+        assert!(app.should_quit);
+
+        twice.handle_input(KeyCode::Char('c'), KeyModifiers::CONTROL);
+        twice.handle_input(KeyCode::Char('c'), KeyModifiers::CONTROL);
+        assert!(twice.should_quit);
+
+        unconfirmed.set_confirm_quit(false);
+        unconfirmed.handle_input(KeyCode::Char('c'), KeyModifiers::CONTROL);
+        assert!(unconfirmed.should_quit);
+    }
+
+    #[test]
+    fn test_pin_url() {
+        let mut app = app();
+        for url in ["/a", "/a", "/b"] {
+            app.log_data.lock().unwrap().add_entry("10.0.0.1".to_string(), url.to_string(), String::new(), 0, "GET".to_string(), "example.com".to_string(), RequestDetails::default(), false);
+        }
+        app.handle_input(KeyCode::Char('f'), KeyModifiers::NONE);
+        assert_eq!(app.pinned_url, None);
+
+        app.top_url_list_state.select(Some(0));
+        app.handle_input(KeyCode::Char('f'), KeyModifiers::NONE);
+        assert_eq!(app.pinned_url.as_deref(), Some("/a"));
+        app.handle_input(KeyCode::Down, KeyModifiers::NONE);
+        assert_eq!(app.pinned_url.as_deref(), Some("/a"));
+
+        app.handle_input(KeyCode::Char('f'), KeyModifiers::NONE);
+        assert_eq!(app.pinned_url, None);
+        app.top_url_list_state.select(Some(1));
+        app.handle_input(KeyCode::Char('f'), KeyModifiers::NONE);
+        assert_eq!(app.pinned_url.as_deref(), Some("/b"));
+    }
+
+    #[test]
+    fn test_jump_to_first_and_last_row() {
+        let mut app = app();
+        app.handle_input(KeyCode::End, KeyModifiers::NONE);
+        assert_eq!(app.top_ip_list_state.selected(), Some(usize::MAX));
+        assert_eq!(app.top_url_list_state.selected(), Some(usize::MAX));
+        app.handle_input(KeyCode::Char('g'), KeyModifiers::NONE);
+        assert_eq!(app.top_ip_list_state.selected(), Some(0));
+
+        app.current_tab = 2;
+        app.request_list_state.select(Some(3));
+        app.handle_input(KeyCode::Home, KeyModifiers::NONE);
+        assert_eq!(app.request_list_state.selected(), Some(0));
+        assert_eq!(app.ip_list_state.selected(), None);
+
+        app.current_tab = 1;
+        app.handle_input(KeyCode::Char('G'), KeyModifiers::SHIFT);
+        assert_eq!(app.input, "G");
+        app.handle_input(KeyCode::End, KeyModifiers::NONE);
+        assert_eq!(app.last_requests_state.selected(), Some(usize::MAX));
+    }
+
+    #[test]
+    fn test_cycle_domain() {
+        let mut app = app();
+        app.cycle_domain();
+        assert_eq!(app.selected_domain, None);
+
+        for domain in ["shop.example", "shop.example", "blog.example"] {
+            app.log_data.lock().unwrap().add_entry("10.0.0.1".to_string(), "/".to_string(), String::new(), 0, "GET".to_string(), domain.to_string(), RequestDetails::default(), false);
+        }
+        app.cycle_domain();
+        assert_eq!(app.selected_domain.as_deref(), Some("shop.example"));
+        app.cycle_domain();
+        assert_eq!(app.selected_domain.as_deref(), Some("blog.example"));
+        app.cycle_domain();
+        assert_eq!(app.selected_domain, None);
+    }
+
+    #[test]
+    fn test_dashboard_grid() {
+        assert_eq!(dashboard_grid(200, 60), (2, 5));
+        assert_eq!(dashboard_grid(200, 14), (2, 4));
+        assert_eq!(dashboard_grid(60, 30), (1, 4));
+        assert_eq!(dashboard_grid(60, 10), (1, 1));
+        assert_eq!(dashboard_grid(30, 60), (1, 0));
+        assert_eq!(dashboard_grid(200, 5), (2, 0));
+    }
+
+    #[test]
+    fn test_window_prompt_rejects_invalid_input() {
+        let mut app = app();
+        app.handle_input(KeyCode::Char('w'), KeyModifiers::NONE);
+        for c in "1-5,m".chars() {
+            app.handle_input(KeyCode::Char(c), KeyModifiers::NONE);
+        }
+        assert_eq!(app.window_input.as_deref(), Some("15m"));
+
+        app.handle_input(KeyCode::Char('x'), KeyModifiers::NONE);
+        assert!(!is_valid_window(app.window_input.as_deref().unwrap()));
+        app.handle_input(KeyCode::Enter, KeyModifiers::NONE);
+        assert_eq!(app.window_input.as_deref(), Some("15mx"));
+        assert_eq!(app.time_window, None);
+
+        app.handle_input(KeyCode::Backspace, KeyModifiers::NONE);
+        app.handle_input(KeyCode::Enter, KeyModifiers::NONE);
+        assert_eq!(app.window_input, None);
+        assert_eq!(app.time_window, Some(900));
+        assert!(is_valid_window(" all "));
+        assert!(is_valid_window(""));
+    }
+
+    #[test]
+    fn test_or_empty_message() {
+        assert_eq!(or_empty_message(Vec::new(), "No errors detected"), vec![ListItem::new("No errors detected").style(Style::default().fg(TEXT_FG_COLOR))]);
+        let items = vec![ListItem::new("/checkout")];
+        assert_eq!(or_empty_message(items.clone(), "No errors detected"), items);
+    }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(0), "0s");
+        assert_eq!(format_duration(42), "42s");
+        assert_eq!(format_duration(250), "4m 10s");
+        assert_eq!(format_duration(5 * 3600 + 12 * 60 + 7), "5h 12m");
+        assert_eq!(format_duration(2 * 86400 + 3 * 3600 + 59), "2d 3h");
+    }
+
+    #[test]
+    fn test_change_tracker_highlights_grown_rows() {
+        let start = Instant::now();
+        let mut tracker = ChangeTracker::default();
+        tracker.update([("10.0.0.1", 5), ("10.0.0.2", 3)], start);
+        assert!(!tracker.is_highlighted("10.0.0.1", start));
+
+        let next = start + Duration::from_millis(500);
+        tracker.update([("10.0.0.1", 6), ("10.0.0.2", 3), ("10.0.0.3", 1)], next);
+        assert!(tracker.is_highlighted("10.0.0.1", next));
+        assert!(!tracker.is_highlighted("10.0.0.2", next));
+        assert!(tracker.is_highlighted("10.0.0.3", next));
+
+        let later = next + CHANGE_HIGHLIGHT;
+        tracker.update([("10.0.0.1", 6), ("10.0.0.2", 3), ("10.0.0.3", 1)], later);
+        assert!(!tracker.is_highlighted("10.0.0.1", later));
+        assert!(!tracker.is_highlighted("10.0.0.3", later));
+    }
+
+    #[test]
+    fn test_collapse_consecutive() {
+        assert_eq!(collapse_consecutive(vec!["a", "a", "a", "b", "a", "a"]), vec![("a", 3), ("b", 1), ("a", 2)]);
+        assert_eq!(collapse_consecutive(vec![("GET /", Some(200)), ("GET /", Some(500))]), vec![(("GET /", Some(200)), 1), (("GET /", Some(500)), 1)]);
+        assert_eq!(collapse_consecutive(Vec::<&str>::new()), vec![]);
+    }
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!(format_size(0), "0 B");
+        assert_eq!(format_size(512), "512 B");
+        assert_eq!(format_size(1536), "1.5 KB");
+        assert_eq!(format_size(5_000_000), "4.8 MB");
+        assert_eq!(format_size(3 * 1024 * 1024 * 1024), "3.0 GB");
+    }
+
+    #[test]
+    fn test_format_relative() {
+        assert_eq!(format_relative(0), "0s ago");
+        assert_eq!(format_relative(12), "12s ago");
+        assert_eq!(format_relative(59), "59s ago");
+        assert_eq!(format_relative(60), "1m ago");
+        assert_eq!(format_relative(3 * 60 + 40), "3m ago");
+        assert_eq!(format_relative(5 * 3600 + 59 * 60), "5h ago");
+        assert_eq!(format_relative(2 * 86400 + 3600), "2d ago");
+        assert_eq!(format_relative(-5), "0s ago");
+
+        let mut app = app();
+        let timestamp = Local::now().timestamp() - 120;
+        assert_eq!(app.format_log_time(timestamp), Local.timestamp_opt(timestamp, 0).unwrap().format("%Y-%m-%d %H:%M:%S").to_string());
+        app.relative_time = true;
+        assert_eq!(app.format_log_time(timestamp), "2m ago");
+    }
+
+    #[test]
+    fn test_request_matches_without_raw_line() {
+        let request = request();
+        assert!(request_matches(&request, "/a/very"));
+        assert!(request_matches(&request, "curl/"));
+        assert!(!request_matches(&request, "POST"));
+
+        let request = RecentRequest { log_line: "10.0.0.9 raw".to_string(), ..request };
+        assert!(request_matches(&request, "raw"));
+        assert!(!request_matches(&request, "/a/very"));
+    }
+
+    #[test]
+    fn test_curl_command() {
+        let mut request = request();
+        request.url = "/search?q=it's&page=2".to_string();
+        request.user_agent = Some("Mozilla/5.0 (X11; \"quoted\")".to_string());
+
+        assert_eq!(curl_command(&request), r#"curl -A 'Mozilla/5.0 (X11; "quoted")' 'https://example.com/search?q=it'\''s&page=2'"#);
+
+        request.request_type = "POST".to_string();
+        request.user_agent = None;
+        request.url = "http://other.example/api".to_string();
+        assert_eq!(curl_command(&request), "curl -X 'POST' 'http://other.example/api'");
+
+        request.request_type = "HEAD".to_string();
+        request.request_domain = "-".to_string();
+        request.url = "/".to_string();
+        assert_eq!(curl_command(&request), "curl -I 'https://localhost/'");
+    }
+
+    #[test]
+    fn test_to_tsv() {
+        let rows = vec![
+            vec!["IP".to_string(), "Requests".to_string()],
+            vec!["10.0.0.1".to_string(), "42".to_string()],
+            vec!["GET /a\tb".to_string(), "line\r\nbreak".to_string()],
+        ];
+
+        assert_eq!(to_tsv(&rows), "IP\tRequests\n10.0.0.1\t42\nGET /a b\tline  break\n");
+        assert_eq!(to_tsv(&[]), "");
+    }
+
+    #[test]
+    fn test_intensity_bar() {
+        let bar = IntensityBar::default();
+        assert_eq!(bar.render(1.0, 10), "██████████");
+        assert_eq!(bar.render(0.3, 10), "▒▒▒");
+        assert_eq!(bar.render(0.0, 10), "");
+
+        let ascii = IntensityBar::parse(Some("ascii"), Some("0.1, 0.5, 0.9")).unwrap();
+        assert_eq!(ascii.render(0.5, 8), "++++");
+        assert_eq!(ascii.render(0.05, 20), ".");
+        assert_eq!(ascii.render(2.0, 4), "####");
+
+        assert!(IntensityBar::parse(Some("ab"), Some("0.5,0.7")).is_err());
+        assert!(IntensityBar::parse(Some("abc"), Some("0.7,0.5")).is_err());
+        assert!(IntensityBar::parse(Some(""), None).is_err());
+    }
+
+    #[test]
+    fn test_parse_feed_columns() {
+        assert_eq!(parse_feed_columns("ip, status,ua"), Ok(vec![FeedColumn::Ip, FeedColumn::Status, FeedColumn::UserAgent]));
+        assert_eq!(parse_feed_columns("raw"), Ok(vec![]));
+        assert_eq!(parse_feed_columns(""), Ok(vec![]));
+        assert!(parse_feed_columns("ip,referer").is_err());
     }
 }
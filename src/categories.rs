@@ -0,0 +1,76 @@
+use regex::Regex;
+
+/// Category of the URLs that no `--url-categories` rule matches.
+pub const UNCATEGORIZED: &str = "other";
+
+/// Labels URLs by the first matching rule of a `--url-categories` file, e.g. `/api/*` as `api`.
+#[derive(Debug, Clone)]
+pub struct UrlCategories {
+    rules: Vec<(String, Regex)>,
+}
+
+impl UrlCategories {
+    /// One `label pattern` rule per line; blank lines and `#` comments are skipped. A pattern is a
+    /// glob over the whole URL (`*` for any run of characters, `?` for one) unless it starts with
+    /// `~`, which makes the rest a regex searched anywhere in the URL.
+    pub fn parse(content: &str) -> Result<Self, String> {
+        let mut rules = Vec::new();
+        for (index, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (label, pattern) = line.split_once(char::is_whitespace).ok_or_else(|| format!("line {}: expected 'label pattern'", index + 1))?;
+            let pattern = pattern.trim();
+            let regex = match pattern.strip_prefix('~') {
+                Some(regex) => Regex::new(regex),
+                None => Regex::new(&glob_to_regex(pattern)),
+            };
+            rules.push((label.to_string(), regex.map_err(|e| format!("line {}: {}", index + 1, e))?));
+        }
+        Ok(UrlCategories { rules })
+    }
+
+    pub fn categorize(&self, url: &str) -> &str {
+        self.rules.iter().find(|(_, regex)| regex.is_match(url)).map_or(UNCATEGORIZED, |(label, _)| label.as_str())
+    }
+}
+
+fn glob_to_regex(glob: &str) -> String {
+    let mut regex = String::from("^");
+    for c in glob.chars() {
+        match c {
+            '*' => regex.push_str(".*"),
+            '?' => regex.push('.'),
+            c => regex.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    regex.push('$');
+    regex
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_categorize_urls() {
+        let categories = UrlCategories::parse("# label pattern\napi /api/*\n\nstatic *.css\nstatic *.js\nadmin ~^/(admin|wp-admin)(/|$)\nmedia /media/??/*\n").unwrap();
+
+        assert_eq!(categories.categorize("/api/v1/users"), "api");
+        assert_eq!(categories.categorize("/assets/site.css"), "static");
+        assert_eq!(categories.categorize("/app.js"), "static");
+        assert_eq!(categories.categorize("/admin"), "admin");
+        assert_eq!(categories.categorize("/wp-admin/index.php"), "admin");
+        assert_eq!(categories.categorize("/media/ab/cat.png"), "media");
+        assert_eq!(categories.categorize("/media/abc/cat.png"), UNCATEGORIZED);
+        assert_eq!(categories.categorize("/app.jsx"), UNCATEGORIZED);
+        assert_eq!(categories.categorize("/administrator"), UNCATEGORIZED);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(UrlCategories::parse("api").unwrap_err(), "line 1: expected 'label pattern'");
+        assert!(UrlCategories::parse("\napi ~(unclosed").unwrap_err().starts_with("line 2: "));
+    }
+}
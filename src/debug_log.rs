@@ -0,0 +1,84 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// Size at which `--log-to-file` moves the log to `<name>.1`, so at most twice this is kept on disk.
+pub const MAX_LOG_SIZE: u64 = 1024 * 1024;
+
+/// Append-only log file that starts over once it reaches `max_size`, keeping the previous
+/// content as `<name>.1` (replacing an older one).
+pub struct RotatingFile {
+    path: PathBuf,
+    file: File,
+    size: u64,
+    max_size: u64,
+}
+
+impl RotatingFile {
+    pub fn open(path: &Path, max_size: u64) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let size = file.metadata()?.len();
+        Ok(Self { path: path.to_path_buf(), file, size, max_size })
+    }
+
+    fn backup_path(&self) -> PathBuf {
+        let mut name = self.path.file_name().unwrap_or_default().to_os_string();
+        name.push(".1");
+        self.path.with_file_name(name)
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        fs::rename(&self.path, self.backup_path())?;
+        self.file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        self.size = 0;
+        Ok(())
+    }
+}
+
+impl Write for RotatingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.size > 0 && self.size + buf.len() as u64 > self.max_size {
+            self.rotate()?;
+        }
+        let written = self.file.write(buf)?;
+        self.size += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rotates_when_exceeding_cap() {
+        let path = std::env::temp_dir().join(format!("logutil_{}_debug.log", std::process::id()));
+        let mut log = RotatingFile::open(&path, 100).unwrap();
+        let backup = log.backup_path();
+
+        for i in 0..5 {
+            writeln!(log, "line {:02} {}", i, "x".repeat(20)).unwrap();
+        }
+
+        let current = fs::read_to_string(&path).unwrap();
+        let previous = fs::read_to_string(&backup).unwrap();
+        assert!(current.len() <= 100);
+        assert!(previous.len() <= 100);
+        assert!(previous.starts_with("line 00"));
+        assert!(current.ends_with(&format!("line 04 {}\n", "x".repeat(20))));
+
+        // Reopening appends instead of overwriting.
+        drop(log);
+        let mut log = RotatingFile::open(&path, 100).unwrap();
+        writeln!(log, "again").unwrap();
+        assert!(fs::read_to_string(&path).unwrap().starts_with(&current));
+
+        fs::remove_file(path).unwrap();
+        fs::remove_file(backup).unwrap();
+    }
+}
@@ -0,0 +1,153 @@
+use crate::helpers::safe_ratio;
+use crate::log_data::LogData;
+
+/// Number of latest status samples compared against the older ones of the same URL.
+pub const RECENT_WINDOW: usize = 20;
+/// Increase of the 5xx ratio (recent minus baseline) that marks a URL as newly failing.
+pub const FAILURE_JUMP: f64 = 0.3;
+
+/// Number of latest size samples compared against the older ones of the same URL.
+pub const SIZE_RECENT_WINDOW: usize = 10;
+/// Share of its usual average size below which a URL's recent responses count as abnormally small.
+pub const SIZE_DROP_RATIO: f64 = 0.1;
+/// Average size a URL must normally return for a drop to matter; tiny responses vary too much.
+const MIN_BASELINE_SIZE: f64 = 1024.0;
+
+/// A URL whose recent share of 5xx responses rose well above its own history.
+pub struct FailingUrl {
+    pub url: String,
+    pub baseline_ratio: f64,
+    pub recent_ratio: f64,
+}
+
+pub fn server_error_ratio(statuses: &[u16]) -> f64 {
+    safe_ratio(statuses.iter().filter(|&&status| status >= 500).count() as f64, statuses.len() as f64)
+}
+
+/// Splits the status samples into baseline and recent parts and compares their 5xx ratios.
+/// URLs need at least `recent_window` baseline samples so a single early error is not a regression.
+pub fn compare_to_baseline(statuses: &[u16], recent_window: usize) -> Option<(f64, f64)> {
+    if recent_window == 0 || statuses.len() < recent_window * 2 {
+        return None;
+    }
+    let (baseline, recent) = statuses.split_at(statuses.len() - recent_window);
+    Some((server_error_ratio(baseline), server_error_ratio(recent)))
+}
+
+pub fn find_failing_urls(log_data: &LogData, recent_window: usize, jump: f64) -> Vec<FailingUrl> {
+    let mut failing: Vec<FailingUrl> = log_data
+        .visible_urls()
+        .filter_map(|(url, entry)| {
+            let (baseline_ratio, recent_ratio) = compare_to_baseline(&entry.statuses, recent_window)?;
+            (recent_ratio - baseline_ratio >= jump).then(|| FailingUrl { url: url.clone(), baseline_ratio, recent_ratio })
+        })
+        .collect();
+    failing.sort_by(|a, b| (b.recent_ratio - b.baseline_ratio).total_cmp(&(a.recent_ratio - a.baseline_ratio)));
+    failing
+}
+
+/// A URL whose recent responses are far smaller than usual, e.g. empty 200s from a broken backend.
+pub struct ShrinkingUrl {
+    pub url: String,
+    pub baseline_size: f64,
+    pub recent_size: f64,
+}
+
+fn average(sizes: &[u64]) -> f64 {
+    safe_ratio(sizes.iter().sum::<u64>() as f64, sizes.len() as f64)
+}
+
+/// Average size of the baseline and recent samples, once there are `recent_window` of each.
+pub fn compare_sizes_to_baseline(sizes: &[u64], recent_window: usize) -> Option<(f64, f64)> {
+    if recent_window == 0 || sizes.len() < recent_window * 2 {
+        return None;
+    }
+    let (baseline, recent) = sizes.split_at(sizes.len() - recent_window);
+    Some((average(baseline), average(recent)))
+}
+
+/// URLs normally returning at least 1KB whose recent average size fell below `ratio` of it.
+pub fn find_shrinking_urls(log_data: &LogData, recent_window: usize, ratio: f64) -> Vec<ShrinkingUrl> {
+    let mut shrinking: Vec<ShrinkingUrl> = log_data
+        .visible_urls()
+        .filter_map(|(url, entry)| {
+            let (baseline_size, recent_size) = compare_sizes_to_baseline(&entry.response_sizes.iter().copied().collect::<Vec<_>>(), recent_window)?;
+            (baseline_size >= MIN_BASELINE_SIZE && recent_size < baseline_size * ratio).then(|| ShrinkingUrl { url: url.clone(), baseline_size, recent_size })
+        })
+        .collect();
+    shrinking.sort_by(|a, b| (b.baseline_size - b.recent_size).total_cmp(&(a.baseline_size - a.recent_size)));
+    shrinking
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::log_data::RequestDetails;
+
+    fn add(log_data: &mut LogData, url: &str, status: u16) {
+        let details = RequestDetails { status: Some(status), ..Default::default() };
+        log_data.add_entry("10.0.0.1".to_string(), url.to_string(), String::new(), 0, "GET".to_string(), "example.com".to_string(), details, false);
+    }
+
+    fn add_sized(log_data: &mut LogData, url: &str, size: u64) {
+        let details = RequestDetails { status: Some(200), response_size: Some(size), ..Default::default() };
+        log_data.add_entry("10.0.0.1".to_string(), url.to_string(), String::new(), 0, "GET".to_string(), "example.com".to_string(), details, false);
+    }
+
+    #[test]
+    fn test_find_shrinking_urls() {
+        let mut log_data = LogData::new();
+        for i in 0..30 {
+            add_sized(&mut log_data, "/report", if i < 20 { 50_000 } else { 0 });
+            add_sized(&mut log_data, "/stable", 50_000);
+            add_sized(&mut log_data, "/tiny", if i < 20 { 200 } else { 0 });
+            add_sized(&mut log_data, "/halved", if i < 20 { 50_000 } else { 25_000 });
+        }
+        add_sized(&mut log_data, "/new", 50_000);
+
+        let shrinking = find_shrinking_urls(&log_data, SIZE_RECENT_WINDOW, SIZE_DROP_RATIO);
+
+        assert_eq!(shrinking.len(), 1);
+        assert_eq!(shrinking[0].url, "/report");
+        assert_eq!(shrinking[0].baseline_size, 50_000.0);
+        assert_eq!(shrinking[0].recent_size, 0.0);
+        assert_eq!(compare_sizes_to_baseline(&[100; 19], SIZE_RECENT_WINDOW), None);
+    }
+
+    #[test]
+    fn test_ratios_of_empty_samples() {
+        assert_eq!(server_error_ratio(&[]), 0.0);
+        assert_eq!(average(&[]), 0.0);
+        assert!(find_failing_urls(&LogData::new(), RECENT_WINDOW, 0.0).is_empty());
+    }
+
+    #[test]
+    fn test_compare_to_baseline() {
+        let mut statuses = vec![200; 30];
+        statuses.extend(vec![502; 10]);
+
+        assert_eq!(compare_to_baseline(&statuses, 20), Some((0.0, 0.5)));
+        assert_eq!(compare_to_baseline(&statuses[..30], 20), None);
+    }
+
+    #[test]
+    fn test_find_failing_urls() {
+        let mut log_data = LogData::new();
+        for _ in 0..40 {
+            add(&mut log_data, "/healthy", 200);
+        }
+        for i in 0..40 {
+            add(&mut log_data, "/checkout", if i < 25 { 200 } else { 503 });
+        }
+        for i in 0..40 {
+            add(&mut log_data, "/flaky", if i % 2 == 0 { 200 } else { 500 });
+        }
+
+        let failing = find_failing_urls(&log_data, RECENT_WINDOW, FAILURE_JUMP);
+
+        assert_eq!(failing.len(), 1);
+        assert_eq!(failing[0].url, "/checkout");
+        assert_eq!(failing[0].baseline_ratio, 0.0);
+        assert_eq!(failing[0].recent_ratio, 0.75);
+    }
+}
@@ -0,0 +1,191 @@
+use std::fmt::Write as _;
+use std::str::FromStr;
+use crate::log_data::{GroupBy, LogData, TopLimits};
+
+/// One exported statistic: a top IP or URL with its request count, 4xx/5xx responses, most
+/// frequent sampled status (`None` without a `status` group) and response bytes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExportRow {
+    pub kind: &'static str,
+    pub key: String,
+    pub requests: usize,
+    pub errors: usize,
+    pub status: Option<u16>,
+    pub bytes: u64,
+}
+
+/// Top IPs followed by the top URLs, busiest first.
+pub fn export_rows(log_data: &LogData, limits: TopLimits) -> Vec<ExportRow> {
+    [("ip", GroupBy::Ip), ("url", GroupBy::Url)]
+        .into_iter()
+        .flat_map(|(kind, group_by)| {
+            log_data.get_top_n_by(group_by, limits.for_group(group_by)).into_iter().map(move |(key, requests)| {
+                let entry = log_data.get_entry(group_by, &key);
+                ExportRow {
+                    kind,
+                    requests,
+                    errors: entry.map_or(0, |entry| entry.error_count),
+                    status: entry.and_then(|entry| entry.top_status()),
+                    bytes: entry.map_or(0, |entry| entry.total_bytes),
+                    key,
+                }
+            })
+        })
+        .collect()
+}
+
+/// Serializes export rows into one of the `--export-format` formats.
+pub trait Exporter {
+    fn export(&self, rows: &[ExportRow]) -> String;
+}
+
+pub struct CsvExporter;
+pub struct JsonExporter;
+pub struct NdjsonExporter;
+
+impl Exporter for CsvExporter {
+    fn export(&self, rows: &[ExportRow]) -> String {
+        let mut out = String::from("kind,key,requests,errors,status,bytes\n");
+        for row in rows {
+            let status = row.status.map(|status| status.to_string()).unwrap_or_default();
+            let _ = writeln!(out, "{},{},{},{},{},{}", row.kind, csv_field(&row.key), row.requests, row.errors, status, row.bytes);
+        }
+        out
+    }
+}
+
+impl Exporter for JsonExporter {
+    fn export(&self, rows: &[ExportRow]) -> String {
+        format!("[{}]\n", rows.iter().map(json_object).collect::<Vec<_>>().join(","))
+    }
+}
+
+impl Exporter for NdjsonExporter {
+    fn export(&self, rows: &[ExportRow]) -> String {
+        rows.iter().map(|row| json_object(row) + "\n").collect()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Csv,
+    Json,
+    Ndjson,
+}
+
+impl ExportFormat {
+    pub fn exporter(&self) -> Box<dyn Exporter> {
+        match self {
+            ExportFormat::Csv => Box::new(CsvExporter),
+            ExportFormat::Json => Box::new(JsonExporter),
+            ExportFormat::Ndjson => Box::new(NdjsonExporter),
+        }
+    }
+}
+
+impl FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "csv" => Ok(ExportFormat::Csv),
+            "json" => Ok(ExportFormat::Json),
+            "ndjson" => Ok(ExportFormat::Ndjson),
+            _ => Err(format!("unknown export format '{}', expected csv, json or ndjson", s)),
+        }
+    }
+}
+
+/// Quotes a CSV field when it contains a separator, a quote or a line break.
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn json_object(row: &ExportRow) -> String {
+    let status = row.status.map_or_else(|| "null".to_string(), |status| status.to_string());
+    format!(
+        "{{\"kind\":{},\"key\":{},\"requests\":{},\"errors\":{},\"status\":{},\"bytes\":{}}}",
+        json_string(row.kind),
+        json_string(&row.key),
+        row.requests,
+        row.errors,
+        status,
+        row.bytes
+    )
+}
+
+fn json_string(value: &str) -> String {
+    let mut out = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::log_data::RequestDetails;
+
+    fn rows() -> Vec<ExportRow> {
+        vec![
+            ExportRow { kind: "ip", key: "10.0.0.1".to_string(), requests: 2, errors: 1, status: Some(200), bytes: 1536 },
+            ExportRow { kind: "url", key: "/search?q=\"a,b\"".to_string(), requests: 1, errors: 0, status: None, bytes: 0 },
+        ]
+    }
+
+    #[test]
+    fn test_export_rows() {
+        let mut log_data = LogData::new();
+        for (ip, url, status, size) in [("10.0.0.1", "/a", 200, 1024), ("10.0.0.1", "/b", 404, 512), ("10.0.0.2", "/a", 502, 0), ("10.0.0.1", "/a", 200, 2048)] {
+            let details = RequestDetails { status: Some(status), response_size: Some(size), ..Default::default() };
+            log_data.add_entry(ip.to_string(), url.to_string(), String::new(), 0, "GET".to_string(), "example.com".to_string(), details, false);
+        }
+
+        assert_eq!(export_rows(&log_data, TopLimits::uniform(1)), vec![
+            ExportRow { kind: "ip", key: "10.0.0.1".to_string(), requests: 3, errors: 1, status: Some(200), bytes: 3584 },
+            ExportRow { kind: "url", key: "/a".to_string(), requests: 3, errors: 1, status: Some(200), bytes: 3072 },
+        ]);
+    }
+
+    #[test]
+    fn test_csv_export() {
+        assert_eq!(
+            ExportFormat::Csv.exporter().export(&rows()),
+            "kind,key,requests,errors,status,bytes\nip,10.0.0.1,2,1,200,1536\nurl,\"/search?q=\"\"a,b\"\"\",1,0,,0\n"
+        );
+    }
+
+    #[test]
+    fn test_json_export() {
+        assert_eq!(
+            ExportFormat::Json.exporter().export(&rows()),
+            "[{\"kind\":\"ip\",\"key\":\"10.0.0.1\",\"requests\":2,\"errors\":1,\"status\":200,\"bytes\":1536},{\"kind\":\"url\",\"key\":\"/search?q=\\\"a,b\\\"\",\"requests\":1,\"errors\":0,\"status\":null,\"bytes\":0}]\n"
+        );
+        assert_eq!(ExportFormat::Json.exporter().export(&[]), "[]\n");
+    }
+
+    #[test]
+    fn test_ndjson_export() {
+        assert_eq!(
+            ExportFormat::Ndjson.exporter().export(&rows()),
+            "{\"kind\":\"ip\",\"key\":\"10.0.0.1\",\"requests\":2,\"errors\":1,\"status\":200,\"bytes\":1536}\n{\"kind\":\"url\",\"key\":\"/search?q=\\\"a,b\\\"\",\"requests\":1,\"errors\":0,\"status\":null,\"bytes\":0}\n"
+        );
+        assert_eq!("xml".parse::<ExportFormat>(), Err("unknown export format 'xml', expected csv, json or ndjson".to_string()));
+    }
+}
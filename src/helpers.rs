@@ -1,22 +1,192 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::VecDeque;
+use std::hash::{Hash, Hasher};
 use std::fs::{File, OpenOptions};
 use std::io::{BufRead, BufReader, Read, Seek, SeekFrom};
-use std::path::PathBuf;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
 use chrono::{DateTime, FixedOffset, Offset, Utc};
 use log::error;
 use regex::Regex;
-use crate::log_data::LogData;
+use crate::log_data::{LogData, ParseFailureReason, RequestDetails};
+use crate::w3c::{self, W3cFields};
 
+/// Groups every regex must capture, in this order; named groups are numbered after them.
+const REQUIRED_GROUPS: [&str; 5] = ["client IP", "date/time", "domain", "request method", "URL"];
+
+/// Names that place the `REQUIRED_GROUPS` anywhere in the regex, e.g. `(?P<ip>\S+)` after the date.
+const CORE_GROUP_NAMES: [&str; 5] = ["ip", "date", "domain", "method", "url"];
+
+/// Whether the regex names all the `REQUIRED_GROUPS`, so their position in the line does not matter.
+fn names_core_groups(re: &Regex) -> bool {
+    CORE_GROUP_NAMES.iter().all(|name| re.capture_names().flatten().any(|group| group == *name))
+}
+
+/// Checked once at startup so a wrong `--regex` gives one clear error instead of failing on every line.
+pub fn validate_regex(pattern: &str) -> Result<(), String> {
+    let re = Regex::new(pattern).map_err(|e| format!("the regex does not compile: {}", e))?;
+    let groups = re.captures_len() - 1;
+    if groups < REQUIRED_GROUPS.len() && !names_core_groups(&re) {
+        return Err(format!(
+            "the regex has {} capture group(s) but at least {} are required, in this order: {} (or named {})",
+            groups,
+            REQUIRED_GROUPS.len(),
+            REQUIRED_GROUPS.iter().enumerate().map(|(i, name)| format!("{} {}", i + 1, name)).collect::<Vec<_>>().join(", "),
+            CORE_GROUP_NAMES.join(", ")
+        ));
+    }
+    Ok(())
+}
+
+/// Fields a regex extracts from one line: positional groups under their role, named groups by name.
+pub type CaptureFields = Vec<(String, String)>;
+
+/// Runs `pattern` over `lines` without adding anything to the analysis, to try a regex on one client's lines.
+/// Lines the regex does not match give `None`.
+pub fn preview_captures(pattern: &str, lines: &[String]) -> Result<Vec<Option<CaptureFields>>, String> {
+    let re = Regex::new(pattern).map_err(|e| format!("the regex does not compile: {}", e))?;
+    let named = names_core_groups(&re);
+    let labels: Vec<String> = re.capture_names().enumerate().skip(1)
+        .map(|(index, name)| match name {
+            Some(name) => CORE_GROUP_NAMES.iter().position(|core| *core == name).map_or(name, |role| REQUIRED_GROUPS[role]).to_string(),
+            None if named => format!("group {}", index),
+            None => REQUIRED_GROUPS.get(index - 1).map_or_else(|| format!("group {}", index), |role| role.to_string()),
+        })
+        .collect();
+    Ok(lines.iter().map(|line| {
+        re.captures(line).map(|caps| {
+            labels.iter().enumerate()
+                .filter_map(|(index, label)| caps.get(index + 1).map(|m| (label.clone(), m.as_str().to_string())))
+                .collect()
+        })
+    }).collect())
+}
+
+/// First line of a (possibly compressed) log, used as the sample for the live regex tester.
+pub fn read_first_line(file_path: &Path) -> std::io::Result<Option<String>> {
+    let mut reader = open_decoder(File::open(file_path)?, detect_compression(file_path)?)?;
+    let mut line = String::new();
+    Ok((reader.read_line(&mut line)? > 0).then(|| line.trim_end().to_string()))
+}
+
+/// Switches applied to every parsed line.
+#[derive(Debug, Clone, Default)]
+pub struct ParseOptions {
+    pub no_clear: bool,
+    pub canonicalize_urls: bool,
+    pub detect_repeats: bool,
+    /// Percentage of lines (1-100) to process, picked by a hash of the line.
+    pub sample_percent: Option<u8>,
+    /// Use the left-most public address of the `xff` group instead of the connection IP.
+    pub real_ip_from_xff: bool,
+    /// Store records without their raw line to save memory.
+    pub no_raw_lines: bool,
+    /// Only read lines `A..=B` (1-based, `--lines A:B`) instead of tailing the file.
+    pub line_range: Option<(usize, usize)>,
+    /// Leading part removed from every line before the main regex runs, e.g. a syslog header.
+    pub strip_prefix: Option<Regex>,
+    /// Keep only requests with one of these statuses (`--only-status`); empty keeps all.
+    pub only_status: Vec<StatusSpec>,
+    /// Drop requests with one of these statuses (`--exclude-status`).
+    pub exclude_status: Vec<StatusSpec>,
+    /// Parse lines as a W3C extended log (`--w3c`) instead of with the regex.
+    pub w3c: bool,
+    /// Column layout of the file being read, taken from its `#Fields:` directive by `tail_file`.
+    pub w3c_fields: Option<W3cFields>,
+}
+
+/// An HTTP status code such as `404`, or a whole class such as `4xx`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatusSpec {
+    Code(u16),
+    Class(u16),
+}
+
+impl StatusSpec {
+    pub fn matches(&self, status: u16) -> bool {
+        match self {
+            StatusSpec::Code(code) => status == *code,
+            StatusSpec::Class(class) => status / 100 == *class,
+        }
+    }
+}
+
+/// Parses a comma-separated list of status codes and classes, e.g. `200,3xx,404`.
+pub fn parse_status_specs(spec: &str) -> Result<Vec<StatusSpec>, String> {
+    spec.split(',').map(|item| {
+        let item = item.trim().to_lowercase();
+        let invalid = || format!("'{}' is not a status code (e.g. 404) or class (e.g. 4xx)", item);
+        match item.strip_suffix("xx") {
+            Some(class) => match (class.len(), class.parse::<u16>()) {
+                (1, Ok(class @ 1..=5)) => Ok(StatusSpec::Class(class)),
+                _ => Err(invalid()),
+            },
+            None => match item.parse::<u16>() {
+                Ok(code @ 100..=599) => Ok(StatusSpec::Code(code)),
+                _ => Err(invalid()),
+            },
+        }
+    }).collect()
+}
+
+/// Whether a request with this status passes `--only-status` and `--exclude-status`.
+/// Requests without a status only pass when `--only-status` is not given.
+pub fn status_allowed(status: Option<u16>, options: &ParseOptions) -> bool {
+    match status {
+        Some(status) => {
+            (options.only_status.is_empty() || options.only_status.iter().any(|spec| spec.matches(status)))
+                && !options.exclude_status.iter().any(|spec| spec.matches(status))
+        }
+        None => options.only_status.is_empty(),
+    }
+}
+
+/// Compiles `--strip-prefix`, anchored so it only ever removes text at the start of a line.
+pub fn compile_strip_prefix(pattern: &str) -> Result<Regex, String> {
+    Regex::new(&format!("^(?:{})", pattern)).map_err(|e| format!("the regex does not compile: {}", e))
+}
+
+#[allow(clippy::too_many_arguments)]
 pub async fn tail_file(
     file_path: &PathBuf,
     count: isize,
     regex_pattern: &str,
     date_format: &str,
     log_data: &Arc<Mutex<LogData>>,
-    no_clear: bool,
+    options: &ParseOptions,
     last_processed_line: Option<usize>,
     progress_callback: impl Fn(f64) + Send,
 ) -> std::io::Result<Option<usize>> {
+    let compression = detect_compression(file_path)?;
+    // The header is read on every call since tailing from the end never sees the top of the file.
+    let w3c_options;
+    let options = if options.w3c {
+        let fields = w3c::read_fields(&mut open_decoder(File::open(file_path)?, compression)?)?;
+        w3c_options = ParseOptions { w3c_fields: Some(fields), ..options.clone() };
+        &w3c_options
+    } else {
+        options
+    };
+    if let Some(range) = options.line_range {
+        // A fixed range does not change when the file grows, so it is read once.
+        if last_processed_line.is_some() {
+            return Ok(last_processed_line);
+        }
+        let mut reader = open_decoder(File::open(file_path)?, compression)?;
+        process_line_range(&mut reader, range, regex_pattern, date_format, log_data, options, &progress_callback).await?;
+        return Ok(Some(range.1));
+    }
+    if compression != Compression::None {
+        // Compressed archives are rotated logs and never grow, so they are read once.
+        if last_processed_line.is_some() {
+            return Ok(last_processed_line);
+        }
+        let processed = process_compressed_file(file_path, compression, count, regex_pattern, date_format, log_data, options, &progress_callback).await?;
+        return Ok(Some(processed));
+    }
+
     let file = OpenOptions::new()
         .read(true)
         .open(file_path)?;
@@ -24,24 +194,156 @@ pub async fn tail_file(
     let metadata = file.metadata()?;
     let file_size = metadata.len() as f64;
     let mut reader = BufReader::new(file);
-    let mut last_processed = last_processed_line.clone();
+    let mut last_processed = last_processed_line;
 
     if let Some(ref last_line) = last_processed_line {
-        set_reader_to_last_processed_line(&mut reader, last_line.clone(), &progress_callback, file_size).await?;
+        set_reader_to_last_processed_line(&mut reader, *last_line, &progress_callback, file_size).await?;
     } else if count > 0 {
-        process_last_n_lines(&mut reader, count, regex_pattern, date_format, log_data, no_clear, &mut last_processed, &progress_callback, file_size).await?;
+        process_last_n_lines(&mut reader, count, regex_pattern, date_format, log_data, options, &mut last_processed, &progress_callback, file_size).await?;
+        progress_callback(1.0);
     } else if count == -1 {
-        process_all_lines_from_start(&mut reader, regex_pattern, date_format, log_data, no_clear, &mut last_processed, &progress_callback, file_size).await?;
+        process_all_lines_from_start(&mut reader, regex_pattern, date_format, log_data, options, &mut last_processed, &progress_callback, file_size).await?;
+        progress_callback(1.0);
     } else {
         set_last_processed_to_last_line(&mut reader, &mut last_processed).await?;
     }
 
-    process_new_lines(&mut reader, regex_pattern, date_format, log_data, no_clear, &mut last_processed, &progress_callback, file_size).await?;
+    process_new_lines(&mut reader, regex_pattern, date_format, log_data, options, &mut last_processed, &progress_callback, file_size).await?;
 
 
     Ok(last_processed)
 }
 
+/// Re-resolves a (possibly symlinked) log path and reports whether it now points at another file,
+/// e.g. after rotation repointed `current.log`. The new target should then be read from its first line.
+pub fn target_changed(file_path: &Path, known_target: &mut Option<PathBuf>) -> bool {
+    let Ok(target) = std::fs::canonicalize(file_path) else {
+        return false;
+    };
+    let changed = known_target.as_ref().is_some_and(|known| *known != target);
+    *known_target = Some(target);
+    changed
+}
+
+/// Polls every `interval` until `file_path` exists (`--wait-for-file`).
+pub async fn wait_for_file(file_path: &Path, interval: Duration) {
+    while !file_path.exists() {
+        tokio::time::sleep(interval).await;
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    None,
+    Bzip2,
+    Zstd,
+}
+
+const BZIP2_MAGIC: &[u8] = b"BZh";
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xB5, 0x2F, 0xFD];
+
+pub fn detect_compression(file_path: &Path) -> std::io::Result<Compression> {
+    match file_path.extension().and_then(|ext| ext.to_str()) {
+        Some("bz2") => return Ok(Compression::Bzip2),
+        Some("zst") | Some("zstd") => return Ok(Compression::Zstd),
+        _ => {}
+    }
+
+    let mut magic = [0u8; 4];
+    let mut file = File::open(file_path)?;
+    let read = file.read(&mut magic)?;
+
+    if read >= ZSTD_MAGIC.len() && magic.starts_with(ZSTD_MAGIC) {
+        Ok(Compression::Zstd)
+    } else if read >= BZIP2_MAGIC.len() && magic.starts_with(BZIP2_MAGIC) {
+        Ok(Compression::Bzip2)
+    } else {
+        Ok(Compression::None)
+    }
+}
+
+fn open_decoder(file: File, compression: Compression) -> std::io::Result<Box<dyn BufRead + Send>> {
+    Ok(match compression {
+        Compression::Bzip2 => Box::new(BufReader::new(bzip2::read::MultiBzDecoder::new(file))),
+        Compression::Zstd => Box::new(BufReader::new(zstd::stream::read::Decoder::new(file)?)),
+        Compression::None => Box::new(BufReader::new(file)),
+    })
+}
+
+/// Streams a compressed log through its decoder. Only the last `count` lines are kept in memory
+/// when a count is given; `0` and `-1` both read the whole archive since it will not grow.
+#[allow(clippy::too_many_arguments)]
+async fn process_compressed_file(
+    file_path: &Path,
+    compression: Compression,
+    count: isize,
+    regex_pattern: &str,
+    date_format: &str,
+    log_data: &Arc<Mutex<LogData>>,
+    options: &ParseOptions,
+    progress_callback: &impl Fn(f64),
+) -> std::io::Result<usize> {
+    let mut reader = open_decoder(File::open(file_path)?, compression)?;
+    let mut line_number = 0;
+    let mut line = String::new();
+
+    if count > 0 {
+        let mut tail: VecDeque<String> = VecDeque::with_capacity(count as usize);
+        while reader.read_line(&mut line)? > 0 {
+            if tail.len() == count as usize {
+                tail.pop_front();
+            }
+            tail.push_back(std::mem::take(&mut line));
+            line_number += 1;
+        }
+        let total_lines = tail.len();
+        for (index, line) in tail.iter().enumerate() {
+            process_line(line, regex_pattern, date_format, log_data, options).await?;
+            progress_callback(((index + 1) as f64 / total_lines as f64).min(1.0));
+        }
+    } else {
+        while reader.read_line(&mut line)? > 0 {
+            process_line(&line, regex_pattern, date_format, log_data, options).await?;
+            line.clear();
+            line_number += 1;
+        }
+    }
+
+    progress_callback(1.0);
+    Ok(line_number)
+}
+
+/// Processes lines `from..=to` (1-based) and stops reading after `to`.
+/// A range that runs past the end of the file is an error, naming the file's line count.
+#[allow(clippy::too_many_arguments)]
+async fn process_line_range(
+    reader: &mut impl BufRead,
+    (from, to): (usize, usize),
+    regex_pattern: &str,
+    date_format: &str,
+    log_data: &Arc<Mutex<LogData>>,
+    options: &ParseOptions,
+    progress_callback: &impl Fn(f64),
+) -> std::io::Result<()> {
+    let mut line = String::new();
+    let mut line_number = 0;
+    while line_number < to {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("--lines {}:{} is out of bounds, the file has {} lines", from, to, line_number),
+            ));
+        }
+        line_number += 1;
+        if line_number >= from {
+            process_line(&line, regex_pattern, date_format, log_data, options).await?;
+            progress_callback((line_number - from + 1) as f64 / (to - from + 1) as f64);
+        }
+    }
+    Ok(())
+}
+
 async fn set_last_processed_to_last_line(reader: &mut BufReader<File>, last_processed: &mut Option<usize>) -> std::io::Result<()> {
     let mut buffer = String::new();
     reader.seek(SeekFrom::Start(0))?;
@@ -72,13 +374,14 @@ async fn set_reader_to_last_processed_line(reader: &mut BufReader<File>, last_li
     Ok(())
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn process_last_n_lines(
     reader: &mut BufReader<File>,
     count: isize,
     regex_pattern: &str,
     date_format: &str,
     log_data: &Arc<Mutex<LogData>>,
-    no_clear: bool,
+    options: &ParseOptions,
     last_processed: &mut Option<usize>,
     progress_callback: &impl Fn(f64),
     _file_size: f64,
@@ -93,7 +396,7 @@ async fn process_last_n_lines(
     let mut processed_lines = 0;
 
     for (index, line) in lines[start..].iter().enumerate() {
-        process_line(line, regex_pattern, date_format, log_data, no_clear).await?;
+        process_line(line, regex_pattern, date_format, log_data, options).await?;
         processed_lines += 1;
         progress_callback((processed_lines as f64 / total_lines as f64).min(1.0));
         *last_processed = Some(start + index);
@@ -102,12 +405,13 @@ async fn process_last_n_lines(
     Ok(())
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn process_all_lines_from_start(
     reader: &mut BufReader<File>,
     regex_pattern: &str,
     date_format: &str,
     log_data: &Arc<Mutex<LogData>>,
-    no_clear: bool,
+    options: &ParseOptions,
     last_processed: &mut Option<usize>,
     progress_callback: &impl Fn(f64),
     file_size: f64,
@@ -118,7 +422,7 @@ async fn process_all_lines_from_start(
 
     let mut line = String::new();
     while reader.read_line(&mut line)? > 0 {
-        process_line(&line, regex_pattern, date_format, log_data, no_clear).await?;
+        process_line(&line, regex_pattern, date_format, log_data, options).await?;
         processed_bytes += line.len();
         line.clear();
         progress_callback((processed_bytes as f64 / file_size).min(1.0));
@@ -129,12 +433,13 @@ async fn process_all_lines_from_start(
     Ok(())
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn process_new_lines(
     reader: &mut BufReader<File>,
     regex_pattern: &str,
     date_format: &str,
     log_data: &Arc<Mutex<LogData>>,
-    no_clear: bool,
+    options: &ParseOptions,
     last_processed: &mut Option<usize>,
     progress_callback: &impl Fn(f64),
     file_size: f64,
@@ -144,7 +449,7 @@ async fn process_new_lines(
     let mut line_number = last_processed.unwrap_or(0);
 
     while reader.read_line(&mut line)? > 0 {
-        process_line(&line, regex_pattern, date_format, log_data, no_clear).await?;
+        process_line(&line, regex_pattern, date_format, log_data, options).await?;
         processed_bytes += line.len();
         line.clear();
         progress_callback((processed_bytes as f64 / file_size).min(1.0));
@@ -160,38 +465,943 @@ pub async fn process_line(
     regex_pattern: &str,
     date_format: &str,
     log_data: &Arc<Mutex<LogData>>,
-    no_clear: bool,
+    options: &ParseOptions,
 ) -> std::io::Result<()> {
+    // `read_line` keeps the line ending, and logs from Windows end lines with `\r\n`.
+    let line = line.trim_end_matches(['\n', '\r']);
+    let line = match &options.strip_prefix {
+        Some(prefix) => prefix.find(line).map_or(line, |m| &line[m.end()..]),
+        None => line,
+    };
+    if options.sample_percent.is_some_and(|percent| !in_sample(line, percent)) {
+        return Ok(());
+    }
+    if let Some(fields) = &options.w3c_fields {
+        // Directives (`#Fields:`, `#Date:`...) are repeated whenever the server restarts.
+        if line.starts_with('#') {
+            return Ok(());
+        }
+        match fields.parse_line(line) {
+            Some(record) => {
+                let timestamp = w3c::parse_timestamp(&record.datetime).unwrap_or_else(|e| {
+                    log_data.lock().unwrap().record_parse_failure(line, ParseFailureReason::InvalidDate(format!("'{}' is not a W3C date and time: {}", record.datetime, e)));
+                    Utc::now().timestamp()
+                });
+                add_request(line, record.ip, record.xff.as_deref(), &record.datetime, timestamp, record.domain, record.method, record.url, record.details, log_data, options);
+            }
+            None if line.trim().is_empty() => {}
+            None => log_data.lock().unwrap().record_parse_failure(line, ParseFailureReason::NoMatch),
+        }
+        return Ok(());
+    }
     let re = Regex::new(regex_pattern).map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
     if let Some(caps) = re.captures(line) {
-        let (ip, datetime_str, request_domain, request_type, url) = extract_captures(&caps);
-
-        let datetime = parse_datetime(&datetime_str, date_format);
-
-        let mut log_data = log_data.lock().unwrap();
-        log_data.add_entry(ip, url, line.to_string(), datetime.timestamp(), request_type, request_domain, no_clear);
+        let (ip, datetime_str, request_domain, request_type, url) = extract_captures(&caps, names_core_groups(&re));
+        let datetime = parse_datetime(&datetime_str, date_format).unwrap_or_else(|e| {
+            log_data.lock().unwrap().record_parse_failure(line, ParseFailureReason::InvalidDate(format!("'{}' does not match {}: {}", datetime_str, date_format, e)));
+            Utc::now().with_timezone(&Utc.fix())
+        });
+        let details = extract_details(&caps, line, &url);
+        let xff = caps.name("xff").map(|xff| xff.as_str());
+        add_request(line, ip, xff, &datetime_str, datetime.timestamp(), request_domain, request_type, url, details, log_data, options);
     } else {
         error!("No match for line: {}", line);
+        if !line.trim().is_empty() {
+            log_data.lock().unwrap().record_parse_failure(line, ParseFailureReason::NoMatch);
+        }
     }
 
     Ok(())
 }
 
-fn extract_captures(caps: &regex::Captures) -> (String, String, String, String, String) {
-    (
-        caps.get(1).map_or("", |m| m.as_str()).to_string(),
-        caps.get(2).map_or("", |m| m.as_str()).to_string(),
-        caps.get(3).map_or("", |m| m.as_str()).to_string(),
-        caps.get(4).map_or("", |m| m.as_str()).to_string(),
-        caps.get(5).map_or("", |m| m.as_str()).to_string(),
-    )
+/// Applies the `ParseOptions` switches to a parsed line and stores it, unless its status is filtered out.
+#[allow(clippy::too_many_arguments)]
+fn add_request(
+    line: &str,
+    mut ip: String,
+    xff: Option<&str>,
+    datetime_str: &str,
+    timestamp: i64,
+    mut request_domain: String,
+    request_type: String,
+    mut url: String,
+    mut details: RequestDetails,
+    log_data: &Arc<Mutex<LogData>>,
+    options: &ParseOptions,
+) {
+    if options.real_ip_from_xff {
+        if let Some(client_ip) = xff.and_then(client_ip_from_xff) {
+            ip = client_ip;
+        }
+    }
+    if !status_allowed(details.status, options) {
+        return;
+    }
+    if options.detect_repeats {
+        details.repeat_key = Some(repeat_key(line, datetime_str));
+    }
+    if options.canonicalize_urls {
+        url = canonicalize_url(&url);
+        request_domain = strip_www(&request_domain).to_string();
+    }
+
+    let mut log_data = log_data.lock().unwrap();
+    let log_line = if options.no_raw_lines { String::new() } else { line.to_string() };
+    log_data.add_entry(ip, url, log_line, timestamp, request_type, request_domain, details, options.no_clear);
+}
+
+/// The `REQUIRED_GROUPS` of a match: by name when the regex names them all, otherwise by position.
+fn extract_captures(caps: &regex::Captures, named: bool) -> (String, String, String, String, String) {
+    let group = |role: usize| {
+        let m = if named { caps.name(CORE_GROUP_NAMES[role]) } else { caps.get(role + 1) };
+        m.map_or("", |m| m.as_str()).to_string()
+    };
+    (group(0), group(1), group(2), group(3), group(4))
+}
+
+/// The left-most public address of an `X-Forwarded-For` chain, i.e. the client as seen by the
+/// first proxy. Private, loopback and malformed hops are skipped.
+fn client_ip_from_xff(xff: &str) -> Option<String> {
+    xff.split(',')
+        .filter_map(|hop| hop.trim().parse::<IpAddr>().ok())
+        .find(is_public_ip)
+        .map(|ip| ip.to_string())
+}
+
+fn is_public_ip(ip: &IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            let carrier_grade_nat = a == 100 && (64..128).contains(&b);
+            !(ip.is_private() || ip.is_loopback() || ip.is_link_local() || ip.is_unspecified() || ip.is_broadcast() || carrier_grade_nat)
+        }
+        IpAddr::V6(ip) => {
+            let first = ip.segments()[0];
+            let unique_local = first & 0xfe00 == 0xfc00;
+            let link_local = first & 0xffc0 == 0xfe80;
+            !(ip.is_loopback() || ip.is_unspecified() || unique_local || link_local)
+        }
+    }
+}
+
+/// Nginx logs `-` when no body was sent; that is a zero-byte response, not a missing value.
+fn parse_response_size(size: &str) -> Option<u64> {
+    match size {
+        "-" => Some(0),
+        size => size.parse().ok(),
+    }
+}
+
+/// Reads the optional named groups, e.g. `(?P<size>\d+)`, that a custom regex may provide.
+fn extract_details(caps: &regex::Captures, line: &str, url: &str) -> RequestDetails {
+    let user_agent = caps.name("ua").map(|m| m.as_str().to_string()).or_else(|| extract_user_agent(line, url));
+    let referer = caps.name("referer").map(|m| m.as_str().to_string()).or_else(|| extract_referer(line, url));
+    RequestDetails {
+        response_size: caps.name("size").and_then(|m| parse_response_size(m.as_str())),
+        request_size: caps.name("req_size").and_then(|m| parse_response_size(m.as_str())),
+        response_time: caps.name("rt").and_then(|m| m.as_str().parse().ok()),
+        status: caps.name("status").and_then(|m| m.as_str().parse().ok()),
+        user_agent: user_agent.clone(),
+        referer: referer.as_deref().and_then(referer_host),
+        empty_referer: referer.as_deref().is_some_and(|referer| referer.is_empty() || referer == "-"),
+        query: caps.name("query").map(|m| m.as_str().to_string()).or_else(|| extract_query(line, url)),
+        repeat_key: None,
+        is_bot: user_agent.as_deref().is_some_and(is_bot),
+    }
+}
+
+/// Deterministic `--sample` decision: the same line is always kept or always skipped.
+fn in_sample(line: &str, percent: u8) -> bool {
+    let mut hasher = DefaultHasher::new();
+    line.trim_end().hash(&mut hasher);
+    hasher.finish() % 100 < percent as u64
+}
+
+/// Identical requests differ only in their timestamp, so it is left out of the hash.
+fn repeat_key(line: &str, datetime_str: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    line.trim_end().replacen(datetime_str, "", 1).hash(&mut hasher);
+    hasher.finish()
+}
+
+/// The default regexes drop the query string from the URL group, so look for it right after the URL.
+fn extract_query(line: &str, url: &str) -> Option<String> {
+    if url.is_empty() {
+        return None;
+    }
+    let start = line.find(&format!("{}?", url))? + url.len() + 1;
+    let query: String = line[start..].chars().take_while(|c| !c.is_whitespace() && *c != '"').collect();
+    (!query.is_empty()).then_some(query)
+}
+
+/// Without a `ua` group, takes the user agent from the combined format: the second quoted field after the request.
+fn extract_user_agent(line: &str, url: &str) -> Option<String> {
+    combined_field(line, url, 2)
+}
+
+/// Without a `referer` group, takes the referer from the combined format: the quoted field right after
+/// the request, kept as `-` when the client sent none.
+fn extract_referer(line: &str, url: &str) -> Option<String> {
+    quoted_field(line, url, 1)
+}
+
+/// The quoted field `offset` places after the one holding the request; `-` means the field is empty.
+fn combined_field(line: &str, url: &str, offset: usize) -> Option<String> {
+    quoted_field(line, url, offset).filter(|field| !field.is_empty() && field != "-")
+}
+
+fn quoted_field(line: &str, url: &str, offset: usize) -> Option<String> {
+    if url.is_empty() {
+        return None;
+    }
+    let fields = quoted_fields(line);
+    let request = fields.iter().position(|field| field.contains(url))?;
+    fields.into_iter().nth(request + offset)
+}
+
+/// `numerator / denominator`, or `0.0` when the denominator is zero, so ratios of empty datasets stay finite.
+pub fn safe_ratio(numerator: f64, denominator: f64) -> f64 {
+    if denominator == 0.0 {
+        0.0
+    } else {
+        numerator / denominator
+    }
+}
+
+/// A count with `,` between groups of three digits (`1,234,567`), or plain when `thousands` is off (`--no-thousands`).
+pub fn format_count(count: usize, thousands: bool) -> String {
+    let digits = count.to_string();
+    if !thousands {
+        return digits;
+    }
+    let mut out = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            out.push(',');
+        }
+        out.push(digit);
+    }
+    out
+}
+
+/// Lowercase host of a referer URL without `www.`, userinfo or port, e.g. `semalt.com`.
+pub fn referer_host(referer: &str) -> Option<String> {
+    if referer == "-" {
+        return None;
+    }
+    let rest = referer.split_once("://").map_or(referer, |(_, rest)| rest);
+    let authority = rest.split(['/', '?', '#']).next()?;
+    let host = authority.rsplit('@').next()?;
+    let host = host.split(':').next()?.trim_end_matches('.');
+    let host = strip_www(host).to_ascii_lowercase();
+    (!host.is_empty()).then_some(host)
+}
+
+/// Lowercase user agent fragments of crawlers, monitors and scripted clients.
+const BOT_INDICATORS: [&str; 12] = [
+    "bot", "crawl", "spider", "slurp", "scrapy", "curl/", "wget/", "python-requests", "go-http-client", "headlesschrome", "facebookexternalhit", "monitor",
+];
+
+/// Whether a user agent belongs to a crawler or script rather than a browser.
+pub fn is_bot(user_agent: &str) -> bool {
+    let user_agent = user_agent.to_ascii_lowercase();
+    BOT_INDICATORS.iter().any(|indicator| user_agent.contains(indicator))
+}
+
+/// Splits out the `"..."` fields of a line. A backslash escapes the next character,
+/// so an agent like `Mozilla \"x\"` does not shift the boundaries of the following fields.
+fn quoted_fields(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut current: Option<String> = None;
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match (&mut current, c) {
+            (None, '"') => current = Some(String::new()),
+            (None, _) => {}
+            (Some(field), '\\') => {
+                if let Some(escaped) = chars.next() {
+                    field.push(escaped);
+                }
+            }
+            (Some(_), '"') => fields.extend(current.take()),
+            (Some(field), _) => field.push(c),
+        }
+    }
+    fields
+}
+
+/// Collapses `/about/`, `https://www.example.com/about` and `/about` into one key.
+pub fn canonicalize_url(url: &str) -> String {
+    let mut url = url;
+    for scheme in ["https://", "http://"] {
+        if url.get(..scheme.len()).is_some_and(|prefix| prefix.eq_ignore_ascii_case(scheme)) {
+            url = strip_www(&url[scheme.len()..]);
+            url = url.find('/').map_or("/", |index| &url[index..]);
+            break;
+        }
+    }
+    let trimmed = url.trim_end_matches('/');
+    if trimmed.is_empty() { "/".to_string() } else { trimmed.to_string() }
+}
+
+/// Decodes the `%XX` escapes of a URL once, e.g. `..%2f` to `../`. Escapes of control characters
+/// stay encoded so a decoded URL cannot drive the terminal; invalid UTF-8 is replaced.
+pub fn percent_decode(url: &str) -> String {
+    if !url.contains('%') {
+        return url.to_string();
+    }
+    let bytes = url.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| bytes.get(i + 1..i + 3))
+            .flatten()
+            .filter(|hex| hex.iter().all(u8::is_ascii_hexdigit))
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok())
+            .filter(|byte| !byte.is_ascii_control());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
 }
 
-fn parse_datetime(datetime_str: &str, date_format: &str) -> DateTime<FixedOffset> {
-    DateTime::parse_from_str(&datetime_str, date_format)
-        .or_else(|_| DateTime::parse_from_str(&datetime_str, "%d/%b/%Y:%H:%M %S")
+fn strip_www(host: &str) -> &str {
+    match host.get(..4) {
+        Some(prefix) if prefix.eq_ignore_ascii_case("www.") => &host[4..],
+        _ => host,
+    }
+}
+
+/// Parses `--lines A:B` into a 1-based inclusive range.
+pub fn parse_line_range(spec: &str) -> Result<(usize, usize), String> {
+    let (from, to) = spec.split_once(':').ok_or_else(|| format!("expected A:B, got '{}'", spec))?;
+    let parse = |value: &str| value.trim().parse::<usize>().map_err(|_| format!("'{}' is not a line number", value));
+    let (from, to) = (parse(from)?, parse(to)?);
+    if from == 0 {
+        return Err("line numbers start at 1".to_string());
+    }
+    if from > to {
+        return Err(format!("the first line ({}) is after the last one ({})", from, to));
+    }
+    Ok((from, to))
+}
+
+/// Parses a relative window such as `15m`, `1h`, `2d` or `last 30s` into seconds.
+pub fn parse_time_window(input: &str) -> Option<i64> {
+    let input = input.trim().trim_start_matches("last").trim();
+    let split = input.find(|c: char| !c.is_ascii_digit())?;
+    let value: i64 = input[..split].parse().ok()?;
+    let unit = match input[split..].trim() {
+        "s" | "sec" => 1,
+        "m" | "min" => 60,
+        "h" | "hour" => 3600,
+        "d" | "day" => 86400,
+        _ => return None,
+    };
+    (value > 0).then_some(value * unit)
+}
+
+/// Tries `date_format` and, when it is a comma-separated list (`--date-format "A,B"`), each of
+/// its formats in order, so logs mixing two timestamp layouts parse fully. A format that itself
+/// contains a comma still works since the whole string is tried first. Falls back to the legacy
+/// `%H:%M %S` layout. The error is the one of the whole `date_format`.
+fn parse_datetime(datetime_str: &str, date_format: &str) -> Result<DateTime<FixedOffset>, chrono::ParseError> {
+    let alternatives = date_format.contains(',').then(|| date_format.split(',').map(str::trim)).into_iter().flatten();
+    parse_with_format(datetime_str, date_format)
+        .or_else(|e| alternatives.map(|format| parse_with_format(datetime_str, format)).find_map(Result::ok).ok_or(e))
+        .or_else(|e| DateTime::parse_from_str(datetime_str, "%d/%b/%Y:%H:%M %S")
             .map(|dt| dt.with_timezone(&Utc.fix()))
-            .map_err(|_: chrono::ParseError| ())
+            .map_err(|_| e)
         )
-        .unwrap_or_else(|_| Utc::now().with_timezone(&Utc.fix()))
-}
\ No newline at end of file
+}
+
+/// Parses with one format, also accepting fractional seconds (`12:00:00.123`) where it has `%S`.
+fn parse_with_format(datetime_str: &str, date_format: &str) -> Result<DateTime<FixedOffset>, chrono::ParseError> {
+    DateTime::parse_from_str(datetime_str, date_format)
+        .or_else(|e| match date_format.contains("%S") && !date_format.contains("%.f") {
+            true => DateTime::parse_from_str(datetime_str, &date_format.replacen("%S", "%S%.f", 1)),
+            false => Err(e),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::log_data::TopLimits;
+    use std::io::Write;
+
+    const REGEX: &str = r#"^(\S+) - ".+" \[(.*?)\] \d+\.\d+ "(\S+)" "(\S+) (\S+?)(?:\?.*?)? "#;
+    const DATE_FORMAT: &str = "%d/%b/%Y:%H:%M:%S %z";
+
+    fn sample_log() -> String {
+        (0..5)
+            .map(|i| format!("192.168.0.{} - \"-\" [16/Jul/2024:10:00:0{} +0000] 0.120 \"example.com\" \"GET /page{} HTTP/1.1\" 200 512\n", i, i, i))
+            .collect()
+    }
+
+    fn write_fixture(name: &str, data: &[u8]) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("logutil_{}_{}", std::process::id(), name));
+        File::create(&path).unwrap().write_all(data).unwrap();
+        path
+    }
+
+    async fn read_fixture(path: &PathBuf, count: isize) -> usize {
+        let log_data = Arc::new(Mutex::new(LogData::new()));
+        tail_file(path, count, REGEX, DATE_FORMAT, &log_data, &ParseOptions::default(), None, |_| {}).await.unwrap();
+        std::fs::remove_file(path).unwrap();
+        let total = log_data.lock().unwrap().total_requests;
+        total
+    }
+
+    #[tokio::test]
+    async fn test_process_line_reads_size_group() {
+        let log_data = Arc::new(Mutex::new(LogData::new()));
+        let regex = format!("{}\\S+ \\d+ (?P<size>\\d+)", REGEX);
+        for line in sample_log().lines() {
+            process_line(line, &regex, DATE_FORMAT, &log_data, &ParseOptions::default()).await.unwrap();
+        }
+
+        assert_eq!(log_data.lock().unwrap().get_response_size_histogram(), [5, 0, 0, 0]);
+    }
+
+    #[tokio::test]
+    async fn test_detect_repeats_ignores_timestamp() {
+        let log_data = Arc::new(Mutex::new(LogData::new()));
+        let options = ParseOptions { detect_repeats: true, ..Default::default() };
+        for second in 0..3 {
+            let line = format!("10.0.0.1 - \"-\" [16/Jul/2024:10:00:0{} +0000] 0.120 \"example.com\" \"GET /login HTTP/1.1\" 200 512", second);
+            process_line(&line, REGEX, DATE_FORMAT, &log_data, &options).await.unwrap();
+        }
+        process_line(sample_log().lines().next().unwrap(), REGEX, DATE_FORMAT, &log_data, &options).await.unwrap();
+
+        let log_data = log_data.lock().unwrap();
+        let mut repeats: Vec<usize> = log_data.by_ip["10.0.0.1"].repeated_lines.values().copied().collect();
+        repeats.sort();
+        assert_eq!(repeats, vec![3]);
+        assert!(log_data.by_ip["192.168.0.0"].repeated_lines.values().all(|&count| count == 1));
+    }
+
+    #[test]
+    fn test_validate_regex() {
+        assert_eq!(validate_regex(REGEX), Ok(()));
+
+        let error = validate_regex(r#"^(\S+) - ".+" \[(.*?)\] \d+\.\d+ "(\S+)""#).unwrap_err();
+        assert!(error.contains("has 3 capture group(s) but at least 5 are required"), "{}", error);
+        assert!(error.contains("1 client IP, 2 date/time, 3 domain, 4 request method, 5 URL"), "{}", error);
+
+        assert!(validate_regex("(unclosed").unwrap_err().starts_with("the regex does not compile"));
+        assert_eq!(validate_regex(VHOST_FIRST_REGEX), Ok(()));
+        assert!(validate_regex(r#"^(?P<domain>\S+) \[(?P<date>.*?)\] (?P<ip>\S+)"#).is_err());
+    }
+
+    /// Virtual host, then date, then client IP: the core fields named out of the positional order.
+    const VHOST_FIRST_REGEX: &str = r#"^(?P<domain>\S+) \[(?P<date>[^\]]+)\] (?P<ip>\S+) "(?P<method>\S+) (?P<url>[^\s?]+)\S* [^"]*" (?P<status>\d{3})"#;
+
+    #[tokio::test]
+    async fn test_ip_not_in_first_field() {
+        let log = "example.com [16/Jul/2024:10:00:00 +0000] 203.0.113.7 \"GET /.env HTTP/1.1\" 404\n\
+            example.com [16/Jul/2024:10:00:01 +0000] 203.0.113.7 \"GET /.git/config HTTP/1.1\" 404\n\
+            shop.example.com [16/Jul/2024:10:00:02 +0000] 198.51.100.2 \"POST /cart?id=1 HTTP/1.1\" 200\n";
+        let path = write_fixture("vhost_first.log", log.as_bytes());
+        let log_data = Arc::new(Mutex::new(LogData::new()));
+
+        tail_file(&path, -1, VHOST_FIRST_REGEX, DATE_FORMAT, &log_data, &ParseOptions::default(), None, |_| {}).await.unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let log_data = log_data.lock().unwrap();
+        assert_eq!(log_data.get_parse_failures().1, 0);
+        let (top_ips, top_urls) = log_data.get_top_n(TopLimits::uniform(10));
+        assert_eq!(top_ips[0].0, "203.0.113.7");
+        assert_eq!(top_ips[0].1.count, 2);
+        assert!(top_urls.iter().any(|(url, _)| url == "/cart"));
+        assert_eq!(log_data.by_ip["198.51.100.2"].last_requests.len(), 1);
+        assert_eq!(log_data.get_time_span(), Some((1_721_124_000, 1_721_124_002)));
+        let suspicious = crate::security::find_suspicious_ips(&log_data, &crate::security::SecurityConfig::default());
+        assert_eq!(suspicious, vec![("203.0.113.7".to_string(), 2)]);
+
+        let preview = preview_captures(VHOST_FIRST_REGEX, &[log.lines().next().unwrap().to_string()]).unwrap();
+        let fields = preview[0].as_ref().unwrap();
+        assert_eq!(fields[0], ("domain".to_string(), "example.com".to_string()));
+        assert_eq!(fields[2], ("client IP".to_string(), "203.0.113.7".to_string()));
+    }
+
+    #[test]
+    fn test_client_ip_from_xff() {
+        assert_eq!(client_ip_from_xff("10.1.2.3, 192.168.0.7, 203.0.113.50, 198.51.100.1"), Some("203.0.113.50".to_string()));
+        assert_eq!(client_ip_from_xff("unknown, 100.64.0.1, 2001:db8::5"), Some("2001:db8::5".to_string()));
+        assert_eq!(client_ip_from_xff("127.0.0.1, fd00::1, 172.16.0.1"), None);
+        assert_eq!(client_ip_from_xff("-"), None);
+    }
+
+    #[tokio::test]
+    async fn test_real_ip_from_xff() {
+        let log_data = Arc::new(Mutex::new(LogData::new()));
+        let regex = format!("{}.*\"(?P<xff>[^\"]*)\"$", REGEX);
+        let options = ParseOptions { real_ip_from_xff: true, ..Default::default() };
+        let line = r#"10.0.0.1 - "-" [16/Jul/2024:10:00:00 +0000] 0.120 "example.com" "GET /a HTTP/1.1" 200 512 "192.168.1.20, 203.0.113.50, 10.0.0.2""#;
+        process_line(line, &regex, DATE_FORMAT, &log_data, &options).await.unwrap();
+        let private_only = r#"10.0.0.1 - "-" [16/Jul/2024:10:00:00 +0000] 0.120 "example.com" "GET /a HTTP/1.1" 200 512 "192.168.1.20""#;
+        process_line(private_only, &regex, DATE_FORMAT, &log_data, &options).await.unwrap();
+
+        let log_data = log_data.lock().unwrap();
+        assert_eq!(log_data.by_ip["203.0.113.50"].count, 1);
+        assert_eq!(log_data.by_ip["10.0.0.1"].count, 1);
+    }
+
+    #[tokio::test]
+    async fn test_sample_ingests_fraction_of_lines() {
+        let log_data = Arc::new(Mutex::new(LogData::new()));
+        let options = ParseOptions { sample_percent: Some(10), ..Default::default() };
+        for i in 0..5000 {
+            let line = format!("10.0.{}.{} - \"-\" [16/Jul/2024:10:00:00 +0000] 0.120 \"example.com\" \"GET /page{} HTTP/1.1\" 200 512", i / 256, i % 256, i);
+            process_line(&line, REGEX, DATE_FORMAT, &log_data, &options).await.unwrap();
+        }
+
+        let total = log_data.lock().unwrap().total_requests;
+        assert!((400..=600).contains(&total), "sampled {} of 5000 lines", total);
+        assert!(in_sample("any line", 100));
+        assert_eq!(in_sample("same line", 50), in_sample("same line", 50));
+    }
+
+    #[tokio::test]
+    async fn test_records_parse_failures_with_reasons() {
+        let log_data = Arc::new(Mutex::new(LogData::new()));
+        log_data.lock().unwrap().set_max_parse_failures(2);
+        let lines = [
+            "garbage that is not an access log",
+            "10.0.0.1 - \"-\" [2024-07-16 10:00:00] 0.120 \"example.com\" \"GET /page HTTP/1.1\" 200 512",
+            "10.0.0.2 - \"-\" [16/Jul/2024:10:00:00 +0000] 0.120 \"example.com\" \"GET /page HTTP/1.1\" 200 512",
+            "still not a log line",
+            "",
+        ];
+        for line in lines {
+            process_line(line, REGEX, DATE_FORMAT, &log_data, &ParseOptions::default()).await.unwrap();
+        }
+
+        let log_data = log_data.lock().unwrap();
+        let (failures, total) = log_data.get_parse_failures();
+        assert_eq!(total, 3);
+        assert_eq!(failures.len(), 2);
+        assert_eq!(failures[0].line, "garbage that is not an access log");
+        assert_eq!(failures[0].reason, ParseFailureReason::NoMatch);
+        assert!(matches!(&failures[1].reason, ParseFailureReason::InvalidDate(error) if error.contains("'2024-07-16 10:00:00'")));
+        // A line with a bad date is still counted, stamped with the current time.
+        assert_eq!(log_data.total_requests, 2);
+    }
+
+    #[tokio::test]
+    async fn test_multiple_date_formats() {
+        let log_data = Arc::new(Mutex::new(LogData::new()));
+        let regex = r#"^(\S+) \[(.*?)\] "(\S+)" "(\S+) (\S+)""#;
+        let formats = "%d/%b/%Y:%H:%M:%S %z, %Y-%m-%dT%H:%M:%S%:z";
+        let lines = [
+            r#"10.0.0.1 [16/Jul/2024:10:00:00 +0000] "example.com" "GET /a""#,
+            r#"10.0.0.2 [2024-07-16T10:00:05+00:00] "example.com" "GET /b""#,
+            r#"10.0.0.3 [16/Jul/2024:10:00:10 +0000] "example.com" "GET /c""#,
+            r#"10.0.0.4 [2024-07-16T10:00:15.250+00:00] "example.com" "GET /d""#,
+        ];
+        for line in lines {
+            process_line(line, regex, formats, &log_data, &ParseOptions::default()).await.unwrap();
+        }
+
+        let log_data = log_data.lock().unwrap();
+        assert_eq!(log_data.total_requests, 4);
+        assert_eq!(log_data.get_parse_failures().1, 0);
+        assert_eq!(log_data.get_time_span(), Some((1_721_124_000, 1_721_124_015)));
+        assert_eq!(parse_datetime("Tue, 16 Jul 2024 10:00:00 +0000", "%a, %d %b %Y %H:%M:%S %z").unwrap().timestamp(), 1_721_124_000);
+        assert!(parse_datetime("16.07.2024", formats).is_err());
+    }
+
+    #[test]
+    fn test_parse_datetime_with_fractional_seconds() {
+        let expected = DateTime::parse_from_rfc3339("2024-01-01T12:00:00+00:00").unwrap();
+
+        assert_eq!(parse_datetime("01/Jan/2024:12:00:00.123 +0000", DATE_FORMAT).unwrap().timestamp(), expected.timestamp());
+        assert_eq!(parse_datetime("01/Jan/2024:12:00:00.123456 +0000", DATE_FORMAT).unwrap().timestamp(), expected.timestamp());
+        assert_eq!(parse_datetime("01/Jan/2024:12:00:00 +0000", DATE_FORMAT).unwrap().timestamp(), expected.timestamp());
+    }
+
+    #[tokio::test]
+    async fn test_dash_size_counts_as_zero_bytes() {
+        let log_data = Arc::new(Mutex::new(LogData::new()));
+        let regex = format!("{}\\S+ \\d+ (?P<size>\\d+|-)", REGEX);
+        let line = r#"10.0.0.1 - "-" [16/Jul/2024:10:00:00 +0000] 0.120 "example.com" "HEAD /page HTTP/1.1" 304 -"#;
+        process_line(line, &regex, DATE_FORMAT, &log_data, &ParseOptions::default()).await.unwrap();
+
+        let log_data = log_data.lock().unwrap();
+        assert_eq!(log_data.get_largest_responses(1), vec![("/page".to_string(), 0)]);
+        assert_eq!(log_data.get_response_size_histogram(), [1, 0, 0, 0]);
+        assert_eq!(parse_response_size("abc"), None);
+    }
+
+    #[tokio::test]
+    async fn test_no_raw_lines_still_indexes() {
+        let log_data = Arc::new(Mutex::new(LogData::new()));
+        let options = ParseOptions { no_raw_lines: true, ..Default::default() };
+        for line in sample_log().lines() {
+            process_line(line, REGEX, DATE_FORMAT, &log_data, &options).await.unwrap();
+        }
+
+        let log_data = log_data.lock().unwrap();
+        assert_eq!(log_data.total_requests, 5);
+        assert_eq!(log_data.get_unique_counts(), (5, 5));
+        assert_eq!(log_data.get_last_requests("192.168.0.3"), vec![String::new()]);
+        let recent = log_data.get_recent_requests();
+        assert_eq!(recent[0].url, "/page4");
+        assert!(recent.iter().all(|request| request.log_line.is_empty()));
+    }
+
+    #[test]
+    fn test_extract_details_without_named_groups() {
+        let re = Regex::new(REGEX).unwrap();
+        let line = sample_log();
+        let caps = re.captures(&line).unwrap();
+        let details = extract_details(&caps, &line, "/page0");
+
+        assert_eq!(details.response_size, None);
+        assert_eq!(details.response_time, None);
+        assert_eq!(details.query, None);
+    }
+
+    #[test]
+    fn test_extract_query_after_url() {
+        let line = r#"10.0.0.1 - "-" [16/Jul/2024:10:00:00 +0000] 0.120 "example.com" "GET /search?q=shoes&page=2 HTTP/1.1" 200 512"#;
+
+        assert_eq!(extract_query(line, "/search"), Some("q=shoes&page=2".to_string()));
+        assert_eq!(extract_query(line, "/other"), None);
+    }
+
+    #[test]
+    fn test_extract_user_agent_with_escaped_quotes() {
+        let line = r#"10.0.0.1 - "-" [16/Jul/2024:10:00:00 +0000] 0.120 "example.com" "GET /page HTTP/1.1" 200 512 "https://ref.example/?q=\"x\"" "Mozilla/5.0 \"crafted\" (X11)" "-""#;
+
+        assert_eq!(quoted_fields(line)[4], r#"Mozilla/5.0 "crafted" (X11)"#);
+        assert_eq!(extract_user_agent(line, "/page"), Some(r#"Mozilla/5.0 "crafted" (X11)"#.to_string()));
+        assert_eq!(extract_user_agent(sample_log().lines().next().unwrap(), "/page0"), None);
+    }
+
+    #[test]
+    fn test_safe_ratio() {
+        assert_eq!(safe_ratio(3.0, 4.0), 0.75);
+        assert_eq!(safe_ratio(3.0, 0.0), 0.0);
+        assert_eq!(safe_ratio(0.0, 0.0), 0.0);
+    }
+
+    #[test]
+    fn test_format_count() {
+        assert_eq!(format_count(0, true), "0");
+        assert_eq!(format_count(999, true), "999");
+        assert_eq!(format_count(1000, true), "1,000");
+        assert_eq!(format_count(123456, true), "123,456");
+        assert_eq!(format_count(1234567, true), "1,234,567");
+        assert_eq!(format_count(1234567, false), "1234567");
+    }
+
+    #[test]
+    fn test_is_bot() {
+        assert!(is_bot("Mozilla/5.0 (compatible; Googlebot/2.1; +http://www.google.com/bot.html)"));
+        assert!(is_bot("Mozilla/5.0 (compatible; bingbot/2.0)"));
+        assert!(is_bot("AhrefsBot/7.0"));
+        assert!(is_bot("curl/8.4.0"));
+        assert!(is_bot("python-requests/2.31.0"));
+        assert!(is_bot("Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 (KHTML, like Gecko) HeadlessChrome/120.0 Safari/537.36"));
+        assert!(!is_bot("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0 Safari/537.36"));
+        assert!(!is_bot("Mozilla/5.0 (iPhone; CPU iPhone OS 17_0 like Mac OS X) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/17.0 Mobile/15E148 Safari/604.1"));
+    }
+
+    #[test]
+    fn test_referer_host() {
+        let line = r#"10.0.0.1 - "-" [16/Jul/2024:10:00:00 +0000] 0.120 "example.com" "GET /page HTTP/1.1" 200 512 "https://www.Semalt.com/crawler.php?u=x" "Mozilla/5.0" "-""#;
+
+        assert_eq!(extract_referer(line, "/page"), Some("https://www.Semalt.com/crawler.php?u=x".to_string()));
+        assert_eq!(extract_referer(sample_log().lines().next().unwrap(), "/page0"), None);
+        assert_eq!(referer_host("https://www.Semalt.com/crawler.php?u=x"), Some("semalt.com".to_string()));
+        assert_eq!(referer_host("http://user@blog.example.org:8080"), Some("blog.example.org".to_string()));
+        assert_eq!(referer_host("android-app://com.google.android.gm/"), Some("com.google.android.gm".to_string()));
+        assert_eq!(referer_host("https://"), None);
+        assert_eq!(extract_referer(&line.replace("https://www.Semalt.com/crawler.php?u=x", "-"), "/page"), Some("-".to_string()));
+        assert_eq!(referer_host("-"), None);
+    }
+
+    #[test]
+    fn test_canonicalize_url() {
+        assert_eq!(canonicalize_url("/about/"), "/about");
+        assert_eq!(canonicalize_url("/about"), "/about");
+        assert_eq!(canonicalize_url("https://www.example.com/about/"), "/about");
+        assert_eq!(canonicalize_url("HTTP://example.com/about"), "/about");
+        assert_eq!(canonicalize_url("https://www.example.com"), "/");
+        assert_eq!(canonicalize_url("/"), "/");
+    }
+
+    #[test]
+    fn test_percent_decode() {
+        assert_eq!(percent_decode("/files/..%2f..%2Fetc/passwd"), "/files/../../etc/passwd");
+        assert_eq!(percent_decode("%2e%2e%2f"), "../");
+        assert_eq!(percent_decode("/caf%C3%A9"), "/café");
+        assert_eq!(percent_decode("%252e"), "%2e");
+        assert_eq!(percent_decode("/a%0d%0aSet-Cookie:%1b[2J"), "/a%0d%0aSet-Cookie:%1b[2J");
+        assert_eq!(percent_decode("/100%/%zz/%4"), "/100%/%zz/%4");
+        assert_eq!(percent_decode("/%ff"), "/\u{fffd}");
+    }
+
+    #[tokio::test]
+    async fn test_canonicalize_urls_collapses_variants() {
+        let log_data = Arc::new(Mutex::new(LogData::new()));
+        let options = ParseOptions { canonicalize_urls: true, ..Default::default() };
+        for (domain, url) in [("www.example.com", "/about/"), ("example.com", "/about"), ("example.com", "https://www.example.com/about")] {
+            let line = format!("10.0.0.1 - \"-\" [16/Jul/2024:10:00:00 +0000] 0.120 \"{}\" \"GET {} HTTP/1.1\" 200 512", domain, url);
+            process_line(&line, REGEX, DATE_FORMAT, &log_data, &options).await.unwrap();
+        }
+
+        let log_data = log_data.lock().unwrap();
+        let (_, top_urls) = log_data.get_top_n(TopLimits::uniform(10));
+        assert_eq!(top_urls.len(), 1);
+        assert_eq!(top_urls[0].0, "/about");
+        assert_eq!(top_urls[0].1.count, 3);
+        assert_eq!(top_urls[0].1.request_domain, "example.com");
+        assert!(top_urls[0].1.last_requests[0].contains("/about/"));
+    }
+
+    #[test]
+    fn test_preview_captures() {
+        let lines = vec![sample_log().lines().next().unwrap().to_string(), "odd format line".to_string()];
+        let pattern = r#"^(\S+) - ".+" \[(.*?)\] \d+\.\d+ "(\S+)" "(\S+) (\S+?) HTTP/1.1" (?P<status>\d{3})"#;
+
+        let preview = preview_captures(pattern, &lines).unwrap();
+
+        assert_eq!(preview.len(), 2);
+        let fields = preview[0].as_ref().unwrap();
+        assert_eq!(fields[0], ("client IP".to_string(), "192.168.0.0".to_string()));
+        assert_eq!(fields[4], ("URL".to_string(), "/page0".to_string()));
+        assert_eq!(fields[5], ("status".to_string(), "200".to_string()));
+        assert_eq!(preview[1], None);
+        assert!(preview_captures("(unclosed", &lines).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_strip_syslog_prefix() {
+        let log_data = Arc::new(Mutex::new(LogData::new()));
+        let options = ParseOptions { strip_prefix: Some(compile_strip_prefix(r"\w{3} +\d+ [\d:]+ \S+ nginx(\[\d+\])?: ").unwrap()), ..Default::default() };
+        let nginx_line = sample_log().lines().next().unwrap().to_string();
+
+        process_line(&format!("Jul 16 10:00:00 web-01 nginx[812]: {}", nginx_line), REGEX, DATE_FORMAT, &log_data, &options).await.unwrap();
+        process_line(&format!("Jul  6 10:00:01 web-02 nginx: {}", nginx_line), REGEX, DATE_FORMAT, &log_data, &options).await.unwrap();
+        // Lines without the prefix are parsed as they are.
+        process_line(&nginx_line, REGEX, DATE_FORMAT, &log_data, &options).await.unwrap();
+
+        let log_data = log_data.lock().unwrap();
+        assert_eq!(log_data.total_requests, 3);
+        assert_eq!(log_data.get_last_requests("192.168.0.0")[0], nginx_line);
+        assert!(compile_strip_prefix("(unclosed").is_err());
+    }
+
+    #[test]
+    fn test_parse_line_range() {
+        assert_eq!(parse_line_range("2:4"), Ok((2, 4)));
+        assert_eq!(parse_line_range("7:7"), Ok((7, 7)));
+        assert!(parse_line_range("0:4").is_err());
+        assert!(parse_line_range("5:4").is_err());
+        assert!(parse_line_range("5").is_err());
+        assert!(parse_line_range("a:4").is_err());
+    }
+
+    #[test]
+    fn test_parse_status_specs() {
+        assert_eq!(parse_status_specs("200, 4xx,5XX"), Ok(vec![StatusSpec::Code(200), StatusSpec::Class(4), StatusSpec::Class(5)]));
+        assert!(parse_status_specs("6xx").is_err());
+        assert!(parse_status_specs("40x").is_err());
+        assert!(parse_status_specs("42").is_err());
+        assert!(parse_status_specs("200,").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_status_filters() {
+        let regex = format!("{}\\S+ (?P<status>\\d+)", REGEX);
+        let statuses = [200, 201, 301, 404, 404, 500, 503];
+        let ingest = |options: ParseOptions| {
+            let regex = regex.clone();
+            async move {
+                let log_data = Arc::new(Mutex::new(LogData::new()));
+                for (i, status) in statuses.iter().enumerate() {
+                    let line = format!("10.0.0.{} - \"-\" [16/Jul/2024:10:00:00 +0000] 0.120 \"example.com\" \"GET / HTTP/1.1\" {} 512", i, status);
+                    process_line(&line, &regex, DATE_FORMAT, &log_data, &options).await.unwrap();
+                }
+                let classes = log_data.lock().unwrap().get_status_classes();
+                classes
+            }
+        };
+
+        let failures_only = ParseOptions { exclude_status: parse_status_specs("2xx,3xx").unwrap(), ..Default::default() };
+        assert_eq!(ingest(failures_only).await, [0, 0, 0, 2, 2]);
+        let only_404_and_5xx = ParseOptions { only_status: parse_status_specs("404,5xx").unwrap(), ..Default::default() };
+        assert_eq!(ingest(only_404_and_5xx).await, [0, 0, 0, 2, 2]);
+        let only_5xx_but_503 = ParseOptions { only_status: parse_status_specs("5xx").unwrap(), exclude_status: parse_status_specs("503").unwrap(), ..Default::default() };
+        assert_eq!(ingest(only_5xx_but_503).await, [0, 0, 0, 0, 1]);
+        assert!(!status_allowed(None, &ParseOptions { only_status: vec![StatusSpec::Code(200)], ..Default::default() }));
+        assert!(status_allowed(None, &ParseOptions { exclude_status: vec![StatusSpec::Class(2)], ..Default::default() }));
+    }
+
+    #[tokio::test]
+    async fn test_crlf_line_endings() {
+        let path = write_fixture("crlf.log", sample_log().replace('\n', "\r\n").as_bytes());
+        let log_data = Arc::new(Mutex::new(LogData::new()));
+        let regex = format!("{}\\S+ (?P<status>\\d+) (?P<size>\\d+)$", REGEX);
+
+        tail_file(&path, -1, &regex, DATE_FORMAT, &log_data, &ParseOptions::default(), None, |_| {}).await.unwrap();
+        tail_file(&path, 2, &regex, DATE_FORMAT, &log_data, &ParseOptions::default(), None, |_| {}).await.unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        let log_data = log_data.lock().unwrap();
+        assert_eq!(log_data.total_requests, 7);
+        assert_eq!(log_data.get_response_size_histogram(), [7, 0, 0, 0]);
+        assert!(log_data.get_recent_requests().iter().all(|request| request.log_line.ends_with("200 512")));
+        assert_eq!(log_data.get_parse_failures().1, 0);
+    }
+
+    #[tokio::test]
+    async fn test_line_range_reads_only_requested_lines() {
+        let path = write_fixture("line_range.log", sample_log().as_bytes());
+        let log_data = Arc::new(Mutex::new(LogData::new()));
+        let options = ParseOptions { line_range: Some((2, 4)), ..Default::default() };
+
+        let last = tail_file(&path, 0, REGEX, DATE_FORMAT, &log_data, &options, None, |_| {}).await.unwrap();
+        assert_eq!(last, Some(4));
+        let mut ips: Vec<String> = log_data.lock().unwrap().by_ip.keys().cloned().collect();
+        ips.sort();
+        assert_eq!(ips, vec!["192.168.0.1", "192.168.0.2", "192.168.0.3"]);
+
+        // Following the file does not read past the range.
+        std::fs::OpenOptions::new().append(true).open(&path).unwrap().write_all(sample_log().as_bytes()).unwrap();
+        tail_file(&path, 0, REGEX, DATE_FORMAT, &log_data, &options, last, |_| {}).await.unwrap();
+        assert_eq!(log_data.lock().unwrap().total_requests, 3);
+
+        let out_of_bounds = ParseOptions { line_range: Some((8, 20)), ..Default::default() };
+        let error = tail_file(&path, 0, REGEX, DATE_FORMAT, &log_data, &out_of_bounds, None, |_| {}).await.unwrap_err();
+        assert!(error.to_string().contains("the file has 10 lines"));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_parse_time_window() {
+        assert_eq!(parse_time_window("15m"), Some(900));
+        assert_eq!(parse_time_window("last 1h"), Some(3600));
+        assert_eq!(parse_time_window(" 2d "), Some(172800));
+        assert_eq!(parse_time_window("30 s"), Some(30));
+        assert_eq!(parse_time_window("0m"), None);
+        assert_eq!(parse_time_window("1w"), None);
+        assert_eq!(parse_time_window("all"), None);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_follows_symlink_swap() {
+        let old_log = write_fixture("old.log", sample_log().as_bytes());
+        let new_log = write_fixture("new.log", sample_log().lines().take(2).map(|l| format!("{}\n", l)).collect::<String>().as_bytes());
+        let link = std::env::temp_dir().join(format!("logutil_{}_current.log", std::process::id()));
+        let _ = std::fs::remove_file(&link);
+        std::os::unix::fs::symlink(&old_log, &link).unwrap();
+
+        let log_data = Arc::new(Mutex::new(LogData::new()));
+        let mut target = None;
+        assert!(!target_changed(&link, &mut target));
+        let last_line = tail_file(&link, -1, REGEX, DATE_FORMAT, &log_data, &ParseOptions::default(), None, |_| {}).await.unwrap();
+        assert_eq!(last_line, Some(5));
+
+        std::fs::remove_file(&link).unwrap();
+        std::os::unix::fs::symlink(&new_log, &link).unwrap();
+        assert!(target_changed(&link, &mut target));
+        assert!(!target_changed(&link, &mut target));
+        tail_file(&link, 0, REGEX, DATE_FORMAT, &log_data, &ParseOptions::default(), Some(0), |_| {}).await.unwrap();
+
+        assert_eq!(log_data.lock().unwrap().total_requests, 7);
+        for path in [&link, &old_log, &new_log] {
+            std::fs::remove_file(path).unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_wait_for_file_created_later() {
+        let path = std::env::temp_dir().join(format!("logutil_{}_delayed.log", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let creator = {
+            let path = path.clone();
+            tokio::spawn(async move {
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                std::fs::write(&path, sample_log()).unwrap();
+            })
+        };
+
+        wait_for_file(&path, Duration::from_millis(10)).await;
+        assert!(path.exists());
+        creator.await.unwrap();
+        assert_eq!(read_fixture(&path, -1).await, 5);
+    }
+
+    #[tokio::test]
+    async fn test_read_w3c_log() {
+        let header = "#Version: 1.0\n#Fields: date time c-ip cs-method cs-uri-stem sc-status\n";
+        let log = format!("{}2024-07-16 10:00:00 203.0.113.7 GET / 200\n2024-07-16 10:00:01 203.0.113.7 GET /missing 404\n{}2024-07-16 10:05:00 198.51.100.2 POST /login 200\n", header, header);
+        let path = write_fixture("w3c.log", log.as_bytes());
+        let log_data = Arc::new(Mutex::new(LogData::new()));
+        let options = ParseOptions { w3c: true, ..ParseOptions::default() };
+
+        tail_file(&path, -1, "", "", &log_data, &options, None, |_| {}).await.unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let log_data = log_data.lock().unwrap();
+        assert_eq!(log_data.total_requests, 3);
+        assert_eq!(log_data.get_parse_failures().1, 0);
+        assert_eq!(log_data.get_status_classes()[3], 1);
+        assert_eq!(log_data.get_time_span(), Some((1_721_124_000, 1_721_124_300)));
+    }
+
+    #[tokio::test]
+    async fn test_read_bzip2_log() {
+        let mut encoder = bzip2::write::BzEncoder::new(Vec::new(), bzip2::Compression::fast());
+        encoder.write_all(sample_log().as_bytes()).unwrap();
+        let path = write_fixture("access.log.bz2", &encoder.finish().unwrap());
+
+        assert_eq!(detect_compression(&path).unwrap(), Compression::Bzip2);
+        assert_eq!(read_fixture(&path, -1).await, 5);
+    }
+
+    #[test]
+    fn test_read_first_line() {
+        let mut encoder = bzip2::write::BzEncoder::new(Vec::new(), bzip2::Compression::fast());
+        encoder.write_all(sample_log().as_bytes()).unwrap();
+        let compressed = write_fixture("first_line.log.bz2", &encoder.finish().unwrap());
+        let empty = write_fixture("first_line_empty.log", b"");
+
+        assert_eq!(read_first_line(&compressed).unwrap().as_deref(), sample_log().lines().next());
+        assert_eq!(read_first_line(&empty).unwrap(), None);
+        std::fs::remove_file(compressed).unwrap();
+        std::fs::remove_file(empty).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_read_zstd_log() {
+        let data = zstd::encode_all(sample_log().as_bytes(), 3).unwrap();
+        let path = write_fixture("access.log.zst", &data);
+
+        assert_eq!(detect_compression(&path).unwrap(), Compression::Zstd);
+        assert_eq!(read_fixture(&path, 0).await, 5);
+    }
+
+    #[tokio::test]
+    async fn test_compression_detected_by_magic_bytes() {
+        let data = zstd::encode_all(sample_log().as_bytes(), 3).unwrap();
+        let path = write_fixture("rotated.1", &data);
+
+        assert_eq!(detect_compression(&path).unwrap(), Compression::Zstd);
+        assert_eq!(read_fixture(&path, 2).await, 2);
+    }
+}
@@ -0,0 +1,64 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+/// Bits of the hash that pick a register: 2^14 one-byte registers (16KB) for a standard error of 0.8%.
+const PRECISION: u32 = 14;
+const REGISTERS: usize = 1 << PRECISION;
+
+/// Approximate count of distinct values in constant memory (`--approx-unique`).
+#[derive(Debug, Clone)]
+pub struct HyperLogLog {
+    registers: Vec<u8>,
+}
+
+impl Default for HyperLogLog {
+    fn default() -> Self {
+        HyperLogLog { registers: vec![0; REGISTERS] }
+    }
+}
+
+impl HyperLogLog {
+    pub fn insert(&mut self, value: &str) {
+        let mut hasher = DefaultHasher::new();
+        value.hash(&mut hasher);
+        let hash = hasher.finish();
+        let index = (hash >> (64 - PRECISION)) as usize;
+        // Position of the first set bit in the remaining hash bits, capped when they are all zero.
+        let rank = ((hash << PRECISION).leading_zeros() + 1).min(64 - PRECISION + 1) as u8;
+        self.registers[index] = self.registers[index].max(rank);
+    }
+
+    /// Estimated number of distinct values inserted, using linear counting while many registers are empty.
+    pub fn estimate(&self) -> usize {
+        let m = REGISTERS as f64;
+        let sum: f64 = self.registers.iter().map(|&rank| 2f64.powi(-(rank as i32))).sum();
+        let estimate = 0.7213 / (1.0 + 1.079 / m) * m * m / sum;
+        let empty = self.registers.iter().filter(|&&rank| rank == 0).count();
+        if estimate <= 2.5 * m && empty > 0 {
+            (m * (m / empty as f64).ln()).round() as usize
+        } else {
+            estimate.round() as usize
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_estimate_within_error_bounds() {
+        let mut sketch = HyperLogLog::default();
+        assert_eq!(sketch.estimate(), 0);
+
+        for (inserted, checkpoint) in [(0, 1_000), (1_000, 100_000), (100_000, 1_000_000)] {
+            for i in inserted..checkpoint {
+                sketch.insert(&format!("/page/{}", i));
+            }
+            // Duplicates leave the estimate unchanged.
+            sketch.insert("/page/0");
+            let error = (sketch.estimate() as f64 - checkpoint as f64).abs() / checkpoint as f64;
+            assert!(error < 0.03, "estimated {} for {} distinct values", sketch.estimate(), checkpoint);
+        }
+    }
+}
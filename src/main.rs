@@ -2,6 +2,14 @@ mod app;
 mod log_data;
 mod helpers;
 mod tui_manager;
+mod metrics;
+mod security;
+mod errors;
+mod debug_log;
+mod categories;
+mod export;
+mod hyperloglog;
+mod w3c;
 
 use ratatui::{backend::{CrosstermBackend}, crossterm::{
     event::{self, Event},
@@ -10,17 +18,21 @@ use ratatui::{backend::{CrosstermBackend}, crossterm::{
 }, terminal::Terminal};
 
 use std::env;
-use std::fs::{self, File};
+use std::fs;
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, mpsc, Mutex};
-use std::time::{Duration};
+use std::time::{Duration, Instant};
 use env_logger::Builder;
 use log::{error, LevelFilter};
 use structopt::StructOpt;
 use tokio::time::sleep;
-use crate::app::App;
-use crate::helpers::tail_file;
-use crate::log_data::LogData;
+use crate::debug_log::{RotatingFile, MAX_LOG_SIZE};
+use crate::app::{parse_feed_columns, App, FeedColumn, IntensityBar};
+use crate::helpers::{compile_strip_prefix, parse_line_range, parse_status_specs, read_first_line, tail_file, target_changed, validate_regex, wait_for_file, ParseOptions};
+use crate::log_data::{GroupBy, LogData, TopLimits};
+use crate::security::{DenyFormat, SecurityConfig};
+use crate::categories::UrlCategories;
+use crate::export::ExportFormat;
 
 #[derive(StructOpt)]
 #[structopt(
@@ -30,9 +42,9 @@ use crate::log_data::LogData;
     GitHub: https://github.com/s00d/logutil"
 )]
 struct Cli {
-    /// Path to the log file
-    #[structopt(parse(from_os_str))]
-    file: PathBuf,
+    /// Paths to the log files, all of them are analyzed together
+    #[structopt(parse(from_os_str), required = true, min_values = 1)]
+    files: Vec<PathBuf>,
 
     /// Number of lines to read from the end of the file (0 to start from the end, -1 to read the entire file)
     #[structopt(short = "c", long, default_value = "0")]
@@ -46,7 +58,7 @@ struct Cli {
     )]
     regex: String,
 
-    /// Date format to parse the log entries
+    /// Date format to parse the log entries; several comma-separated formats are tried in order
     #[structopt(
         short = "d",
         long,
@@ -54,22 +66,418 @@ struct Cli {
     )]
     date_format: String,
 
+    /// Read W3C extended logs (IIS and others): columns are mapped from each file's #Fields directive, --regex and --date-format are ignored
+    #[structopt(long)]
+    w3c: bool,
+
     /// Number of top entries to display
     #[structopt(short, long, default_value = "100")]
     top: usize,
 
+    /// Number of top IPs to display, overriding --top
+    #[structopt(long)]
+    top_ips: Option<usize>,
+
+    /// Number of top URLs to display, overriding --top
+    #[structopt(long)]
+    top_urls: Option<usize>,
+
+    /// Number of error rows (error codes, failing URLs) to display, overriding --top
+    #[structopt(long)]
+    top_errors: Option<usize>,
+
     /// Disable clearing of outdated entries
     #[structopt(long)]
     no_clear: bool,
 
-    /// Enable logging to a file
+    /// Print counts without thousands separators (1234567 instead of 1,234,567), e.g. for scripts parsing the reports
+    #[structopt(long)]
+    no_thousands: bool,
+
+    /// Quit on Ctrl+C or Ctrl+Q right away instead of asking for confirmation
+    #[structopt(long)]
+    no_confirm_quit: bool,
+
+    /// Merge trailing-slash and www/scheme variants of the same URL
+    #[structopt(long)]
+    canonicalize_urls: bool,
+
+    /// Count identical requests per IP and list heavy repeaters in the Security tab (costs memory)
+    #[structopt(long)]
+    detect_repeats: bool,
+
+    /// Process only this percentage (1-100) of lines, chosen by hash, and extrapolate request counts
+    #[structopt(long)]
+    sample: Option<u8>,
+
+    /// Use the left-most public IP of the `xff` named group (X-Forwarded-For) as the client IP
+    #[structopt(long)]
+    real_ip_from_xff: bool,
+
+    /// Do not keep raw log lines in memory; the Requests feed shows parsed columns and the Detailed tab has no raw lines
+    #[structopt(long)]
+    no_raw_lines: bool,
+
+    /// Count unique IPs and URLs with HyperLogLog estimates (~0.8% error) and cap the URL index at 10,000 entries, for logs with millions of distinct URLs
+    #[structopt(long)]
+    approx_unique: bool,
+
+    /// Log to app.log, which is moved to app.log.1 when it reaches 1MB
     #[structopt(long)]
     log_to_file: bool,
+
+    /// Comma-separated URL fragments reported as sensitive file probes in the Security tab (e.g. ".env,.git,.sql")
+    #[structopt(long, use_delimiter = true)]
+    sensitive_paths: Vec<String>,
+
+    /// Comma-separated CMS admin and config paths as name:/path (e.g. "WordPress:/wp-admin,Joomla:/administrator"); IPs requesting many are listed in the Security tab
+    #[structopt(long, use_delimiter = true)]
+    cms_probes: Vec<String>,
+
+    /// Comma-separated HTTP methods considered normal; others are listed in the Security tab
+    #[structopt(long, use_delimiter = true)]
+    allowed_methods: Vec<String>,
+
+    /// Comma-separated referer spam domains listed in the Security tab; subdomains match too
+    #[structopt(long, use_delimiter = true)]
+    spam_referers: Vec<String>,
+
+    /// Comma-separated login endpoints (matched with the paths below them) whose 401/403 bursts are reported as credential stuffing in the Security tab
+    #[structopt(long, use_delimiter = true)]
+    auth_endpoints: Vec<String>,
+
+    /// 401/403 responses on login endpoints from one IP within --auth-window that flag it for credential stuffing
+    #[structopt(long, default_value = "10")]
+    auth_failure_threshold: usize,
+
+    /// 401/403 responses on one login endpoint from all IPs within --auth-window that flag it for credential stuffing
+    #[structopt(long, default_value = "50")]
+    auth_endpoint_threshold: usize,
+
+    /// Seconds a burst of 401/403 responses must fit in to count as credential stuffing
+    #[structopt(long, default_value = "60")]
+    auth_window: i64,
+
+    /// Distinct query strings on one path, from at most 5 IPs, reported as cache busting in the Security tab
+    #[structopt(long, default_value = "50")]
+    cache_busting_threshold: usize,
+
+    /// Response time in seconds from which a request counts as slow; IPs with many slow requests are listed in the Security tab
+    #[structopt(long, default_value = "10")]
+    slow_threshold: f64,
+
+    /// Requests per second considered normal, marked as a line in the Sparkline tab; by default the median
+    #[structopt(long)]
+    baseline_rps: Option<f64>,
+
+    /// Show URLs percent-decoded (e.g. `..%2f` as `../`) in the Overview, Requests and Security tabs; raw lines stay as logged
+    #[structopt(long)]
+    decode_urls: bool,
+
+    /// Width of the URL column in the Overview; by default it fills the terminal width
+    #[structopt(long)]
+    url_width: Option<usize>,
+
+    /// Seconds back from the latest log entry in which an IP's first request makes it new in the Security tab
+    #[structopt(long, default_value = "300")]
+    new_ip_window: i64,
+
+    /// Prefix length (0-32) IPv4 clients are grouped by in the Subnets tab; IPv6 clients are grouped by /64
+    #[structopt(long, default_value = "24")]
+    subnet_prefix: u8,
+
+    /// Print block rules for suspicious IPs (iptables, nginx or plain) and exit
+    #[structopt(long)]
+    export_deny: Option<DenyFormat>,
+
+    /// Minimum number of suspicious requests for an IP to be included by --export-deny
+    #[structopt(long, default_value = "1")]
+    deny_threshold: usize,
+
+    /// Regex of a prefix removed from each line before --regex runs, e.g. a syslog header
+    #[structopt(long)]
+    strip_prefix: Option<String>,
+
+    /// Comma-separated status codes and classes to keep, e.g. "200" or "4xx,5xx"; other requests are dropped
+    #[structopt(long)]
+    only_status: Option<String>,
+
+    /// Comma-separated status codes and classes to drop, e.g. "2xx,304"
+    #[structopt(long)]
+    exclude_status: Option<String>,
+
+    /// Only read lines A to B (1-based, inclusive) of each file, e.g. `--lines 120000:125000`
+    #[structopt(long)]
+    lines: Option<String>,
+
+    /// String to watch for while tailing, e.g. a CVE probe; matching lines flash a notice and are listed in the Alerts overlay (`A`). Repeatable
+    #[structopt(long = "alert-pattern", number_of_values = 1)]
+    alert_patterns: Vec<String>,
+
+    /// Number of failing lines kept, with the reason, for the Parse Errors overlay (`p`)
+    #[structopt(long, default_value = "5")]
+    max_parse_errors: usize,
+
+    /// Response sizes and times kept per IP and per URL for averages, percentiles and size trends; more is more accurate but uses more memory
+    #[structopt(long, default_value = "100")]
+    sample_per_entity: usize,
+
+    /// Maximum number of worker threads of the async runtime; lines are read and parsed by a single task either way (default: one per CPU core)
+    #[structopt(long)]
+    threads: Option<usize>,
+
+    /// Exit with code 4 in headless modes when no line matched the regex
+    #[structopt(long)]
+    fail_on_empty: bool,
+
+    /// Print the top requests counted by ip, url, status, ua, domain or category (with --url-categories) and exit
+    #[structopt(long)]
+    group_by: Option<GroupBy>,
+
+    /// File of `label pattern` lines (glob, or regex after `~`) grouping URLs into categories such as api or static
+    #[structopt(long, parse(from_os_str))]
+    url_categories: Option<PathBuf>,
+
+    /// Print the status class distribution, the top error codes and the top error URLs and exit
+    #[structopt(long)]
+    show_errors: bool,
+
+    /// Write the top --top IPs and URLs with their requests, errors, most frequent status and bytes to this file and exit
+    #[structopt(long, parse(from_os_str))]
+    export: Option<PathBuf>,
+
+    /// Format of --export: csv, json or ndjson
+    #[structopt(long, default_value = "csv")]
+    export_format: ExportFormat,
+
+    /// Print requests, 5xx errors and unique IPs per interval of this many seconds (default 3600) and exit
+    #[structopt(long)]
+    interval_summary: Option<Option<i64>>,
+
+    /// Comma-separated columns of the Requests feed: time, ip, method, status, size, url, ua ("raw" for raw lines)
+    #[structopt(long)]
+    feed_columns: Option<String>,
+
+    /// Characters of the intensity bars, lowest to highest intensity ("ascii" for terminals without block glyphs)
+    #[structopt(long)]
+    bar_chars: Option<String>,
+
+    /// Comma-separated intensity thresholds (0-1) switching between the bar characters, one fewer than the characters
+    #[structopt(long)]
+    bar_thresholds: Option<String>,
+
+    /// Draw the UI with ASCII characters only, for terminals that cannot show box-drawing, block or arrow glyphs
+    #[structopt(long)]
+    ascii: bool,
+
+    /// Highlight URLs whose largest sampled response is at least this many bytes in the Performance tab
+    #[structopt(long)]
+    large_response_threshold: Option<u64>,
+
+    /// Milliseconds between two checks of the files for new lines; lower is more responsive but uses more CPU
+    #[structopt(long, default_value = "1000")]
+    watch_interval: u64,
+
+    /// Wait for log files that do not exist yet instead of exiting, checking every --watch-interval
+    #[structopt(long)]
+    wait_for_file: bool,
+
+    /// Periodically write Prometheus metrics to this textfile while tailing
+    #[structopt(long, parse(from_os_str))]
+    prometheus: Option<PathBuf>,
+
+    /// Periodically write a JSON health snapshot (requests, error rate, rps, suspicious IPs) to this file while tailing
+    #[structopt(long, parse(from_os_str))]
+    status_json: Option<PathBuf>,
+
+    /// Quit the TUI after this many seconds and print the session summary, e.g. for smoke tests in CI
+    #[structopt(long)]
+    run_for: Option<u64>,
+
+    /// Print the effective configuration (defaults and given options combined) and exit without reading the files
+    #[structopt(long)]
+    dry_run: bool,
+}
+
+/// Security detection settings: the defaults, replaced by each list option that was given.
+fn security_config(args: &Cli) -> SecurityConfig {
+    let mut security_config = SecurityConfig {
+        detect_repeats: args.detect_repeats,
+        cache_busting_queries: args.cache_busting_threshold,
+        slow_threshold: args.slow_threshold,
+        auth_failure_threshold: args.auth_failure_threshold,
+        auth_endpoint_threshold: args.auth_endpoint_threshold,
+        auth_window: args.auth_window,
+        ..Default::default()
+    };
+    if !args.sensitive_paths.is_empty() {
+        security_config.sensitive_paths = args.sensitive_paths.clone();
+    }
+    if !args.allowed_methods.is_empty() {
+        security_config.allowed_methods = args.allowed_methods.clone();
+    }
+    if !args.spam_referers.is_empty() {
+        security_config.spam_referers = args.spam_referers.clone();
+    }
+    if !args.cms_probes.is_empty() {
+        security_config.cms_probes = args.cms_probes.clone();
+    }
+    if !args.auth_endpoints.is_empty() {
+        security_config.auth_endpoints = args.auth_endpoints.clone();
+    }
+    security_config
+}
+
+/// What the run would do: the headless mode that takes precedence, checked in the order `run` handles them, or the TUI.
+fn run_mode(args: &Cli) -> String {
+    if let Some(format) = args.export_deny {
+        format!("export deny list ({:?})", format).to_lowercase()
+    } else if let Some(group_by) = args.group_by {
+        format!("group by {}", group_by.label())
+    } else if let Some(path) = &args.export {
+        format!("export to {} ({:?})", path.display(), args.export_format).to_lowercase()
+    } else if args.show_errors {
+        "show errors".to_string()
+    } else if let Some(interval) = args.interval_summary {
+        format!("interval summary ({}s)", interval.unwrap_or(3600))
+    } else {
+        "TUI".to_string()
+    }
+}
+
+/// `--dry-run` output: every setting after defaults, regex files and list options are resolved.
+fn format_effective_config(args: &Cli, regex_pattern: &str, security_config: &SecurityConfig) -> String {
+    let optional = |value: Option<String>| value.unwrap_or_else(|| "-".to_string());
+    let settings: Vec<(&str, String)> = vec![
+        ("mode", run_mode(args)),
+        ("files", args.files.iter().map(|file| file.display().to_string()).collect::<Vec<_>>().join(", ")),
+        ("count", args.count.to_string()),
+        ("lines", optional(args.lines.clone())),
+        ("regex", regex_pattern.trim_end_matches(['\n', '\r']).to_string()),
+        ("w3c", args.w3c.to_string()),
+        ("url categories", optional(args.url_categories.as_ref().map(|path| path.display().to_string()))),
+        ("strip prefix", optional(args.strip_prefix.clone())),
+        ("only status", optional(args.only_status.clone())),
+        ("exclude status", optional(args.exclude_status.clone())),
+        ("date format", args.date_format.clone()),
+        ("top", args.top.to_string()),
+        ("top ips", args.top_ips.unwrap_or(args.top).to_string()),
+        ("top urls", args.top_urls.unwrap_or(args.top).to_string()),
+        ("top errors", args.top_errors.unwrap_or(args.top).to_string()),
+        ("sample", optional(args.sample.map(|percent| format!("{}%", percent)))),
+        ("no clear", args.no_clear.to_string()),
+        ("canonicalize urls", args.canonicalize_urls.to_string()),
+        ("real ip from xff", args.real_ip_from_xff.to_string()),
+        ("no raw lines", args.no_raw_lines.to_string()),
+        ("detect repeats", security_config.detect_repeats.to_string()),
+        ("sensitive paths", security_config.sensitive_paths.join(",")),
+        ("allowed methods", security_config.allowed_methods.join(",")),
+        ("spam referers", security_config.spam_referers.join(",")),
+        ("cms probes", security_config.cms_probes.join(",")),
+        ("cache busting threshold", security_config.cache_busting_queries.to_string()),
+        ("auth endpoints", security_config.auth_endpoints.join(",")),
+        ("auth failure threshold", security_config.auth_failure_threshold.to_string()),
+        ("auth endpoint threshold", security_config.auth_endpoint_threshold.to_string()),
+        ("auth window", format!("{}s", security_config.auth_window)),
+        ("slow threshold", format!("{}s", security_config.slow_threshold)),
+        ("new ip window", format!("{}s", args.new_ip_window)),
+        ("subnet prefix", format!("/{}", args.subnet_prefix)),
+        ("url width", optional(args.url_width.map(|width| width.to_string()))),
+        ("decode urls", args.decode_urls.to_string()),
+        ("baseline rps", args.baseline_rps.map_or_else(|| "median".to_string(), |rps| rps.to_string())),
+        ("deny threshold", args.deny_threshold.to_string()),
+        ("large response threshold", optional(args.large_response_threshold.map(|bytes| bytes.to_string()))),
+        ("max parse errors", args.max_parse_errors.to_string()),
+        ("alert patterns", optional((!args.alert_patterns.is_empty()).then(|| args.alert_patterns.join(", ")))),
+        ("sample per entity", args.sample_per_entity.to_string()),
+        ("approx unique", args.approx_unique.to_string()),
+        ("fail on empty", args.fail_on_empty.to_string()),
+        ("watch interval", format!("{}ms", args.watch_interval)),
+        ("wait for file", args.wait_for_file.to_string()),
+        ("run for", optional(args.run_for.map(|seconds| format!("{}s", seconds)))),
+        ("threads", optional(args.threads.map(|threads| threads.to_string()))),
+        ("prometheus", optional(args.prometheus.as_ref().map(|path| path.display().to_string()))),
+        ("status json", optional(args.status_json.as_ref().map(|path| path.display().to_string()))),
+        ("log to file", args.log_to_file.to_string()),
+    ];
+    settings.iter().map(|(name, value)| format!("{:<25} {}\n", format!("{}:", name), value)).collect()
+}
+
+/// Exit codes of the headless modes (`--export-deny`, `--export`, `--group-by`, `--show-errors`, `--interval-summary`).
+/// Invalid command-line options, including those rejected by the argument parser, exit with 1,
+/// as does an `--export` file that cannot be written.
+const EXIT_INVALID_ARGS: i32 = 1;
+/// The `--regex` pattern does not compile, lacks the required groups or its file cannot be read,
+/// or `--strip-prefix` does not compile.
+const EXIT_INVALID_REGEX: i32 = 2;
+/// A log file could not be opened or read.
+const EXIT_UNREADABLE_FILE: i32 = 3;
+/// No line matched the regex and `--fail-on-empty` was given.
+const EXIT_NO_MATCHES: i32 = 4;
+
+/// Reads the whole files for a headless mode, exiting with the code documented above on failure.
+#[allow(clippy::too_many_arguments)]
+async fn load_headless(
+    file_paths: &[PathBuf],
+    count: isize,
+    regex_pattern: &str,
+    date_format: &str,
+    log_data: &Arc<Mutex<LogData>>,
+    parse_options: &ParseOptions,
+    fail_on_empty: bool,
+    wait_interval: Option<Duration>,
+) {
+    // Headless modes read whole files unless a count was given explicitly.
+    let count = if count == 0 { -1 } else { count };
+    for file_path in file_paths {
+        if let Some(interval) = wait_interval.filter(|_| !file_path.exists()) {
+            eprintln!("Waiting for {}...", file_path.display());
+            wait_for_file(file_path, interval).await;
+        }
+        if let Err(e) = tail_file(file_path, count, regex_pattern, date_format, log_data, parse_options, None, |_| {}).await {
+            eprintln!("Could not read {}: {}", file_path.display(), e);
+            std::process::exit(EXIT_UNREADABLE_FILE);
+        }
+    }
+    let log_data = log_data.lock().unwrap();
+    if fail_on_empty && log_data.total_requests == 0 {
+        eprintln!("No lines matched the regex");
+        let (failures, _) = log_data.get_parse_failures();
+        for failure in failures {
+            eprintln!("  {}: {}", failure.reason, failure.line);
+        }
+        std::process::exit(EXIT_NO_MATCHES);
+    }
+}
+
+/// Pause between two polls of the followed files, from `--watch-interval` in milliseconds.
+fn watch_interval(millis: u64) -> Result<Duration, String> {
+    match millis {
+        0 => Err("the interval must be at least 1 millisecond".to_string()),
+        millis => Ok(Duration::from_millis(millis)),
+    }
+}
+
+/// Multi-threaded runtime with `threads` workers, or one per core when not given. This caps the
+/// threads logutil starts; reading and parsing run in one task and do not spread over them.
+fn build_runtime(threads: Option<usize>) -> std::io::Result<tokio::runtime::Runtime> {
+    let mut builder = tokio::runtime::Builder::new_multi_thread();
+    if let Some(threads) = threads {
+        builder.worker_threads(threads);
+    }
+    builder.enable_all().build()
 }
 
-#[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
+fn main() -> Result<(), Box<dyn std::error::Error>> {
     let args = Cli::from_args();
+    if args.threads == Some(0) {
+        eprintln!("Invalid --threads: expected at least 1 thread");
+        std::process::exit(EXIT_INVALID_ARGS);
+    }
+    build_runtime(args.threads)?.block_on(run(args))
+}
+
+async fn run(args: Cli) -> Result<(), Box<dyn std::error::Error>> {
 
     if env::args().any(|arg| arg == "-h" || arg == "--help") {
         return Ok(());
@@ -80,7 +488,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     }
 
     if args.log_to_file {
-        let log_file = File::create("app.log").expect("Unable to create log file");
+        let log_file = RotatingFile::open(Path::new("app.log"), MAX_LOG_SIZE).expect("Unable to open log file");
         Builder::new()
             .filter(None, LevelFilter::Info)
             .write_style(env_logger::WriteStyle::Always)
@@ -91,32 +499,219 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     }
 
 
-    enable_raw_mode()?;
-    let mut stdout = std::io::stdout();
-    execute!(stdout, EnterAlternateScreen)?;
-    let backend = CrosstermBackend::new(stdout);
-    let mut terminal = Terminal::new(backend)?;
-
-    terminal.clear()?;
+    for file in &args.files {
+        if !file.is_dir() {
+            continue;
+        }
+        let has_files = fs::read_dir(file)?
+            .filter_map(Result::ok)
+            .any(|entry| entry.path().is_file());
+        if has_files {
+            eprintln!("{} is a directory, pass the log files inside it", file.display());
+        } else {
+            eprintln!("{} is an empty directory, there are no log files to analyze", file.display());
+        }
+        std::process::exit(EXIT_UNREADABLE_FILE);
+    }
+    if let Some(file) = args.files.iter().find(|file| !args.wait_for_file && !file.exists()) {
+        eprintln!("{} does not exist, pass --wait-for-file to wait for it", file.display());
+        std::process::exit(EXIT_UNREADABLE_FILE);
+    }
 
-    let file_path = args.file.clone();
+    let file_paths = args.files.clone();
     let count = args.count;
     let regex_pattern = if Path::new(&args.regex).exists() {
-        fs::read_to_string(&args.regex).expect("Could not read regex file")
+        fs::read_to_string(&args.regex).unwrap_or_else(|e| {
+            eprintln!("Could not read regex file {}: {}", args.regex, e);
+            std::process::exit(EXIT_INVALID_REGEX);
+        })
     } else {
         args.regex.clone()
     };
+    if let Err(e) = validate_regex(&regex_pattern) {
+        eprintln!("Invalid --regex: {}", e);
+        std::process::exit(EXIT_INVALID_REGEX);
+    }
     let date_format = args.date_format.clone();
-    let top_n = args.top;
-    let no_clear = args.no_clear;
+    let top = TopLimits::new(args.top, args.top_ips, args.top_urls, args.top_errors);
+    let parse_options = ParseOptions {
+        w3c: args.w3c,
+        w3c_fields: None,
+        no_clear: args.no_clear,
+        canonicalize_urls: args.canonicalize_urls,
+        detect_repeats: args.detect_repeats,
+        sample_percent: args.sample,
+        real_ip_from_xff: args.real_ip_from_xff,
+        no_raw_lines: args.no_raw_lines,
+        strip_prefix: args.strip_prefix.as_deref().map(|pattern| compile_strip_prefix(pattern).unwrap_or_else(|e| {
+            eprintln!("Invalid --strip-prefix: {}", e);
+            std::process::exit(EXIT_INVALID_REGEX);
+        })),
+        line_range: args.lines.as_deref().map(|spec| parse_line_range(spec).unwrap_or_else(|e| {
+            eprintln!("Invalid --lines: {}", e);
+            std::process::exit(EXIT_INVALID_ARGS);
+        })),
+        only_status: args.only_status.as_deref().map_or_else(Vec::new, |spec| parse_status_specs(spec).unwrap_or_else(|e| {
+            eprintln!("Invalid --only-status: {}", e);
+            std::process::exit(EXIT_INVALID_ARGS);
+        })),
+        exclude_status: args.exclude_status.as_deref().map_or_else(Vec::new, |spec| parse_status_specs(spec).unwrap_or_else(|e| {
+            eprintln!("Invalid --exclude-status: {}", e);
+            std::process::exit(EXIT_INVALID_ARGS);
+        })),
+    };
+    if args.subnet_prefix > 32 {
+        eprintln!("Invalid --subnet-prefix: expected a prefix length between 0 and 32");
+        std::process::exit(EXIT_INVALID_ARGS);
+    }
+    if args.new_ip_window <= 0 {
+        eprintln!("Invalid --new-ip-window: the window must be a positive number of seconds");
+        std::process::exit(EXIT_INVALID_ARGS);
+    }
+    let url_categories = args.url_categories.as_ref().map(|path| {
+        fs::read_to_string(path).map_err(|e| e.to_string()).and_then(|content| UrlCategories::parse(&content)).unwrap_or_else(|e| {
+            eprintln!("Invalid --url-categories {}: {}", path.display(), e);
+            std::process::exit(EXIT_INVALID_ARGS);
+        })
+    });
+    if args.group_by == Some(GroupBy::Category) && url_categories.is_none() {
+        eprintln!("Invalid --group-by: category needs --url-categories");
+        std::process::exit(EXIT_INVALID_ARGS);
+    }
+    if args.auth_failure_threshold == 0 || args.auth_endpoint_threshold == 0 || args.auth_window <= 0 {
+        eprintln!("Invalid --auth-failure-threshold, --auth-endpoint-threshold or --auth-window: they must be positive");
+        std::process::exit(EXIT_INVALID_ARGS);
+    }
+    if args.alert_patterns.iter().any(|pattern| pattern.trim().is_empty()) {
+        eprintln!("Invalid --alert-pattern: the pattern is empty and would match every line");
+        std::process::exit(EXIT_INVALID_ARGS);
+    }
+    if args.sample_per_entity == 0 {
+        eprintln!("Invalid --sample-per-entity: keep at least 1 sample");
+        std::process::exit(EXIT_INVALID_ARGS);
+    }
+    if args.baseline_rps.is_some_and(|rps| !rps.is_finite() || rps < 0.0) {
+        eprintln!("Invalid --baseline-rps: expected a number of requests per second");
+        std::process::exit(EXIT_INVALID_ARGS);
+    }
+    if args.run_for == Some(0) {
+        eprintln!("Invalid --run-for: run for at least 1 second");
+        std::process::exit(EXIT_INVALID_ARGS);
+    }
+    if args.sample.is_some_and(|percent| percent == 0 || percent > 100) {
+        eprintln!("Invalid --sample: expected a percentage between 1 and 100");
+        std::process::exit(EXIT_INVALID_ARGS);
+    }
+    let prometheus_path = args.prometheus.clone();
+    let status_json_path = args.status_json.clone();
+    let poll_interval = watch_interval(args.watch_interval).unwrap_or_else(|e| {
+        eprintln!("Invalid --watch-interval: {}", e);
+        std::process::exit(EXIT_INVALID_ARGS);
+    });
+    let wait_interval = args.wait_for_file.then_some(poll_interval);
 
     let log_data = Arc::new(Mutex::new(LogData::new()));
+    log_data.lock().unwrap().set_sample_percent(args.sample);
+    log_data.lock().unwrap().set_max_parse_failures(args.max_parse_errors);
+    log_data.lock().unwrap().set_samples_per_entity(args.sample_per_entity);
+    log_data.lock().unwrap().set_alert_patterns(&args.alert_patterns);
+    if let Some(categories) = url_categories {
+        log_data.lock().unwrap().set_url_categories(categories);
+    }
+    if args.approx_unique {
+        log_data.lock().unwrap().set_approx_unique();
+    }
     let log_data_clone = Arc::clone(&log_data);
 
+    let security_config = security_config(&args);
+    log_data.lock().unwrap().set_auth_endpoints(&security_config.auth_endpoints);
+
+    let feed_columns = match &args.feed_columns {
+        Some(spec) => parse_feed_columns(spec).unwrap_or_else(|e| {
+            eprintln!("Invalid --feed-columns: {}", e);
+            std::process::exit(EXIT_INVALID_ARGS);
+        }),
+        None => FeedColumn::DEFAULT.to_vec(),
+    };
+
+    let intensity_bar = IntensityBar::parse(args.bar_chars.as_deref(), args.bar_thresholds.as_deref()).unwrap_or_else(|e| {
+        eprintln!("Invalid bar configuration: {}", e);
+        std::process::exit(EXIT_INVALID_ARGS);
+    });
+
+    if args.dry_run {
+        print!("{}", format_effective_config(&args, &regex_pattern, &security_config));
+        return Ok(());
+    }
+
+    if let Some(format) = args.export_deny {
+        load_headless(&file_paths, count, &regex_pattern, &date_format, &log_data, &parse_options, args.fail_on_empty, wait_interval).await;
+        let ips = security::find_suspicious_ips(&log_data.lock().unwrap(), &security_config);
+        print!("{}", security::format_deny_list(&ips, format, args.deny_threshold));
+        return Ok(());
+    }
+
+    if let Some(group_by) = args.group_by {
+        load_headless(&file_paths, count, &regex_pattern, &date_format, &log_data, &parse_options, args.fail_on_empty, wait_interval).await;
+        let counts = log_data.lock().unwrap().get_top_n_by(group_by, top.for_group(group_by));
+        print!("{}", metrics::format_group_counts(group_by.label(), &counts, !args.no_thousands));
+        return Ok(());
+    }
+
+    if let Some(path) = &args.export {
+        load_headless(&file_paths, count, &regex_pattern, &date_format, &log_data, &parse_options, args.fail_on_empty, wait_interval).await;
+        let rows = export::export_rows(&log_data.lock().unwrap(), top);
+        if let Err(e) = fs::write(path, args.export_format.exporter().export(&rows)) {
+            eprintln!("Could not write {}: {}", path.display(), e);
+            std::process::exit(EXIT_INVALID_ARGS);
+        }
+        return Ok(());
+    }
+
+    if args.show_errors {
+        load_headless(&file_paths, count, &regex_pattern, &date_format, &log_data, &parse_options, args.fail_on_empty, wait_interval).await;
+        print!("{}", metrics::format_error_report(&log_data.lock().unwrap(), top.errors, !args.no_thousands));
+        return Ok(());
+    }
+
+    if let Some(interval) = args.interval_summary {
+        let interval = interval.unwrap_or(3600);
+        if interval <= 0 {
+            eprintln!("Invalid --interval-summary: the interval must be a positive number of seconds");
+            std::process::exit(EXIT_INVALID_ARGS);
+        }
+        log_data.lock().unwrap().track_intervals(interval);
+        load_headless(&file_paths, count, &regex_pattern, &date_format, &log_data, &parse_options, args.fail_on_empty, wait_interval).await;
+        print!("{}", metrics::format_interval_summary(&log_data.lock().unwrap().get_interval_stats(), !args.no_thousands));
+        return Ok(());
+    }
+
+    enable_raw_mode()?;
+    let mut stdout = std::io::stdout();
+    execute!(stdout, EnterAlternateScreen)?;
+    let backend = CrosstermBackend::new(stdout);
+    let mut terminal = Terminal::new(backend)?;
+
+    terminal.clear()?;
+
     let (tx, rx) = mpsc::channel();
 
-    let app = Arc::new(Mutex::new(App::new(log_data, top_n)));
+    let status_security_config = security_config.clone();
+    let summary_security_config = security_config.clone();
+    let summary_log_data = Arc::clone(&log_data);
+    let mut app = App::new(log_data, top, security_config, feed_columns, intensity_bar, args.large_response_threshold);
+    app.set_subnet_prefix(args.subnet_prefix);
+    app.set_new_ip_window(args.new_ip_window);
+    app.set_ascii(args.ascii);
+    app.set_confirm_quit(!args.no_confirm_quit);
+    app.set_thousands(!args.no_thousands);
+    app.set_url_width(args.url_width);
+    app.set_baseline_rps(args.baseline_rps);
+    app.set_decode_urls(args.decode_urls);
+    app.set_sample_line(file_paths.first().and_then(|path| read_first_line(path).ok().flatten()));
+    let app = Arc::new(Mutex::new(app));
     let app_clone = Arc::clone(&app);
+    let waiting_app = Arc::clone(&app);
 
     let handle = tokio::spawn(async move {
         let progress_callback = {
@@ -127,33 +722,86 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             }
         };
 
-        let mut last_processed_line: Option<usize> = None;
-        match tail_file(&file_path, count, &regex_pattern, &date_format, &log_data_clone, no_clear, None, progress_callback.clone()).await {
-            Ok(last_line) => {
-                last_processed_line = last_line;
+        let mut last_processed_lines: Vec<Option<usize>> = vec![None; file_paths.len()];
+        for (file_path, last_processed_line) in file_paths.iter().zip(last_processed_lines.iter_mut()) {
+            if wait_interval.is_none() || file_path.exists() {
+                continue;
             }
-            Err(e) => {
-                error!("Error reading file: {:?}", e);
+            waiting_app.lock().unwrap().set_waiting_for(Some(file_path.clone()));
+            // Polled here rather than with `wait_for_file` so quitting still stops the wait.
+            while !file_path.exists() {
+                if rx.try_recv().is_ok() {
+                    return;
+                }
+                sleep(poll_interval).await;
             }
+            waiting_app.lock().unwrap().set_waiting_for(None);
+            // Everything in a file that was just created is new, whatever --count says.
+            *last_processed_line = Some(0);
         }
 
+        let mut resolved_targets: Vec<Option<PathBuf>> = vec![None; file_paths.len()];
+        for ((file_path, last_processed_line), resolved_target) in file_paths.iter().zip(last_processed_lines.iter_mut()).zip(resolved_targets.iter_mut()) {
+            target_changed(file_path, resolved_target);
+            match tail_file(file_path, count, &regex_pattern, &date_format, &log_data_clone, &parse_options, *last_processed_line, progress_callback.clone()).await {
+                Ok(last_line) => {
+                    *last_processed_line = last_line;
+                }
+                Err(e) => {
+                    error!("Error reading file {}: {:?}", file_path.display(), e);
+                }
+            }
+        }
+
+        let export_metrics = |log_data: &Arc<Mutex<LogData>>| {
+            if let Some(path) = &prometheus_path {
+                let content = {
+                    let log_data = log_data.lock().unwrap();
+                    let suspicious_ips = security::find_suspicious_ips(&log_data, &status_security_config).len();
+                    metrics::format_prometheus(&log_data, suspicious_ips)
+                };
+                if let Err(e) = metrics::write_atomic(path, &content) {
+                    error!("Error writing Prometheus metrics: {:?}", e);
+                }
+            }
+            if let Some(path) = &status_json_path {
+                let content = {
+                    let log_data = log_data.lock().unwrap();
+                    let suspicious_ips = security::find_suspicious_ips(&log_data, &status_security_config).len();
+                    metrics::format_status_json(&log_data, suspicious_ips, chrono::Utc::now().timestamp())
+                };
+                if let Err(e) = metrics::write_atomic(path, &content) {
+                    error!("Error writing the status JSON: {:?}", e);
+                }
+            }
+        };
+        app_clone.lock().unwrap().finish_initial_read();
+        export_metrics(&log_data_clone);
+
         loop {
             if rx.try_recv().is_ok() {
                 break;
             }
-            match tail_file(&file_path, 0, &regex_pattern, &date_format, &log_data_clone, no_clear, last_processed_line.clone(), progress_callback.clone()).await {
-                Ok(last_line) => {
-                    last_processed_line = last_line;
+            for ((file_path, last_processed_line), resolved_target) in file_paths.iter().zip(last_processed_lines.iter_mut()).zip(resolved_targets.iter_mut()) {
+                if target_changed(file_path, resolved_target) {
+                    *last_processed_line = Some(0);
                 }
-                Err(e) => {
-                    error!("Error reading file: {:?}", e);
+                match tail_file(file_path, 0, &regex_pattern, &date_format, &log_data_clone, &parse_options, *last_processed_line, progress_callback.clone()).await {
+                    Ok(last_line) => {
+                        *last_processed_line = last_line;
+                    }
+                    Err(e) => {
+                        error!("Error reading file {}: {:?}", file_path.display(), e);
+                    }
                 }
             }
-            sleep(Duration::from_secs(1)).await;
+            export_metrics(&log_data_clone);
+            sleep(poll_interval).await;
         }
     });
 
 
+    let deadline = args.run_for.map(|seconds| Instant::now() + Duration::from_secs(seconds));
     loop {
         terminal.draw(|f| {
             let mut app = app.lock().unwrap();
@@ -167,7 +815,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             }
         }
 
-        if app.lock().unwrap().should_quit {
+        if app.lock().unwrap().should_quit || deadline.is_some_and(|deadline| Instant::now() >= deadline) {
             break;
         }
     }
@@ -179,7 +827,50 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     tx.send(()).unwrap();
     handle.await.unwrap();
 
+    if args.run_for.is_some() {
+        let log_data = summary_log_data.lock().unwrap();
+        let suspicious_ips = security::find_suspicious_ips(&log_data, &summary_security_config);
+        print!("{}", metrics::format_session_summary(&log_data, &suspicious_ips, !args.no_thousands));
+    }
+
     Ok(())
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
 
+    #[test]
+    fn test_build_runtime_honors_threads() {
+        assert_eq!(build_runtime(Some(2)).unwrap().metrics().num_workers(), 2);
+        assert_eq!(build_runtime(Some(1)).unwrap().metrics().num_workers(), 1);
+        assert!(build_runtime(None).unwrap().metrics().num_workers() >= 1);
+    }
+
+    #[test]
+    fn test_format_effective_config() {
+        let args = Cli::from_iter(["logutil", "a.log", "b.log", "--top", "20", "--allowed-methods", "GET,POST", "--group-by", "status", "--sample", "10"]);
+        let config = format_effective_config(&args, "^(\\S+) custom\n", &security_config(&args));
+
+        assert!(config.contains("mode:                     group by Status\n"), "{}", config);
+        assert!(config.contains("files:                    a.log, b.log\n"));
+        assert!(config.contains("regex:                    ^(\\S+) custom\n"));
+        assert!(config.contains("date format:              %d/%b/%Y:%H:%M:%S %z\n"));
+        assert!(config.contains("top:                      20\n"));
+        assert!(config.contains("sample:                   10%\n"));
+        assert!(config.contains("allowed methods:          GET,POST\n"));
+        assert!(config.contains("sensitive paths:          .env,.git,"));
+        assert!(config.contains("watch interval:           1000ms\n"));
+        assert!(config.contains("threads:                  -\n"));
+        assert_eq!(run_mode(&Cli::from_iter(["logutil", "a.log"])), "TUI");
+        assert_eq!(run_mode(&Cli::from_iter(["logutil", "a.log", "--export", "out.json", "--export-format", "json"])), "export to out.json (json)");
+        assert_eq!(run_mode(&Cli::from_iter(["logutil", "a.log", "--group-by", "ip", "--export", "out.json"])), "group by IP");
+    }
+
+    #[test]
+    fn test_watch_interval() {
+        assert_eq!(watch_interval(250), Ok(Duration::from_millis(250)));
+        assert_eq!(watch_interval(5000), Ok(Duration::from_secs(5)));
+        assert!(watch_interval(0).is_err());
+    }
+}
@@ -0,0 +1,271 @@
+use std::fmt::Write as _;
+use std::fs;
+use std::io;
+use std::path::Path;
+use chrono::{TimeZone, Utc};
+use crate::helpers::{format_count, safe_ratio};
+use crate::log_data::{GroupBy, IntervalStats, LogData};
+use crate::security::get_threat_level;
+
+const RPS_WINDOW: i64 = 60;
+
+/// Renders the current state as a Prometheus textfile (node_exporter textfile collector format).
+/// `suspicious_ips` is the number of IPs behind Security detections, as for `--status-json`.
+pub fn format_prometheus(log_data: &LogData, suspicious_ips: usize) -> String {
+    let (unique_ips, unique_urls) = log_data.get_unique_estimates().unwrap_or_else(|| log_data.get_unique_counts());
+    let classes = log_data.get_status_classes();
+    let mut out = String::new();
+
+    write_gauge(&mut out, "logutil_total_requests", "Total number of parsed requests.", log_data.total_requests as f64);
+    write_gauge(&mut out, "logutil_unique_ips", "Number of distinct client IPs.", unique_ips as f64);
+    write_gauge(&mut out, "logutil_unique_urls", "Number of distinct request URLs.", unique_urls as f64);
+    write_gauge(&mut out, "logutil_rps", "Average requests per second over the last minute of log time.", log_data.get_requests_per_second(RPS_WINDOW));
+    write_gauge(&mut out, "logutil_error_rate", "Share of 4xx and 5xx responses.", safe_ratio((classes[3] + classes[4]) as f64, log_data.total_requests as f64));
+    write_gauge(&mut out, "logutil_suspicious_ips", "Number of IPs behind Security tab detections.", suspicious_ips as f64);
+
+    out
+}
+
+/// Renders the `--status-json` health snapshot. `error_rate` is the share of 4xx and 5xx responses,
+/// `last_log_time` the newest log timestamp (null before the first request) and `updated_at` the write time.
+pub fn format_status_json(log_data: &LogData, suspicious_ips: usize, updated_at: i64) -> String {
+    let classes = log_data.get_status_classes();
+    let error_rate = safe_ratio((classes[3] + classes[4]) as f64, log_data.total_requests as f64);
+    let rfc3339 = |timestamp: i64| Utc.timestamp_opt(timestamp, 0).single().map(|dt| dt.to_rfc3339());
+    let last_log_time = log_data.get_time_span().and_then(|(_, last)| rfc3339(last)).map_or_else(|| "null".to_string(), |time| format!("\"{}\"", time));
+    format!(
+        "{{\"total_requests\":{},\"error_rate\":{:.4},\"rps\":{:.2},\"suspicious_ips\":{},\"last_log_time\":{},\"updated_at\":\"{}\"}}\n",
+        log_data.total_requests,
+        error_rate,
+        log_data.get_requests_per_second(RPS_WINDOW),
+        suspicious_ips,
+        last_log_time,
+        rfc3339(updated_at).unwrap_or_default(),
+    )
+}
+
+fn write_gauge(out: &mut String, name: &str, help: &str, value: f64) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} gauge", name);
+    let _ = writeln!(out, "{} {}", name, value);
+}
+
+/// Renders `--interval-summary` buckets as a plain-text table; errors are 5xx responses.
+pub fn format_interval_summary(intervals: &[(i64, &IntervalStats)], thousands: bool) -> String {
+    let mut out = format!("{:<20} {:>10} {:>8} {:>10}\n", "Interval (UTC)", "Requests", "Errors", "Unique IPs");
+    for (start, stats) in intervals {
+        let start = Utc.timestamp_opt(*start, 0).single().map_or_else(|| start.to_string(), |dt| dt.format("%Y-%m-%d %H:%M:%S").to_string());
+        let _ = writeln!(out, "{:<20} {:>10} {:>8} {:>10}", start, format_count(stats.requests, thousands), format_count(stats.errors, thousands), format_count(stats.ips.len(), thousands));
+    }
+    out
+}
+
+/// Entries listed per section of the session summary.
+const SUMMARY_TOP: usize = 5;
+
+/// Plain-text handoff of the session (`S` in the TUI): totals, error rate, the busiest IPs and URLs
+/// and the most suspicious IPs, short enough to paste into a chat or a ticket.
+pub fn format_session_summary(log_data: &LogData, suspicious_ips: &[(String, usize)], thousands: bool) -> String {
+    let count = |count: usize| format_count(count, thousands);
+    let time = |timestamp: i64| Utc.timestamp_opt(timestamp, 0).single().map_or_else(|| timestamp.to_string(), |dt| dt.format("%Y-%m-%d %H:%M:%S").to_string());
+    let classes = log_data.get_status_classes();
+    let (unique_ips, unique_urls) = log_data.get_unique_estimates().unwrap_or_else(|| log_data.get_unique_counts());
+
+    let mut out = match log_data.get_time_span() {
+        Some((first, last)) => format!("Log summary, {} to {} UTC\n", time(first), time(last)),
+        None => "Log summary\n".to_string(),
+    };
+    let _ = writeln!(
+        out,
+        "Requests: {} | Unique IPs: {} | Unique URLs: {} | Error rate: {:.1}% (4xx/5xx)",
+        count(log_data.total_requests),
+        count(unique_ips),
+        count(unique_urls),
+        safe_ratio((classes[3] + classes[4]) as f64, log_data.total_requests as f64) * 100.0,
+    );
+    for (title, group_by) in [("Top IPs", GroupBy::Ip), ("Top URLs", GroupBy::Url)] {
+        let entries = log_data.get_top_n_by(group_by, SUMMARY_TOP);
+        let _ = writeln!(out, "{}:", title);
+        for (key, requests) in entries {
+            let _ = writeln!(out, "  {} ({})", key, count(requests));
+        }
+    }
+    let _ = writeln!(out, "Top detections:");
+    if suspicious_ips.is_empty() {
+        let _ = writeln!(out, "  none");
+    }
+    for (ip, suspicious) in suspicious_ips.iter().take(SUMMARY_TOP) {
+        let _ = writeln!(out, "  {} {} ({} suspicious requests)", ip, get_threat_level(*suspicious).label(), count(*suspicious));
+    }
+    out
+}
+
+/// Renders `--group-by` counts as a plain-text table, busiest first.
+pub fn format_group_counts(label: &str, counts: &[(String, usize)], thousands: bool) -> String {
+    let mut out = format!("{:<50} {:>10}\n", label, "Requests");
+    for (key, count) in counts {
+        let _ = writeln!(out, "{:<50} {:>10}", key, format_count(*count, thousands));
+    }
+    out
+}
+
+/// Renders `--show-errors`: the status class distribution, then the top `n` error codes and
+/// the top `n` URLs by 4xx and 5xx responses, as plain-text tables.
+pub fn format_error_report(log_data: &LogData, n: usize, thousands: bool) -> String {
+    let count = |count: usize| format_count(count, thousands);
+    let classes = log_data.get_status_classes();
+    let errors = classes[3] + classes[4];
+    let mut out = format!("Errors: {} of {} requests\n\n{:<50} {:>10}\n", count(errors), count(log_data.total_requests), "Status class", "Requests");
+    for (class, &requests) in classes.iter().enumerate() {
+        let _ = writeln!(out, "{:<50} {:>10}", format!("{}xx", class + 1), count(requests));
+    }
+    let _ = write!(out, "\n{:<50} {:>10}\n", "Error code", "Requests");
+    for (status, requests) in log_data.get_top_error_statuses(n) {
+        let _ = writeln!(out, "{:<50} {:>10}", status, count(requests));
+    }
+    let _ = write!(out, "\n{:<50} {:>10}\n", "Error URL", "Errors");
+    for (url, errors) in log_data.get_top_error_urls(n) {
+        let _ = writeln!(out, "{:<50} {:>10}", url, count(errors));
+    }
+    out
+}
+
+/// Writes through a temporary file and renames it, so collectors never read a partial file.
+pub fn write_atomic(path: &Path, content: &str) -> io::Result<()> {
+    let mut tmp_name = path.file_name().unwrap_or_default().to_os_string();
+    tmp_name.push(".tmp");
+    let tmp_path = path.with_file_name(tmp_name);
+    fs::write(&tmp_path, content)?;
+    fs::rename(&tmp_path, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::log_data::RequestDetails;
+
+    #[test]
+    fn test_format_prometheus() {
+        let mut log_data = LogData::new();
+        for second in 0..60 {
+            log_data.add_entry("10.0.0.1".to_string(), "/a".to_string(), String::new(), 1_700_000_000 + second, "GET".to_string(), "example.com".to_string(), RequestDetails::default(), false);
+            let details = RequestDetails { status: Some(if second % 4 == 0 { 404 } else { 200 }), ..Default::default() };
+            log_data.add_entry("10.0.0.2".to_string(), "/b".to_string(), String::new(), 1_700_000_000 + second, "GET".to_string(), "example.com".to_string(), details, false);
+        }
+
+        let text = format_prometheus(&log_data, 3);
+
+        assert!(text.contains("# TYPE logutil_total_requests gauge\nlogutil_total_requests 120\n"));
+        assert!(text.contains("logutil_unique_ips 2\n"));
+        assert!(text.contains("logutil_unique_urls 2\n"));
+        assert!(text.contains("logutil_rps 2\n"));
+        assert!(text.contains("# TYPE logutil_error_rate gauge\nlogutil_error_rate 0.125\n"));
+        assert!(text.contains("logutil_suspicious_ips 3\n"));
+    }
+
+    #[test]
+    fn test_format_status_json() {
+        let mut log_data = LogData::new();
+        assert_eq!(
+            format_status_json(&log_data, 0, 1_700_000_100),
+            "{\"total_requests\":0,\"error_rate\":0.0000,\"rps\":0.00,\"suspicious_ips\":0,\"last_log_time\":null,\"updated_at\":\"2023-11-14T22:15:00+00:00\"}\n"
+        );
+        for (second, status) in [(0, 200), (30, 200), (59, 503), (59, 404)] {
+            let details = RequestDetails { status: Some(status), ..Default::default() };
+            log_data.add_entry("10.0.0.1".to_string(), "/".to_string(), String::new(), 1_700_000_000 + second, "GET".to_string(), "example.com".to_string(), details, false);
+        }
+
+        assert_eq!(
+            format_status_json(&log_data, 2, 1_700_000_100),
+            "{\"total_requests\":4,\"error_rate\":0.5000,\"rps\":0.07,\"suspicious_ips\":2,\"last_log_time\":\"2023-11-14T22:14:19+00:00\",\"updated_at\":\"2023-11-14T22:15:00+00:00\"}\n"
+        );
+    }
+
+    #[test]
+    fn test_format_session_summary() {
+        let mut log_data = LogData::new();
+        assert_eq!(format_session_summary(&log_data, &[], true), "Log summary\nRequests: 0 | Unique IPs: 0 | Unique URLs: 0 | Error rate: 0.0% (4xx/5xx)\nTop IPs:\nTop URLs:\nTop detections:\n  none\n");
+
+        for (second, ip, url, status) in [(0, "10.0.0.1", "/", 200), (10, "10.0.0.1", "/", 200), (20, "10.0.0.1", "/.env", 404), (60, "10.0.0.2", "/.env", 404)] {
+            let details = RequestDetails { status: Some(status), ..Default::default() };
+            log_data.add_entry(ip.to_string(), url.to_string(), String::new(), 1_700_000_000 + second, "GET".to_string(), "example.com".to_string(), details, false);
+        }
+        let suspicious = vec![("10.0.0.1".to_string(), 25), ("10.0.0.2".to_string(), 1)];
+
+        assert_eq!(format_session_summary(&log_data, &suspicious, true), "\
+Log summary, 2023-11-14 22:13:20 to 2023-11-14 22:14:20 UTC
+Requests: 4 | Unique IPs: 2 | Unique URLs: 2 | Error rate: 50.0% (4xx/5xx)
+Top IPs:
+  10.0.0.1 (3)
+  10.0.0.2 (1)
+Top URLs:
+  / (2)
+  /.env (2)
+Top detections:
+  10.0.0.1 HIGH (25 suspicious requests)
+  10.0.0.2 LOW (1 suspicious requests)
+");
+    }
+
+    #[test]
+    fn test_format_group_counts() {
+        let counts = vec![("200".to_string(), 3), ("404".to_string(), 1)];
+
+        assert_eq!(
+            format_group_counts("Status", &counts, true),
+            format!("{:<50} {:>10}\n{:<50} {:>10}\n{:<50} {:>10}\n", "Status", "Requests", "200", 3, "404", 1)
+        );
+
+        let counts = vec![("200".to_string(), 1_234_567)];
+        assert_eq!(format_group_counts("Status", &counts, true), format!("{:<50} {:>10}\n{:<50} {:>10}\n", "Status", "Requests", "200", "1,234,567"));
+        assert_eq!(format_group_counts("Status", &counts, false), format!("{:<50} {:>10}\n{:<50} {:>10}\n", "Status", "Requests", "200", "1234567"));
+    }
+
+    #[test]
+    fn test_format_error_report() {
+        let mut log_data = LogData::new();
+        for (url, status) in [("/", 200), ("/missing", 404), ("/missing", 404), ("/api", 503), ("/api", 200), ("/old", 301), ("/gone", 410)] {
+            let details = RequestDetails { status: Some(status), ..Default::default() };
+            log_data.add_entry("10.0.0.1".to_string(), url.to_string(), String::new(), 0, "GET".to_string(), "example.com".to_string(), details, false);
+        }
+
+        let row = |key: &str, count: usize| format!("{:<50} {:>10}\n", key, count);
+        let expected = [
+            "Errors: 4 of 7 requests\n\n".to_string(),
+            format!("{:<50} {:>10}\n", "Status class", "Requests"),
+            row("1xx", 0), row("2xx", 2), row("3xx", 1), row("4xx", 3), row("5xx", 1),
+            format!("\n{:<50} {:>10}\n", "Error code", "Requests"),
+            row("404", 2), row("410", 1),
+            format!("\n{:<50} {:>10}\n", "Error URL", "Errors"),
+            row("/missing", 2), row("/api", 1),
+        ].concat();
+        assert_eq!(format_error_report(&log_data, 2, true), expected);
+    }
+
+    #[test]
+    fn test_format_interval_summary() {
+        let mut log_data = LogData::new();
+        log_data.track_intervals(3600);
+        for (ip, timestamp, status) in [("10.0.0.1", 1_700_000_000, 200), ("10.0.0.2", 1_700_000_100, 503), ("10.0.0.1", 1_700_004_000, 200)] {
+            let details = RequestDetails { status: Some(status), ..Default::default() };
+            log_data.add_entry(ip.to_string(), "/".to_string(), String::new(), timestamp, "GET".to_string(), "example.com".to_string(), details, false);
+        }
+
+        assert_eq!(
+            format_interval_summary(&log_data.get_interval_stats(), true),
+            "Interval (UTC)         Requests   Errors Unique IPs\n\
+             2023-11-14 22:00:00           2        1          2\n\
+             2023-11-14 23:00:00           1        0          1\n"
+        );
+    }
+
+    #[test]
+    fn test_write_atomic() {
+        let path = std::env::temp_dir().join(format!("logutil_{}_metrics.prom", std::process::id()));
+        write_atomic(&path, "logutil_total_requests 1\n").unwrap();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "logutil_total_requests 1\n");
+        assert!(!path.with_file_name(format!("logutil_{}_metrics.prom.tmp", std::process::id())).exists());
+        fs::remove_file(&path).unwrap();
+    }
+}
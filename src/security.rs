@@ -0,0 +1,994 @@
+use std::collections::{HashMap, HashSet};
+use std::net::IpAddr;
+use std::str::FromStr;
+use crate::helpers::percent_decode;
+use crate::log_data::{LogData, RefererStats};
+
+/// Distinct query strings on one path needed to report cache busting (`--cache-busting-threshold`).
+pub const DEFAULT_CACHE_BUSTING_QUERIES: usize = 50;
+/// Cache busting is reported only when this few IPs produce the distinct queries.
+const CACHE_BUSTING_MAX_IPS: usize = 5;
+
+/// Response time in seconds from which a request counts as slow (`--slow-threshold`).
+pub const DEFAULT_SLOW_THRESHOLD: f64 = 10.0;
+/// Slow requests among an IP's sampled response times needed to list it as a slow client.
+pub const SLOW_REQUESTS_THRESHOLD: usize = 5;
+
+/// Identical requests from one IP needed to be listed as automated.
+pub const REPEAT_THRESHOLD: usize = 10;
+
+pub const DEFAULT_ALLOWED_METHODS: &[&str] = &["GET", "POST", "HEAD", "PUT", "DELETE", "OPTIONS", "PATCH"];
+
+/// Methods whose requests normally carry no body; large ones are listed as method/size mismatches.
+pub const BODILESS_METHODS: &[&str] = &["GET", "HEAD", "DELETE", "OPTIONS", "TRACE"];
+
+pub const DEFAULT_SENSITIVE_PATHS: &[&str] = &[
+    ".env", ".git", ".svn", ".htaccess", ".htpasswd", ".sql", ".bak", "phpinfo.php", ".ds_store", "wp-config",
+    "../", "..\\", "/etc/passwd",
+];
+
+/// Rounds of percent-decoding before matching, enough for double-encoded `%252e%252e%252f`.
+const MAX_DECODE_ROUNDS: usize = 3;
+
+/// Admin panels and setup pages of common CMSs and tools, as `name:/path` (`--cms-probes`).
+/// A request probes one when its URL starts with the path; a bare `/path` is its own bucket.
+pub const DEFAULT_CMS_PROBES: &[&str] = &[
+    "WordPress:/wp-admin", "WordPress:/wp-login.php", "WordPress:/xmlrpc.php", "WordPress:/wp-config.php",
+    "Joomla:/administrator", "Drupal:/core/install.php", "Magento:/downloader",
+    "phpMyAdmin:/phpmyadmin", "phpMyAdmin:/pma", "Adminer:/adminer",
+];
+
+/// Login and token endpoints whose 401/403 bursts are reported as credential stuffing (`--auth-endpoints`).
+pub const DEFAULT_AUTH_ENDPOINTS: &[&str] = &[
+    "/login", "/signin", "/sign_in", "/wp-login.php", "/xmlrpc.php", "/user/login", "/admin/login",
+    "/api/login", "/api/auth", "/auth", "/oauth/token", "/session", "/account/login",
+];
+/// 401/403 responses on auth endpoints from one IP within the window (`--auth-failure-threshold`).
+pub const DEFAULT_AUTH_FAILURE_THRESHOLD: usize = 10;
+/// 401/403 responses on one auth endpoint from all IPs within the window (`--auth-endpoint-threshold`),
+/// which catches stuffing spread over many addresses.
+pub const DEFAULT_AUTH_ENDPOINT_THRESHOLD: usize = 50;
+/// Seconds a burst of auth failures must fit in (`--auth-window`).
+pub const DEFAULT_AUTH_WINDOW: i64 = 60;
+
+/// Referer spam domains (hosts and their subdomains) reported in the Security tab.
+pub const DEFAULT_SPAM_REFERERS: &[&str] = &[
+    "semalt.com", "buttons-for-website.com", "darodar.com", "ilovevitaly.com", "priceg.com", "best-seo-offer.com",
+    "social-buttons.com", "free-share-buttons.com", "get-free-traffic-now.com", "hulfingtonpost.com",
+];
+
+#[derive(Clone)]
+pub struct SecurityConfig {
+    pub sensitive_paths: Vec<String>,
+    /// Methods considered normal; anything else is listed as an unusual method.
+    pub allowed_methods: Vec<String>,
+    pub cache_busting_queries: usize,
+    pub spam_referers: Vec<String>,
+    /// Admin and config paths as `name:/path`, probed by scanners looking for a CMS.
+    pub cms_probes: Vec<String>,
+    pub slow_threshold: f64,
+    /// Whether identical requests are counted per IP (`--detect-repeats`).
+    pub detect_repeats: bool,
+    /// URL prefixes of login endpoints, and the 401/403 bursts on them reported as credential stuffing.
+    pub auth_endpoints: Vec<String>,
+    pub auth_failure_threshold: usize,
+    pub auth_endpoint_threshold: usize,
+    pub auth_window: i64,
+}
+
+impl Default for SecurityConfig {
+    fn default() -> Self {
+        Self {
+            sensitive_paths: DEFAULT_SENSITIVE_PATHS.iter().map(|p| p.to_string()).collect(),
+            allowed_methods: DEFAULT_ALLOWED_METHODS.iter().map(|m| m.to_string()).collect(),
+            cache_busting_queries: DEFAULT_CACHE_BUSTING_QUERIES,
+            spam_referers: DEFAULT_SPAM_REFERERS.iter().map(|d| d.to_string()).collect(),
+            cms_probes: DEFAULT_CMS_PROBES.iter().map(|p| p.to_string()).collect(),
+            slow_threshold: DEFAULT_SLOW_THRESHOLD,
+            detect_repeats: false,
+            auth_endpoints: DEFAULT_AUTH_ENDPOINTS.iter().map(|e| e.to_string()).collect(),
+            auth_failure_threshold: DEFAULT_AUTH_FAILURE_THRESHOLD,
+            auth_endpoint_threshold: DEFAULT_AUTH_ENDPOINT_THRESHOLD,
+            auth_window: DEFAULT_AUTH_WINDOW,
+        }
+    }
+}
+
+/// A resource that matched a detection, with its hit count and the IPs requesting it.
+pub struct Finding {
+    pub target: String,
+    pub count: usize,
+    pub sources: Vec<(String, usize)>,
+}
+
+pub fn is_sensitive_path(url: &str, sensitive_paths: &[String]) -> bool {
+    let url = normalize_for_matching(url);
+    sensitive_paths.iter().any(|path| url.contains(&normalize_for_matching(path)))
+}
+
+/// Percent-decodes (repeatedly, for double encoding), lowercases and drops SQL comments (`/*...*/`)
+/// and whitespace (also `+`), so that `UnIoN/**/SeLeCt` and `un ion select` match the pattern
+/// `union select` and `..%2f` matches `../`.
+pub fn normalize_for_matching(text: &str) -> String {
+    let mut text = text.to_string();
+    for _ in 0..MAX_DECODE_ROUNDS {
+        let decoded = percent_decode(&text);
+        if decoded == text {
+            break;
+        }
+        text = decoded;
+    }
+    let mut text = text.to_lowercase();
+    while let Some(start) = text.find("/*") {
+        let end = text[start + 2..].find("*/").map_or(text.len(), |end| start + 2 + end + 2);
+        text.replace_range(start..end, "");
+    }
+    text.chars().filter(|c| !c.is_whitespace() && *c != '+').collect()
+}
+
+/// Requests for sensitive files (`.env`, `.git/config`, dumps...) ranked by hits.
+pub fn find_sensitive_file_probes(log_data: &LogData, config: &SecurityConfig, n: usize) -> Vec<Finding> {
+    let mut findings: Vec<Finding> = log_data
+        .urls()
+        .filter(|(url, _)| is_sensitive_path(url, &config.sensitive_paths))
+        .map(|(url, entry)| Finding {
+            target: url.clone(),
+            count: entry.count,
+            sources: top_sources(entry.clients.iter().map(|(ip, &count)| (ip.clone(), count)).collect(), 5),
+        })
+        .collect();
+    findings.sort_by_key(|f| std::cmp::Reverse(f.count));
+    findings.truncate(n);
+    findings
+}
+
+pub fn is_unusual_method(method: &str, allowed_methods: &[String]) -> bool {
+    !allowed_methods.iter().any(|allowed| allowed.eq_ignore_ascii_case(method))
+}
+
+/// Requests using methods outside the allowlist (TRACE, CONNECT, DEBUG...), ranked by hits.
+pub fn find_unusual_methods(log_data: &LogData, config: &SecurityConfig, n: usize) -> Vec<Finding> {
+    let mut findings: Vec<Finding> = log_data
+        .by_method
+        .iter()
+        .filter(|(method, _)| is_unusual_method(method, &config.allowed_methods))
+        .map(|(method, stats)| Finding {
+            target: method.clone(),
+            count: stats.count,
+            sources: top_sources(stats.clients.iter().map(|(ip, &count)| (ip.clone(), count)).collect(), 5),
+        })
+        .collect();
+    findings.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.target.cmp(&b.target)));
+    findings.truncate(n);
+    findings
+}
+
+/// Methods that give a client away as a script when they are the only ones it sends.
+pub const AUTOMATION_METHODS: &[&str] = &["POST", "HEAD"];
+
+/// An IP that only ever sent POST or HEAD requests, e.g. a login brute-forcer or an uptime probe.
+pub fn is_automated_method_profile(profile: &[(String, usize, f64)]) -> bool {
+    matches!(profile, [(method, _, _)] if AUTOMATION_METHODS.iter().any(|automated| automated.eq_ignore_ascii_case(method)))
+}
+
+pub fn is_bodiless_method(method: &str) -> bool {
+    BODILESS_METHODS.iter().any(|bodiless| bodiless.eq_ignore_ascii_case(method))
+}
+
+/// Requests of at least `LARGE_REQUEST_SIZE` bytes on methods that should not carry a body,
+/// ranked by hits. Empty when the regex has no `req_size` group.
+pub fn find_body_mismatches(log_data: &LogData, n: usize) -> Vec<Finding> {
+    let mut findings: Vec<Finding> = log_data
+        .large_requests
+        .iter()
+        .filter(|(method, _)| is_bodiless_method(method))
+        .map(|(method, stats)| Finding {
+            target: method.clone(),
+            count: stats.count,
+            sources: top_sources(stats.clients.iter().map(|(ip, &count)| (ip.clone(), count)).collect(), 5),
+        })
+        .collect();
+    findings.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.target.cmp(&b.target)));
+    findings.truncate(n);
+    findings
+}
+
+/// An IP that requested admin or config paths of CMSs, with how many distinct `--cms-probes`
+/// entries it hit and which CMSs.
+#[derive(Debug, PartialEq)]
+pub struct CmsProber {
+    pub ip: String,
+    pub paths: usize,
+    pub cms: Vec<String>,
+}
+
+/// The `--cms-probes` entry whose path `url` is or falls under, e.g. `WordPress:/wp-admin` for `/wp-admin/setup.php`.
+pub fn cms_probe<'a>(url: &str, cms_probes: &'a [String]) -> Option<&'a str> {
+    let url = url.to_lowercase();
+    cms_probes.iter().map(String::as_str).find(|probe| {
+        let path = probe.split_once(':').map_or(*probe, |(_, path)| path);
+        is_under_path(&url, &path.to_lowercase())
+    })
+}
+
+/// CMS name of a `--cms-probes` entry; a bare `/path` is its own name.
+fn cms_name(probe: &str) -> &str {
+    probe.split_once(':').map_or(probe, |(name, _)| name)
+}
+
+/// IPs ranked by the number of distinct CMS admin paths they requested, which tells a scanner
+/// sweeping many CMSs apart from a user who mistyped one URL.
+pub fn find_cms_probers(log_data: &LogData, config: &SecurityConfig, n: usize) -> Vec<CmsProber> {
+    let mut probes: HashMap<&str, HashSet<&str>> = HashMap::new();
+    for (url, entry) in log_data.urls() {
+        let Some(probe) = cms_probe(url, &config.cms_probes) else { continue };
+        for ip in entry.clients.keys() {
+            probes.entry(ip).or_default().insert(probe);
+        }
+    }
+    let mut probers: Vec<CmsProber> = probes.into_iter()
+        .map(|(ip, probes)| {
+            let mut cms: Vec<String> = probes.iter().map(|probe| cms_name(probe).to_string()).collect();
+            cms.sort();
+            cms.dedup();
+            CmsProber { ip: ip.to_string(), paths: probes.len(), cms }
+        })
+        .collect();
+    probers.sort_by(|a, b| b.paths.cmp(&a.paths).then_with(|| a.ip.cmp(&b.ip)));
+    probers.truncate(n);
+    probers
+}
+
+/// Whether a referer host is a listed spam domain or one of its subdomains.
+pub fn is_spam_referer(host: &str, spam_referers: &[String]) -> bool {
+    spam_referers.iter().any(|domain| {
+        let domain = domain.to_lowercase();
+        host == domain || host.strip_suffix(&domain).is_some_and(|prefix| prefix.ends_with('.'))
+    })
+}
+
+/// Referer hosts from the spam domain list, referer hosts imitating the requested host and page
+/// assets requested without a referer, ranked by the requests concerned.
+pub fn find_referer_spam(log_data: &LogData, config: &SecurityConfig, n: usize) -> Vec<Finding> {
+    let finding = |target: String, stats: &RefererStats| Finding {
+        target,
+        count: stats.count,
+        sources: top_sources(stats.clients.iter().map(|(ip, &count)| (ip.clone(), count)).collect(), 5),
+    };
+    let spam = log_data.by_referer.iter()
+        .filter(|(host, _)| is_spam_referer(host, &config.spam_referers))
+        .map(|(host, stats)| finding(host.clone(), stats));
+    let look_alikes = log_data.look_alike_referers.iter().map(|(host, stats)| finding(format!("{} (look-alike host)", host), stats));
+    let empty = log_data.empty_referers.iter().map(|(url, stats)| finding(format!("{} (asset without referer)", url), stats));
+    let mut findings: Vec<Finding> = spam.chain(look_alikes).chain(empty).collect();
+    findings.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.target.cmp(&b.target)));
+    findings.truncate(n);
+    findings
+}
+
+/// Paths hit with many distinct query strings by only a few IPs, which bypasses caches (a DoS pattern).
+/// `count` is the number of distinct query strings.
+pub fn find_cache_busting(log_data: &LogData, config: &SecurityConfig, n: usize) -> Vec<Finding> {
+    let mut findings: Vec<Finding> = log_data
+        .urls()
+        .filter(|(_, entry)| entry.distinct_queries.len() >= config.cache_busting_queries && entry.clients.len() <= CACHE_BUSTING_MAX_IPS)
+        .map(|(url, entry)| Finding {
+            target: url.clone(),
+            count: entry.distinct_queries.len(),
+            sources: top_sources(entry.clients.iter().map(|(ip, &count)| (ip.clone(), count)).collect(), CACHE_BUSTING_MAX_IPS),
+        })
+        .collect();
+    findings.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.target.cmp(&b.target)));
+    findings.truncate(n);
+    findings
+}
+
+/// Whether `url` is one of the endpoints or below it, so `/auth` covers `/auth/token` but not `/author`.
+pub fn is_auth_endpoint(url: &str, auth_endpoints: &[String]) -> bool {
+    let url = url.to_lowercase();
+    auth_endpoints.iter().any(|endpoint| is_under_path(&url, &endpoint.to_lowercase()))
+}
+
+/// Whether `url` is `path` or below it on a segment boundary; both are expected in lowercase.
+fn is_under_path(url: &str, path: &str) -> bool {
+    url.strip_prefix(path.trim_end_matches('/')).is_some_and(|rest| rest.is_empty() || rest.starts_with(['/', '?', ';']))
+}
+
+/// Most timestamps falling within `window` seconds of each other.
+fn peak_in_window(mut times: Vec<i64>, window: i64) -> usize {
+    times.sort_unstable();
+    let mut start = 0;
+    let mut peak = 0;
+    for end in 0..times.len() {
+        while times[end] - times[start] >= window {
+            start += 1;
+        }
+        peak = peak.max(end - start + 1);
+    }
+    peak
+}
+
+/// IPs with a burst of `--auth-failure-threshold` 401/403 responses on auth endpoints within
+/// `--auth-window`, with their auth failures in total.
+pub fn find_credential_stuffers(log_data: &LogData, config: &SecurityConfig) -> HashMap<String, usize> {
+    let mut failures: HashMap<&str, (usize, Vec<i64>)> = HashMap::new();
+    for (_, stats) in log_data.auth_failures.iter().filter(|(url, _)| is_auth_endpoint(url, &config.auth_endpoints)) {
+        for (ip, &count) in &stats.clients {
+            let (total, times) = failures.entry(ip).or_default();
+            *total += count;
+            times.extend(stats.times.get(ip).into_iter().flatten());
+        }
+    }
+    failures.into_iter()
+        .filter(|(_, (_, times))| peak_in_window(times.clone(), config.auth_window) >= config.auth_failure_threshold)
+        .map(|(ip, (total, _))| (ip.to_string(), total))
+        .collect()
+}
+
+/// Auth endpoints under credential stuffing: a burst of `--auth-endpoint-threshold` 401/403
+/// responses across all IPs within `--auth-window`, or one IP over `--auth-failure-threshold`.
+/// `count` is the most failures within one window, ranked by it.
+pub fn find_credential_stuffing(log_data: &LogData, config: &SecurityConfig, n: usize) -> Vec<Finding> {
+    let stuffers = find_credential_stuffers(log_data, config);
+    let mut findings: Vec<Finding> = log_data
+        .auth_failures
+        .iter()
+        .filter(|(url, _)| is_auth_endpoint(url, &config.auth_endpoints))
+        .filter_map(|(url, stats)| {
+            let peak = peak_in_window(stats.times.values().flatten().copied().collect(), config.auth_window);
+            let flagged_ip = stats.clients.keys().any(|ip| stuffers.contains_key(ip));
+            (peak >= config.auth_endpoint_threshold || flagged_ip).then(|| Finding {
+                target: url.clone(),
+                count: peak,
+                sources: top_sources(stats.clients.iter().map(|(ip, &count)| (ip.clone(), count)).collect(), 5),
+            })
+        })
+        .collect();
+    findings.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.target.cmp(&b.target)));
+    findings.truncate(n);
+    findings
+}
+
+/// IPs behind the detections with the number of suspicious requests they made, highest first.
+pub fn find_suspicious_ips(log_data: &LogData, config: &SecurityConfig) -> Vec<(String, usize)> {
+    let mut ips: HashMap<String, usize> = HashMap::new();
+    let probes = log_data.urls().filter(|(url, _)| is_sensitive_path(url, &config.sensitive_paths)).map(|(_, entry)| &entry.clients);
+    let methods = log_data.by_method.iter().filter(|(method, _)| is_unusual_method(method, &config.allowed_methods)).map(|(_, stats)| &stats.clients);
+    let bodies = log_data.large_requests.iter().filter(|(method, _)| is_bodiless_method(method)).map(|(_, stats)| &stats.clients);
+    let stuffers = find_credential_stuffers(log_data, config);
+    for clients in probes.chain(methods).chain(bodies).chain([&stuffers]) {
+        for (ip, count) in clients {
+            *ips.entry(ip.clone()).or_insert(0) += count;
+        }
+    }
+    top_sources(ips.into_iter().collect(), usize::MAX)
+}
+
+/// The detections one IP triggered (probed files, unusual methods, spam and look-alike referers), with its
+/// request count for each, highest first.
+pub fn ip_patterns(log_data: &LogData, config: &SecurityConfig, ip: &str) -> Vec<(String, usize)> {
+    let probes = log_data.urls()
+        .filter(|(url, _)| is_sensitive_path(url, &config.sensitive_paths))
+        .filter_map(|(url, entry)| entry.clients.get(ip).map(|&count| (format!("probe {}", url), count)));
+    let methods = log_data.by_method.iter()
+        .filter(|(method, _)| is_unusual_method(method, &config.allowed_methods))
+        .filter_map(|(method, stats)| stats.clients.get(ip).map(|&count| (format!("method {}", method), count)));
+    let bodies = log_data.large_requests.iter()
+        .filter(|(method, _)| is_bodiless_method(method))
+        .filter_map(|(method, stats)| stats.clients.get(ip).map(|&count| (format!("large {} body", method), count)));
+    let referers = log_data.by_referer.iter()
+        .filter(|(host, _)| is_spam_referer(host, &config.spam_referers))
+        .filter_map(|(host, stats)| stats.clients.get(ip).map(|&count| (format!("spam referer {}", host), count)));
+    let look_alikes = log_data.look_alike_referers.iter()
+        .filter_map(|(host, stats)| stats.clients.get(ip).map(|&count| (format!("look-alike referer {}", host), count)));
+    let stuffing = find_credential_stuffers(log_data, config).contains_key(ip);
+    let auth_failures = log_data.auth_failures.iter()
+        .filter(|(url, _)| stuffing && is_auth_endpoint(url, &config.auth_endpoints))
+        .filter_map(|(url, stats)| stats.clients.get(ip).map(|&count| (format!("auth failures {}", url), count)));
+    top_sources(probes.chain(methods).chain(bodies).chain(referers).chain(look_alikes).chain(auth_failures).collect(), usize::MAX)
+}
+
+/// Plain-text block describing one suspicious IP, ready to paste into an abuse report.
+pub fn format_ip_report(ip: &str, count: usize, level: ThreatLevel, patterns: &[(String, usize)]) -> String {
+    let mut report = format!("IP: {}\nSuspicious requests: {}\nThreat level: {}\nPatterns:\n", ip, count, level.label());
+    for (pattern, hits) in patterns {
+        report.push_str(&format!("  - {} ({})\n", pattern, hits));
+    }
+    report
+}
+
+/// Why an IP was flagged (`e` in the Security tab): the detections it triggered and the lines behind them.
+#[derive(Debug, PartialEq)]
+pub struct Explanation {
+    pub ip: String,
+    pub total_requests: usize,
+    /// The score the threat level is rated from, as counted by `find_suspicious_ips`.
+    pub suspicious_requests: usize,
+    pub level: ThreatLevel,
+    pub patterns: Vec<(String, usize)>,
+    /// Stored lines of the IP (its last 10) that hit a sensitive path or used an unusual method.
+    pub example_lines: Vec<String>,
+}
+
+/// Assembles the explanation of a suspicious IP, `None` when no detection flagged it.
+pub fn explain_ip(log_data: &LogData, config: &SecurityConfig, ip: &str) -> Option<Explanation> {
+    let suspicious_requests = find_suspicious_ips(log_data, config).into_iter().find(|(flagged, _)| flagged == ip)?.1;
+    let entry = log_data.by_ip.get(ip)?;
+    let example_lines = entry.last_requests.iter()
+        .filter(|line| {
+            is_sensitive_path(line, &config.sensitive_paths)
+                || line.split(|c: char| c == '"' || c.is_whitespace()).any(|token| log_data.by_method.contains_key(token) && is_unusual_method(token, &config.allowed_methods))
+        })
+        .cloned()
+        .collect();
+    Some(Explanation {
+        ip: ip.to_string(),
+        total_requests: entry.count,
+        suspicious_requests,
+        level: get_threat_level(suspicious_requests),
+        patterns: ip_patterns(log_data, config, ip),
+        example_lines,
+    })
+}
+
+/// Lines of the explanation popup, from the verdict down to the evidence.
+pub fn format_explanation(explanation: &Explanation) -> Vec<String> {
+    let mut lines = vec![
+        format!("Threat level: {}", explanation.level.label()),
+        format!("Requests: {} in total, {} suspicious", explanation.total_requests, explanation.suspicious_requests),
+        format!(
+            "Score: {} suspicious requests (MEDIUM from {}, HIGH from {})",
+            explanation.suspicious_requests, MEDIUM_THREAT_REQUESTS, HIGH_THREAT_REQUESTS
+        ),
+        "Signals:".to_string(),
+    ];
+    lines.extend(explanation.patterns.iter().map(|(pattern, hits)| format!("  - {} ({})", pattern, hits)));
+    lines.push("Example lines:".to_string());
+    if explanation.example_lines.is_empty() {
+        lines.push("  (none among the last stored lines)".to_string());
+    }
+    lines.extend(explanation.example_lines.iter().map(|line| format!("  {}", line)));
+    lines
+}
+
+/// Suspicious requests from one IP needed for a medium and for a high threat level.
+const MEDIUM_THREAT_REQUESTS: usize = 5;
+const HIGH_THREAT_REQUESTS: usize = 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ThreatLevel {
+    Low,
+    Medium,
+    High,
+}
+
+impl ThreatLevel {
+    pub fn label(&self) -> &'static str {
+        match self {
+            ThreatLevel::Low => "LOW",
+            ThreatLevel::Medium => "MEDIUM",
+            ThreatLevel::High => "HIGH",
+        }
+    }
+}
+
+/// Rates an IP by the number of suspicious requests returned for it by `find_suspicious_ips`.
+pub fn get_threat_level(suspicious_requests: usize) -> ThreatLevel {
+    if suspicious_requests >= HIGH_THREAT_REQUESTS {
+        ThreatLevel::High
+    } else if suspicious_requests >= MEDIUM_THREAT_REQUESTS {
+        ThreatLevel::Medium
+    } else {
+        ThreatLevel::Low
+    }
+}
+
+/// Which threat levels the Security tab shows, cycled with a key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ThreatFilter {
+    #[default]
+    All,
+    High,
+    MediumPlus,
+}
+
+impl ThreatFilter {
+    pub fn next(self) -> Self {
+        match self {
+            ThreatFilter::All => ThreatFilter::High,
+            ThreatFilter::High => ThreatFilter::MediumPlus,
+            ThreatFilter::MediumPlus => ThreatFilter::All,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ThreatFilter::All => "ALL",
+            ThreatFilter::High => "HIGH",
+            ThreatFilter::MediumPlus => "MEDIUM+",
+        }
+    }
+
+    pub fn allows(&self, level: ThreatLevel) -> bool {
+        match self {
+            ThreatFilter::All => true,
+            ThreatFilter::High => level == ThreatLevel::High,
+            ThreatFilter::MediumPlus => level >= ThreatLevel::Medium,
+        }
+    }
+}
+
+/// IPs that sent the same request at least `min_repeats` times, with their highest repeat count.
+/// Byte-identical requests in a row are typical of scripts rather than browsers.
+pub fn find_repeating_ips(log_data: &LogData, min_repeats: usize) -> Vec<(String, usize)> {
+    let ips = log_data
+        .by_ip
+        .iter()
+        .filter_map(|(ip, entry)| {
+            let repeats = entry.repeated_lines.values().copied().max()?;
+            (repeats >= min_repeats).then(|| (ip.clone(), repeats))
+        })
+        .collect();
+    top_sources(ips, usize::MAX)
+}
+
+/// IPs with at least `min_slow` sampled responses slower than `--slow-threshold`, with that count.
+/// Many slow requests from one client hint at a slowloris-style attempt to tie up workers.
+pub fn find_slow_clients(log_data: &LogData, config: &SecurityConfig, min_slow: usize) -> Vec<(String, usize)> {
+    let ips = log_data
+        .by_ip
+        .iter()
+        .filter_map(|(ip, entry)| {
+            let slow = entry.response_times.iter().filter(|&&time| time >= config.slow_threshold).count();
+            (slow >= min_slow).then(|| (ip.clone(), slow))
+        })
+        .collect();
+    top_sources(ips, usize::MAX)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DenyFormat {
+    Iptables,
+    Nginx,
+    Plain,
+}
+
+impl FromStr for DenyFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "iptables" => Ok(DenyFormat::Iptables),
+            "nginx" => Ok(DenyFormat::Nginx),
+            "plain" => Ok(DenyFormat::Plain),
+            _ => Err(format!("unknown deny list format '{}', expected iptables, nginx or plain", s)),
+        }
+    }
+}
+
+/// Renders ready-to-apply block rules. Anything that is not a valid IP address is skipped,
+/// since the values come straight from the log and end up in shell commands or nginx config.
+pub fn format_deny_list(ips: &[(String, usize)], format: DenyFormat, threshold: usize) -> String {
+    ips.iter()
+        .filter(|(_, count)| *count >= threshold)
+        .filter_map(|(ip, _)| ip.parse::<IpAddr>().ok())
+        .map(|ip| match (format, ip) {
+            (DenyFormat::Iptables, IpAddr::V6(_)) => format!("ip6tables -A INPUT -s {} -j DROP\n", ip),
+            (DenyFormat::Iptables, IpAddr::V4(_)) => format!("iptables -A INPUT -s {} -j DROP\n", ip),
+            (DenyFormat::Nginx, _) => format!("deny {};\n", ip),
+            (DenyFormat::Plain, _) => format!("{}\n", ip),
+        })
+        .collect()
+}
+
+fn top_sources(mut sources: Vec<(String, usize)>, n: usize) -> Vec<(String, usize)> {
+    sources.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    sources.truncate(n);
+    sources
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helpers::referer_host;
+    use crate::log_data::RequestDetails;
+
+    fn add(log_data: &mut LogData, ip: &str, url: &str) {
+        log_data.add_entry(ip.to_string(), url.to_string(), String::new(), 0, "GET".to_string(), "example.com".to_string(), RequestDetails::default(), false);
+    }
+
+    fn fail(log_data: &mut LogData, ip: &str, url: &str, timestamp: i64, status: u16) {
+        let details = RequestDetails { status: Some(status), ..Default::default() };
+        log_data.add_entry(ip.to_string(), url.to_string(), String::new(), timestamp, "POST".to_string(), "example.com".to_string(), details, false);
+    }
+
+    #[test]
+    fn test_find_sensitive_file_probes() {
+        let mut log_data = LogData::new();
+        add(&mut log_data, "203.0.113.5", "/.env");
+        add(&mut log_data, "203.0.113.5", "/.env");
+        add(&mut log_data, "203.0.113.9", "/.env");
+        add(&mut log_data, "203.0.113.5", "/.git/config");
+        add(&mut log_data, "192.168.0.1", "/index.html");
+        add(&mut log_data, "192.168.0.1", "/index.php");
+
+        let findings = find_sensitive_file_probes(&log_data, &SecurityConfig::default(), 10);
+
+        assert_eq!(findings.len(), 2);
+        assert_eq!(findings[0].target, "/.env");
+        assert_eq!(findings[0].count, 3);
+        assert_eq!(findings[0].sources, vec![("203.0.113.5".to_string(), 2), ("203.0.113.9".to_string(), 1)]);
+        assert_eq!(findings[1].target, "/.git/config");
+    }
+
+    #[test]
+    fn test_is_sensitive_path_normalizes_evasion() {
+        let patterns = vec!["union select".to_string()];
+
+        assert!(is_sensitive_path("/item/1 UnIoN SeLeCt password", &patterns));
+        assert!(is_sensitive_path("/item/1/**/union/**/select", &patterns));
+        assert!(is_sensitive_path("/item/1/*x*/UNION/*y*/SELECT", &patterns));
+        assert!(is_sensitive_path("/item/1 un ion  sel ect", &patterns));
+        assert!(is_sensitive_path("/item/1+union%20select", &patterns));
+        assert!(!is_sensitive_path("/reunion/selection", &["union select".to_string(), ".env".to_string()]));
+        assert!(is_sensitive_path("/.ENV", &DEFAULT_SENSITIVE_PATHS.iter().map(|p| p.to_string()).collect::<Vec<_>>()));
+        assert_eq!(normalize_for_matching("a/* unterminated"), "a");
+    }
+
+    #[test]
+    fn test_encoded_path_traversal() {
+        let defaults: Vec<String> = DEFAULT_SENSITIVE_PATHS.iter().map(|p| p.to_string()).collect();
+
+        assert!(is_sensitive_path("/static/../../etc/shadow", &defaults));
+        assert!(is_sensitive_path("/static/..%2f..%2fetc%2fshadow", &defaults));
+        assert!(is_sensitive_path("/static/%2e%2e%2f%2E%2E%2Fetc/shadow", &defaults));
+        assert!(is_sensitive_path("/static/%252e%252e%252fetc/shadow", &defaults));
+        assert!(is_sensitive_path("/download?file=..%5c..%5cwindows%5cwin.ini", &defaults));
+        assert!(is_sensitive_path("/%2eenv", &defaults));
+        assert!(!is_sensitive_path("/docs/v1.2/guide%20intro", &defaults));
+
+        let mut log_data = LogData::new();
+        add(&mut log_data, "203.0.113.5", "/static/..%2f..%2fetc%2fpasswd");
+        add(&mut log_data, "198.51.100.7", "/static/app.js");
+        let findings = find_sensitive_file_probes(&log_data, &SecurityConfig::default(), 10);
+        assert_eq!(findings.iter().map(|f| f.target.as_str()).collect::<Vec<_>>(), vec!["/static/..%2f..%2fetc%2fpasswd"]);
+        assert_eq!(find_suspicious_ips(&log_data, &SecurityConfig::default()), vec![("203.0.113.5".to_string(), 1)]);
+    }
+
+    #[test]
+    fn test_find_suspicious_ips() {
+        let mut log_data = LogData::new();
+        add(&mut log_data, "203.0.113.5", "/.env");
+        add(&mut log_data, "203.0.113.5", "/.git/config");
+        add(&mut log_data, "203.0.113.9", "/.env");
+        add(&mut log_data, "192.168.0.1", "/index.html");
+
+        let ips = find_suspicious_ips(&log_data, &SecurityConfig::default());
+
+        assert_eq!(ips, vec![("203.0.113.5".to_string(), 2), ("203.0.113.9".to_string(), 1)]);
+    }
+
+    #[test]
+    fn test_ip_patterns() {
+        let mut log_data = LogData::new();
+        add(&mut log_data, "203.0.113.5", "/.env");
+        add(&mut log_data, "203.0.113.5", "/.env");
+        add(&mut log_data, "203.0.113.5", "/.git/config");
+        add(&mut log_data, "203.0.113.9", "/.env");
+        log_data.add_entry("203.0.113.5".to_string(), "/".to_string(), String::new(), 0, "TRACE".to_string(), "example.com".to_string(), RequestDetails::default(), false);
+
+        let patterns = ip_patterns(&log_data, &SecurityConfig::default(), "203.0.113.5");
+
+        assert_eq!(patterns, vec![
+            ("probe /.env".to_string(), 2),
+            ("method TRACE".to_string(), 1),
+            ("probe /.git/config".to_string(), 1),
+        ]);
+    }
+
+    #[test]
+    fn test_explain_ip() {
+        let mut log_data = LogData::new();
+        for (url, method) in [("/.env", "GET"), ("/", "GET"), ("/.env", "GET"), ("/", "TRACE")] {
+            let line = format!("203.0.113.5 - - \"{} {} HTTP/1.1\" 404", method, url);
+            log_data.add_entry("203.0.113.5".to_string(), url.to_string(), line, 0, method.to_string(), "example.com".to_string(), RequestDetails::default(), false);
+        }
+        add(&mut log_data, "192.168.0.1", "/");
+
+        let explanation = explain_ip(&log_data, &SecurityConfig::default(), "203.0.113.5").unwrap();
+
+        assert_eq!(explanation, Explanation {
+            ip: "203.0.113.5".to_string(),
+            total_requests: 4,
+            suspicious_requests: 3,
+            level: ThreatLevel::Low,
+            patterns: vec![("probe /.env".to_string(), 2), ("method TRACE".to_string(), 1)],
+            example_lines: vec![
+                "203.0.113.5 - - \"GET /.env HTTP/1.1\" 404".to_string(),
+                "203.0.113.5 - - \"GET /.env HTTP/1.1\" 404".to_string(),
+                "203.0.113.5 - - \"TRACE / HTTP/1.1\" 404".to_string(),
+            ],
+        });
+        assert_eq!(format_explanation(&explanation)[..6], [
+            "Threat level: LOW",
+            "Requests: 4 in total, 3 suspicious",
+            "Score: 3 suspicious requests (MEDIUM from 5, HIGH from 20)",
+            "Signals:",
+            "  - probe /.env (2)",
+            "  - method TRACE (1)",
+        ]);
+        assert_eq!(explain_ip(&log_data, &SecurityConfig::default(), "192.168.0.1"), None);
+    }
+
+    #[test]
+    fn test_format_ip_report() {
+        let patterns = vec![("probe /.env".to_string(), 2), ("method TRACE".to_string(), 1)];
+
+        let report = format_ip_report("203.0.113.5", 3, ThreatLevel::Low, &patterns);
+
+        assert_eq!(report, "IP: 203.0.113.5\nSuspicious requests: 3\nThreat level: LOW\nPatterns:\n  - probe /.env (2)\n  - method TRACE (1)\n");
+    }
+
+    #[test]
+    fn test_find_slow_clients() {
+        let mut log_data = LogData::new();
+        let mut add_timed = |ip: &str, time: f64| {
+            let details = RequestDetails { response_time: Some(time), ..Default::default() };
+            log_data.add_entry(ip.to_string(), "/".to_string(), String::new(), 0, "GET".to_string(), "example.com".to_string(), details, false);
+        };
+        for _ in 0..8 {
+            add_timed("203.0.113.5", 30.0);
+        }
+        add_timed("203.0.113.5", 0.1);
+        for _ in 0..4 {
+            add_timed("203.0.113.9", 12.0);
+        }
+        for _ in 0..20 {
+            add_timed("192.168.0.1", 0.2);
+        }
+
+        let slow = find_slow_clients(&log_data, &SecurityConfig::default(), SLOW_REQUESTS_THRESHOLD);
+
+        assert_eq!(slow, vec![("203.0.113.5".to_string(), 8)]);
+        let strict = SecurityConfig { slow_threshold: 0.15, ..Default::default() };
+        assert_eq!(find_slow_clients(&log_data, &strict, SLOW_REQUESTS_THRESHOLD), vec![("192.168.0.1".to_string(), 20), ("203.0.113.5".to_string(), 8)]);
+    }
+
+    #[test]
+    fn test_find_unusual_methods() {
+        let mut log_data = LogData::new();
+        for (ip, method) in [("203.0.113.5", "TRACE"), ("203.0.113.5", "TRACE"), ("203.0.113.9", "TRACE"), ("198.51.100.7", "DEBUG"), ("192.168.0.1", "GET"), ("192.168.0.1", "post")] {
+            log_data.add_entry(ip.to_string(), "/".to_string(), String::new(), 0, method.to_string(), "example.com".to_string(), RequestDetails::default(), false);
+        }
+
+        let findings = find_unusual_methods(&log_data, &SecurityConfig::default(), 10);
+
+        assert_eq!(findings.len(), 2);
+        assert_eq!(findings[0].target, "TRACE");
+        assert_eq!(findings[0].count, 3);
+        assert_eq!(findings[0].sources, vec![("203.0.113.5".to_string(), 2), ("203.0.113.9".to_string(), 1)]);
+        assert_eq!(findings[1].target, "DEBUG");
+        assert_eq!(find_suspicious_ips(&log_data, &SecurityConfig::default())[0], ("203.0.113.5".to_string(), 2));
+    }
+
+    #[test]
+    fn test_is_automated_method_profile() {
+        let profile = |methods: &[(&str, usize, f64)]| methods.iter().map(|&(method, count, share)| (method.to_string(), count, share)).collect::<Vec<_>>();
+        assert!(is_automated_method_profile(&profile(&[("POST", 40, 1.0)])));
+        assert!(is_automated_method_profile(&profile(&[("head", 3, 1.0)])));
+        assert!(!is_automated_method_profile(&profile(&[("GET", 40, 1.0)])));
+        assert!(!is_automated_method_profile(&profile(&[("POST", 36, 0.9), ("GET", 4, 0.1)])));
+        assert!(!is_automated_method_profile(&[]));
+    }
+
+    #[test]
+    fn test_find_body_mismatches() {
+        let mut log_data = LogData::new();
+        for (ip, method, size) in [("203.0.113.5", "GET", 500_000), ("203.0.113.5", "GET", 20_000), ("203.0.113.5", "GET", 300), ("198.51.100.7", "POST", 500_000), ("192.168.0.1", "HEAD", 8_191)] {
+            let details = RequestDetails { request_size: Some(size), ..Default::default() };
+            log_data.add_entry(ip.to_string(), "/".to_string(), String::new(), 0, method.to_string(), "example.com".to_string(), details, false);
+        }
+
+        let findings = find_body_mismatches(&log_data, 10);
+
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].target, "GET");
+        assert_eq!(findings[0].count, 2);
+        assert_eq!(findings[0].sources, vec![("203.0.113.5".to_string(), 2)]);
+        assert!(log_data.has_request_sizes());
+        assert_eq!(find_suspicious_ips(&log_data, &SecurityConfig::default()), vec![("203.0.113.5".to_string(), 2)]);
+        assert_eq!(ip_patterns(&log_data, &SecurityConfig::default(), "203.0.113.5"), vec![("large GET body".to_string(), 2)]);
+
+        let mut without_sizes = LogData::new();
+        without_sizes.add_entry("203.0.113.5".to_string(), "/".to_string(), String::new(), 0, "GET".to_string(), "example.com".to_string(), RequestDetails::default(), false);
+        assert!(find_body_mismatches(&without_sizes, 10).is_empty());
+        assert!(!without_sizes.has_request_sizes());
+    }
+
+    #[test]
+    fn test_find_cms_probers() {
+        let mut log_data = LogData::new();
+        for url in ["/wp-admin/", "/wp-admin/setup.php", "/wp-login.php", "/wp-login.php", "/administrator/index.php", "/phpMyAdmin/", "/index.html"] {
+            add(&mut log_data, "203.0.113.5", url);
+        }
+        add(&mut log_data, "198.51.100.7", "/wp-login.php");
+        add(&mut log_data, "192.168.0.1", "/blog/wp-admin-guide");
+        add(&mut log_data, "192.168.0.1", "/pmap/report");
+        add(&mut log_data, "192.168.0.1", "/user/login");
+
+        let probers = find_cms_probers(&log_data, &SecurityConfig::default(), 10);
+
+        assert_eq!(probers, vec![
+            CmsProber { ip: "203.0.113.5".to_string(), paths: 4, cms: vec!["Joomla".to_string(), "WordPress".to_string(), "phpMyAdmin".to_string()] },
+            CmsProber { ip: "198.51.100.7".to_string(), paths: 1, cms: vec!["WordPress".to_string()] },
+        ]);
+
+        let config = SecurityConfig { cms_probes: vec!["/internal-admin".to_string()], ..Default::default() };
+        add(&mut log_data, "10.0.0.9", "/internal-admin/users");
+        assert_eq!(find_cms_probers(&log_data, &config, 10), vec![
+            CmsProber { ip: "10.0.0.9".to_string(), paths: 1, cms: vec!["/internal-admin".to_string()] },
+        ]);
+    }
+
+    #[test]
+    fn test_find_referer_spam() {
+        let mut log_data = LogData::new();
+        for (ip, referer) in [("203.0.113.5", "http://semalt.com/crawler.php?u=x"), ("203.0.113.5", "https://www.semalt.com/"), ("198.51.100.7", "http://forum.darodar.com/"), ("192.168.0.1", "https://www.google.com/"), ("192.168.0.2", "https://notsemalt.com/")] {
+            let details = RequestDetails { referer: referer_host(referer), ..Default::default() };
+            log_data.add_entry(ip.to_string(), "/".to_string(), String::new(), 0, "GET".to_string(), "example.com".to_string(), details, false);
+        }
+
+        let findings = find_referer_spam(&log_data, &SecurityConfig::default(), 10);
+
+        assert_eq!(findings.len(), 2);
+        assert_eq!(findings[0].target, "semalt.com");
+        assert_eq!(findings[0].count, 2);
+        assert_eq!(findings[0].sources, vec![("203.0.113.5".to_string(), 2)]);
+        assert_eq!(findings[1].target, "forum.darodar.com");
+
+        let custom = SecurityConfig { spam_referers: vec!["Google.com".to_string()], ..Default::default() };
+        assert_eq!(find_referer_spam(&log_data, &custom, 10)[0].target, "google.com");
+    }
+
+    #[test]
+    fn test_find_referer_spoofing() {
+        let mut log_data = LogData::new();
+        for (ip, url, referer) in [
+            ("203.0.113.5", "/", Some("https://example.com.offers.ru/")),
+            ("203.0.113.5", "/pricing", Some("https://www.example.com.offers.ru/")),
+            ("192.168.0.1", "/", Some("https://blog.example.com/post")),
+            ("198.51.100.7", "/static/app.js?v=3", None),
+            ("198.51.100.7", "/static/app.js?v=3", None),
+            ("192.168.0.2", "/", None),
+        ] {
+            let details = RequestDetails { referer: referer.and_then(referer_host), empty_referer: referer.is_none(), ..Default::default() };
+            log_data.add_entry(ip.to_string(), url.to_string(), String::new(), 0, "GET".to_string(), "example.com".to_string(), details, false);
+        }
+
+        let findings = find_referer_spam(&log_data, &SecurityConfig::default(), 10);
+
+        assert_eq!(findings.iter().map(|f| (f.target.as_str(), f.count)).collect::<Vec<_>>(), vec![
+            ("/static/app.js?v=3 (asset without referer)", 2),
+            ("example.com.offers.ru (look-alike host)", 2),
+        ]);
+        assert_eq!(ip_patterns(&log_data, &SecurityConfig::default(), "203.0.113.5"), vec![("look-alike referer example.com.offers.ru".to_string(), 2)]);
+    }
+
+    #[test]
+    fn test_threat_filter() {
+        assert_eq!(get_threat_level(1), ThreatLevel::Low);
+        assert_eq!(get_threat_level(5), ThreatLevel::Medium);
+        assert_eq!(get_threat_level(20), ThreatLevel::High);
+
+        assert!(ThreatFilter::All.allows(ThreatLevel::Low));
+        assert!(ThreatFilter::High.allows(ThreatLevel::High));
+        assert!(!ThreatFilter::High.allows(ThreatLevel::Medium));
+        assert!(ThreatFilter::MediumPlus.allows(ThreatLevel::Medium));
+        assert!(ThreatFilter::MediumPlus.allows(ThreatLevel::High));
+        assert!(!ThreatFilter::MediumPlus.allows(ThreatLevel::Low));
+        assert_eq!(ThreatFilter::All.next().next().next(), ThreatFilter::All);
+    }
+
+    #[test]
+    fn test_find_cache_busting() {
+        let mut log_data = LogData::new();
+        for i in 0..100 {
+            let details = RequestDetails { query: Some(format!("_={}", i * 7919 % 100_003)), ..Default::default() };
+            log_data.add_entry("203.0.113.5".to_string(), "/products".to_string(), String::new(), 0, "GET".to_string(), "example.com".to_string(), details, false);
+        }
+        for i in 0..60 {
+            let details = RequestDetails { query: Some(format!("page={}", i)), ..Default::default() };
+            log_data.add_entry(format!("198.51.100.{}", i), "/search".to_string(), String::new(), 0, "GET".to_string(), "example.com".to_string(), details, false);
+        }
+
+        let findings = find_cache_busting(&log_data, &SecurityConfig::default(), 10);
+
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].target, "/products");
+        assert_eq!(findings[0].count, 100);
+        assert_eq!(findings[0].sources, vec![("203.0.113.5".to_string(), 100)]);
+
+        let strict = SecurityConfig { cache_busting_queries: 101, ..Default::default() };
+        assert!(find_cache_busting(&log_data, &strict, 10).is_empty());
+    }
+
+    #[test]
+    fn test_find_repeating_ips() {
+        let mut log_data = LogData::new();
+        for (ip, key, times) in [("203.0.113.5", 1, 12), ("203.0.113.5", 2, 3), ("198.51.100.7", 1, 4), ("192.168.0.1", 3, 1)] {
+            for _ in 0..times {
+                let details = RequestDetails { repeat_key: Some(key), ..Default::default() };
+                log_data.add_entry(ip.to_string(), "/login".to_string(), String::new(), 0, "GET".to_string(), "example.com".to_string(), details, false);
+            }
+        }
+
+        assert_eq!(find_repeating_ips(&log_data, 4), vec![("203.0.113.5".to_string(), 12), ("198.51.100.7".to_string(), 4)]);
+        assert_eq!(find_repeating_ips(&log_data, REPEAT_THRESHOLD), vec![("203.0.113.5".to_string(), 12)]);
+    }
+
+    #[test]
+    fn test_find_credential_stuffing() {
+        let mut log_data = LogData::new();
+        // A burst of 12 failed logins in 33 seconds, and as many spread over two hours.
+        for i in 0..12 {
+            fail(&mut log_data, "203.0.113.5", "/login", 1_700_000_000 + i * 3, 401);
+            fail(&mut log_data, "198.51.100.7", "/login", 1_700_000_000 + i * 600, 401);
+        }
+        fail(&mut log_data, "203.0.113.5", "/signin", 1_700_000_040, 403);
+        // 403s outside the auth endpoints are not login failures.
+        for i in 0..20 {
+            fail(&mut log_data, "192.0.2.9", "/admin/backup.zip", 1_700_000_000 + i, 403);
+        }
+
+        let config = SecurityConfig::default();
+        let stuffers = find_credential_stuffers(&log_data, &config);
+        assert_eq!(stuffers.into_iter().collect::<Vec<_>>(), vec![("203.0.113.5".to_string(), 13)]);
+        let findings = find_credential_stuffing(&log_data, &config, 10);
+        assert_eq!(findings.iter().map(|f| (f.target.as_str(), f.count)).collect::<Vec<_>>(), vec![("/login", 13), ("/signin", 1)]);
+        assert_eq!(findings[0].sources, vec![("198.51.100.7".to_string(), 12), ("203.0.113.5".to_string(), 12)]);
+        assert_eq!(find_suspicious_ips(&log_data, &config), vec![("203.0.113.5".to_string(), 13)]);
+        assert_eq!(ip_patterns(&log_data, &config, "203.0.113.5"), vec![("auth failures /login".to_string(), 12), ("auth failures /signin".to_string(), 1)]);
+
+        // Stuffing spread over many IPs, one attempt each, only trips the endpoint threshold.
+        let config = SecurityConfig { auth_endpoint_threshold: 5, ..Default::default() };
+        for i in 0..6 {
+            fail(&mut log_data, &format!("203.0.113.{}", 100 + i), "/api/auth/token", 1_700_001_000 + i, 401);
+        }
+        let findings = find_credential_stuffing(&log_data, &config, 10);
+        assert!(findings.iter().any(|f| f.target == "/api/auth/token" && f.count == 6));
+        assert!(!find_credential_stuffers(&log_data, &config).contains_key("203.0.113.100"));
+        assert!(!log_data.auth_failures.contains_key("/admin/backup.zip"));
+    }
+
+    #[test]
+    fn test_is_auth_endpoint() {
+        let endpoints = vec!["/auth".to_string(), "/session/".to_string(), "/wp-login.php".to_string()];
+
+        assert!(is_auth_endpoint("/auth", &endpoints));
+        assert!(is_auth_endpoint("/Auth/token", &endpoints));
+        assert!(is_auth_endpoint("/session", &endpoints));
+        assert!(is_auth_endpoint("/wp-login.php?action=login", &endpoints));
+        assert!(!is_auth_endpoint("/author/jane", &endpoints));
+        assert!(!is_auth_endpoint("/sessions", &endpoints));
+        assert!(!is_auth_endpoint("/api/auth", &endpoints));
+    }
+
+    #[test]
+    fn test_format_deny_list() {
+        let ips = vec![("203.0.113.5".to_string(), 3), ("2001:db8::1".to_string(), 2), ("1.2.3.4; rm -rf /".to_string(), 5), ("198.51.100.7".to_string(), 1)];
+
+        assert_eq!(format_deny_list(&ips, DenyFormat::Iptables, 2), "iptables -A INPUT -s 203.0.113.5 -j DROP\nip6tables -A INPUT -s 2001:db8::1 -j DROP\n");
+        assert_eq!(format_deny_list(&ips, DenyFormat::Nginx, 2), "deny 203.0.113.5;\ndeny 2001:db8::1;\n");
+        assert_eq!(format_deny_list(&ips, DenyFormat::Plain, 1), "203.0.113.5\n2001:db8::1\n198.51.100.7\n");
+        assert_eq!("nginx".parse::<DenyFormat>(), Ok(DenyFormat::Nginx));
+        assert!("ufw".parse::<DenyFormat>().is_err());
+    }
+
+    #[test]
+    fn test_sensitive_paths_are_configurable() {
+        let config = SecurityConfig { sensitive_paths: vec![".sql".to_string()], ..Default::default() };
+
+        assert!(is_sensitive_path("/backup/DB.SQL", &config.sensitive_paths));
+        assert!(!is_sensitive_path("/index.php", &config.sensitive_paths));
+    }
+}
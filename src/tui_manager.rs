@@ -1,16 +1,66 @@
+use ratatui::buffer::Buffer;
 use ratatui::prelude::{Color, Modifier, Style};
 use ratatui::widgets::{Block, Borders, Gauge, List, ListItem, Paragraph, Scrollbar, ScrollbarOrientation, ScrollbarState, Sparkline, Tabs};
 use ratatui::widgets::canvas::{Canvas, Rectangle};
-use ratatui::layout::{Rect};
+use ratatui::layout::{Alignment, Constraint, Direction, Layout, Rect};
 use ratatui::Frame;
 use ratatui::symbols::Marker;
 use ratatui::text::Line;
+use std::io::Write;
+use base64::Engine;
 
 pub struct TuiManager;
 
 // pub const NORMAL_ROW_BG: Color = Color::Rgb(18, 18, 20);
 pub const SELECTED_STYLE: Style = Style::new().bg(Color::Rgb(0, 31, 63)).add_modifier(Modifier::BOLD);
 pub const TEXT_FG_COLOR: Color = Color::Rgb(158, 158, 158);
+/// Background of Overview rows whose count just went up.
+pub const CHANGED_STYLE: Style = Style::new().bg(Color::Rgb(48, 48, 0));
+
+/// Color used for an HTTP status everywhere in the UI: 2xx green, 3xx cyan, 4xx yellow, 5xx red.
+pub fn status_color(status: Option<u16>) -> Color {
+    match status {
+        Some(200..=299) => Color::Green,
+        Some(300..=399) => Color::Cyan,
+        Some(400..=499) => Color::Yellow,
+        Some(500..=599) => Color::Red,
+        _ => TEXT_FG_COLOR,
+    }
+}
+
+/// ASCII stand-in of a symbol drawn on screen, for terminals that show Unicode glyphs as tofu
+/// (`--ascii`): borders, bar and heatmap blocks, arrows, dashes and ellipses get a lookalike and
+/// any other non-ASCII symbol, log data included, becomes `?`.
+pub fn ascii_glyph(symbol: &str) -> &str {
+    if symbol.is_ascii() {
+        return symbol;
+    }
+    match symbol {
+        "─" | "━" | "═" | "—" | "–" => "-",
+        "│" | "┃" | "║" => "|",
+        "┌" | "┐" | "└" | "┘" | "╭" | "╮" | "╰" | "╯" | "┏" | "┓" | "┗" | "┛" | "╔" | "╗" | "╚" | "╝" | "├" | "┤" | "┬" | "┴" | "┼" => "+",
+        "░" | "▁" | "▂" | "▃" | "…" | "·" => ".",
+        "▒" => ":",
+        "▓" => "+",
+        "█" | "▇" | "▆" | "▅" | "▄" | "▀" | "▌" | "▐" => "#",
+        "•" => "*",
+        "↑" => "^",
+        "↓" => "v",
+        "→" => ">",
+        "←" => "<",
+        _ => "?",
+    }
+}
+
+/// Replaces every symbol of a drawn frame with its `ascii_glyph`.
+pub fn to_ascii(buffer: &mut Buffer) {
+    for cell in buffer.content.iter_mut() {
+        if !cell.symbol().is_ascii() {
+            let glyph = ascii_glyph(cell.symbol()).to_string();
+            cell.set_symbol(&glyph);
+        }
+    }
+}
 
 impl TuiManager {
     pub fn new() -> Self {
@@ -25,9 +75,9 @@ impl TuiManager {
             .divider("|")
     }
 
-    pub fn draw_summary<'a>(&self, summary: &'a str) -> Paragraph<'a> {
+    pub fn draw_summary<'a>(&self, summary: &'a str, title: String) -> Paragraph<'a> {
         Paragraph::new(summary)
-            .block(Block::default().borders(Borders::ALL).title("Summary"))
+            .block(Block::default().borders(Borders::ALL).title(title))
     }
 
     // pub fn draw_table<'a>(&self, rows: Vec<Row<'a>>, headers: Vec<&'a str>, title: &'a str, constraints: &'a [Constraint]) -> Table<'a> {
@@ -42,6 +92,13 @@ impl TuiManager {
             .block(Block::default().borders(Borders::ALL).title("Search"))
     }
 
+    /// Input line of a prompt, red while `valid` is false.
+    pub fn draw_prompt<'a>(&self, title: &'a str, input: &'a str, valid: bool) -> Paragraph<'a> {
+        Paragraph::new(input)
+            .style(Style::default().fg(if valid { Color::Yellow } else { Color::Red }))
+            .block(Block::default().borders(Borders::ALL).title(title))
+    }
+
     pub fn draw_list<'a>(&self, items: Vec<ListItem<'a>>, title: String) -> List<'a> {
         List::new(items)
             .block(Block::default().borders(Borders::ALL).style(Style::default()).title(title))
@@ -49,7 +106,7 @@ impl TuiManager {
             // .highlight_symbol(">")
     }
 
-    pub fn draw_progress_bar(&self, progress: f64) -> Gauge {
+    pub fn draw_progress_bar(&self, progress: f64) -> Gauge<'_> {
         Gauge::default()
             .block(Block::default().borders(Borders::ALL).title("Loading Progress"))
             .gauge_style(Style::default().fg(Color::Green).bg(Color::Black).add_modifier(Modifier::ITALIC))
@@ -93,6 +150,29 @@ impl TuiManager {
             })
     }
 
+    /// Puts `text` on the system clipboard through the OSC 52 escape sequence, which works
+    /// over SSH and in most terminal emulators without any platform clipboard library.
+    pub fn copy_to_clipboard(&self, text: &str) -> std::io::Result<()> {
+        let encoded = base64::engine::general_purpose::STANDARD.encode(text);
+        let mut stdout = std::io::stdout();
+        write!(stdout, "\x1b]52;c;{}\x07", encoded)?;
+        stdout.flush()
+    }
+
+    pub fn draw_message(&self, message: &str, frame: &mut Frame, area: Rect) {
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Percentage(40), Constraint::Length(3), Constraint::Min(0)])
+            .split(area);
+        frame.render_widget(
+            Paragraph::new(message.to_string())
+                .alignment(Alignment::Center)
+                .style(Style::default().fg(TEXT_FG_COLOR))
+                .block(Block::default().borders(Borders::ALL)),
+            rows[1],
+        );
+    }
+
     pub fn draw_scrollbar(&self, count: usize, selected_index: usize, frame: &mut Frame, rect: Rect) {
         let mut scrollbar_state = ScrollbarState::default()
             .content_length(count)
@@ -106,3 +186,29 @@ impl TuiManager {
         );
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_color() {
+        assert_eq!(status_color(Some(200)), Color::Green);
+        assert_eq!(status_color(Some(204)), Color::Green);
+        assert_eq!(status_color(Some(301)), Color::Cyan);
+        assert_eq!(status_color(Some(404)), Color::Yellow);
+        assert_eq!(status_color(Some(503)), Color::Red);
+        assert_eq!(status_color(Some(101)), TEXT_FG_COLOR);
+        assert_eq!(status_color(None), TEXT_FG_COLOR);
+    }
+
+    #[test]
+    fn test_ascii_glyph() {
+        assert_eq!(ascii_glyph("a"), "a");
+        assert_eq!(ascii_glyph("┌"), "+");
+        assert_eq!(ascii_glyph("─"), "-");
+        assert_eq!(ascii_glyph("▓"), "+");
+        assert_eq!(ascii_glyph("↓"), "v");
+        assert_eq!(ascii_glyph("é"), "?");
+    }
+}
@@ -0,0 +1,133 @@
+use std::collections::HashMap;
+use std::io::BufRead;
+use chrono::NaiveDateTime;
+use crate::helpers::{is_bot, referer_host};
+use crate::log_data::RequestDetails;
+
+/// Columns a `#Fields:` directive must declare for the requests to be usable.
+pub const REQUIRED_FIELDS: &[&str] = &["date", "time", "c-ip", "cs-method", "cs-uri-stem"];
+
+/// Column layout of a W3C extended log (IIS and other servers), from its `#Fields:` directive.
+#[derive(Debug, Clone, PartialEq)]
+pub struct W3cFields {
+    columns: HashMap<String, usize>,
+}
+
+/// One row of a W3C log, mapped to the fields a regex would capture.
+#[derive(Debug, PartialEq)]
+pub struct W3cRecord {
+    pub ip: String,
+    /// `date time` as logged, in UTC as the format requires.
+    pub datetime: String,
+    pub domain: String,
+    pub method: String,
+    pub url: String,
+    pub xff: Option<String>,
+    pub details: RequestDetails,
+}
+
+impl W3cFields {
+    /// Reads `#Fields: date time c-ip ...`, failing when a `REQUIRED_FIELDS` column is missing.
+    pub fn parse(directive: &str) -> Result<Self, String> {
+        let names = directive.strip_prefix("#Fields:").ok_or_else(|| "expected a '#Fields:' directive".to_string())?;
+        let columns: HashMap<String, usize> = names.split_whitespace().enumerate().map(|(index, name)| (name.to_lowercase(), index)).collect();
+        let missing: Vec<&str> = REQUIRED_FIELDS.iter().copied().filter(|field| !columns.contains_key(*field)).collect();
+        if !missing.is_empty() {
+            return Err(format!("the #Fields directive has no {} column", missing.join(", ")));
+        }
+        Ok(W3cFields { columns })
+    }
+
+    /// Splits a data row on spaces and maps it by column name. `-` is an empty value and IIS writes
+    /// the spaces of user agents as `+`. `time-taken` is read as milliseconds, as IIS logs it.
+    pub fn parse_line(&self, line: &str) -> Option<W3cRecord> {
+        let values: Vec<&str> = line.split_whitespace().collect();
+        let field = |name: &str| self.columns.get(name).and_then(|&index| values.get(index)).copied().filter(|value| *value != "-");
+        let required = |name: &str| field(name).map(str::to_string);
+
+        let user_agent = field("cs(user-agent)").map(|ua| ua.replace('+', " "));
+        Some(W3cRecord {
+            ip: required("c-ip")?,
+            datetime: format!("{} {}", field("date")?, field("time")?),
+            domain: field("cs-host").or_else(|| field("s-sitename")).unwrap_or("-").to_string(),
+            method: required("cs-method")?,
+            url: required("cs-uri-stem")?,
+            xff: field("x-forwarded-for").or_else(|| field("cs(x-forwarded-for)")).map(str::to_string),
+            details: RequestDetails {
+                response_size: field("sc-bytes").and_then(|size| size.parse().ok()),
+                request_size: field("cs-bytes").and_then(|size| size.parse().ok()),
+                response_time: field("time-taken").and_then(|millis| millis.parse::<f64>().ok()).map(|millis| millis / 1000.0),
+                query: field("cs-uri-query").map(str::to_string),
+                status: field("sc-status").and_then(|status| status.parse().ok()),
+                is_bot: user_agent.as_deref().is_some_and(is_bot),
+                user_agent,
+                referer: field("cs(referer)").and_then(referer_host),
+                empty_referer: self.columns.get("cs(referer)").and_then(|&index| values.get(index)) == Some(&"-"),
+                repeat_key: None,
+            },
+        })
+    }
+}
+
+/// The `#Fields:` directive from the header lines (`#Software:`, `#Date:`...) at the top of a log.
+pub fn read_fields(reader: &mut impl BufRead) -> std::io::Result<W3cFields> {
+    let invalid = |message: String| std::io::Error::new(std::io::ErrorKind::InvalidData, message);
+    let mut line = String::new();
+    while reader.read_line(&mut line)? > 0 && line.starts_with('#') {
+        if line.starts_with("#Fields:") {
+            return W3cFields::parse(line.trim_end()).map_err(invalid);
+        }
+        line.clear();
+    }
+    Err(invalid("no #Fields directive before the first request".to_string()))
+}
+
+/// Seconds since the epoch of a `date time` pair such as `2024-07-16 10:00:00`.
+pub fn parse_timestamp(datetime: &str) -> Result<i64, chrono::ParseError> {
+    NaiveDateTime::parse_from_str(datetime, "%Y-%m-%d %H:%M:%S%.f").map(|datetime| datetime.and_utc().timestamp())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HEADER: &str = "#Software: Microsoft Internet Information Services 10.0\n#Version: 1.0\n#Date: 2024-07-16 10:00:00\n\
+        #Fields: date time s-ip cs-method cs-uri-stem cs-uri-query s-port cs-username c-ip cs(User-Agent) cs(Referer) sc-status sc-substatus sc-win32-status sc-bytes time-taken\n";
+
+    #[test]
+    fn test_read_fields_and_rows() {
+        let log = format!("{}2024-07-16 10:00:01 10.0.0.5 GET /index.html q=1 443 - 203.0.113.7 Mozilla/5.0+(Windows+NT+10.0) https://example.com/ 200 0 0 5120 250\n", HEADER);
+        let mut reader = log.as_bytes();
+
+        let fields = read_fields(&mut reader).unwrap();
+        let mut row = String::new();
+        reader.read_line(&mut row).unwrap();
+        let record = fields.parse_line(&row).unwrap();
+
+        assert_eq!(record, W3cRecord {
+            ip: "203.0.113.7".to_string(),
+            datetime: "2024-07-16 10:00:01".to_string(),
+            domain: "-".to_string(),
+            method: "GET".to_string(),
+            url: "/index.html".to_string(),
+            xff: None,
+            details: RequestDetails {
+                response_size: Some(5120),
+                response_time: Some(0.25),
+                query: Some("q=1".to_string()),
+                status: Some(200),
+                user_agent: Some("Mozilla/5.0 (Windows NT 10.0)".to_string()),
+                referer: Some("example.com".to_string()),
+                ..RequestDetails::default()
+            },
+        });
+        assert_eq!(parse_timestamp(&record.datetime), Ok(1_721_124_001));
+        assert_eq!(fields.parse_line("2024-07-16 10:00:01 10.0.0.5"), None);
+    }
+
+    #[test]
+    fn test_fields_directive_errors() {
+        assert_eq!(W3cFields::parse("#Fields: date time c-ip"), Err("the #Fields directive has no cs-method, cs-uri-stem column".to_string()));
+        assert!(read_fields(&mut "#Version: 1.0\n2024-07-16 10:00:01 GET /\n".as_bytes()).is_err());
+    }
+}
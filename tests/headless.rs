@@ -0,0 +1,94 @@
+use std::fs;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+const LOG_LINE: &str = "192.168.0.1 - \"-\" [16/Jul/2024:10:00:00 +0000] 0.120 \"example.com\" \"GET /page HTTP/1.1\" 200 512\n";
+
+fn write_fixture(name: &str, data: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("logutil_{}_{}", std::process::id(), name));
+    fs::write(&path, data).unwrap();
+    path
+}
+
+fn exit_code(args: &[&str]) -> i32 {
+    let output = Command::new(env!("CARGO_BIN_EXE_logutil")).args(args).output().unwrap();
+    output.status.code().unwrap()
+}
+
+#[test]
+fn test_exit_success() {
+    let path = write_fixture("exit_success.log", LOG_LINE);
+    assert_eq!(exit_code(&[path.to_str().unwrap(), "--interval-summary"]), 0);
+    assert_eq!(exit_code(&[path.to_str().unwrap(), "--export-deny", "plain", "--fail-on-empty"]), 0);
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_exit_invalid_args() {
+    let path = write_fixture("exit_invalid_args.log", LOG_LINE);
+    assert_eq!(exit_code(&[path.to_str().unwrap(), "--interval-summary", "0"]), 1);
+    assert_eq!(exit_code(&[path.to_str().unwrap(), "--interval-summary", "--only-status", "6xx"]), 1);
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_exit_invalid_regex() {
+    let path = write_fixture("exit_invalid_regex.log", LOG_LINE);
+    assert_eq!(exit_code(&[path.to_str().unwrap(), "--interval-summary", "--regex", "(unclosed"]), 2);
+    assert_eq!(exit_code(&[path.to_str().unwrap(), "--interval-summary", "--regex", r"^(\S+) (\S+)"]), 2);
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_exit_unreadable_file() {
+    let path = std::env::temp_dir().join(format!("logutil_{}_missing.log", std::process::id()));
+    assert_eq!(exit_code(&[path.to_str().unwrap(), "--export-deny", "plain"]), 3);
+}
+
+#[test]
+fn test_exit_no_matches() {
+    let path = write_fixture("exit_no_matches.log", "not an access log line\n");
+    assert_eq!(exit_code(&[path.to_str().unwrap(), "--interval-summary"]), 0);
+    assert_eq!(exit_code(&[path.to_str().unwrap(), "--interval-summary", "--fail-on-empty"]), 4);
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_wait_for_file() {
+    let path = std::env::temp_dir().join(format!("logutil_{}_delayed.log", std::process::id()));
+    let _ = fs::remove_file(&path);
+    assert_eq!(exit_code(&[path.to_str().unwrap(), "--interval-summary"]), 3);
+
+    let child = Command::new(env!("CARGO_BIN_EXE_logutil"))
+        .args([path.to_str().unwrap(), "--interval-summary", "--wait-for-file", "--watch-interval", "20"])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    std::thread::sleep(Duration::from_millis(200));
+    fs::write(&path, LOG_LINE).unwrap();
+
+    let output = child.wait_with_output().unwrap();
+    assert_eq!(output.status.code(), Some(0));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Waiting for"));
+    fs::remove_file(path).unwrap();
+}
+
+/// The TUI needs a terminal, so the binary runs under `script`, which gives it a pseudo-terminal.
+#[cfg(target_os = "linux")]
+#[test]
+fn test_run_for() {
+    let path = write_fixture("run_for.log", LOG_LINE);
+    let command = format!("{} {} --count=-1 --run-for 2 --watch-interval 50", env!("CARGO_BIN_EXE_logutil"), path.display());
+    let started = std::time::Instant::now();
+
+    let output = Command::new("script").args(["-qec", &command, "/dev/null"]).stdin(Stdio::null()).output().unwrap();
+
+    let elapsed = started.elapsed();
+    assert_eq!(output.status.code(), Some(0), "{}", String::from_utf8_lossy(&output.stdout));
+    assert!(elapsed >= Duration::from_secs(2) && elapsed < Duration::from_secs(5), "exited after {:?}", elapsed);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Requests: 1 | Unique IPs: 1"));
+    assert_eq!(exit_code(&[path.to_str().unwrap(), "--run-for", "0"]), 1);
+    fs::remove_file(path).unwrap();
+}